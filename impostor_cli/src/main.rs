@@ -1,19 +1,175 @@
 mod log_middleware;
 
-use std::net::SocketAddrV4;
+use std::{
+    net::SocketAddrV4,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 
-use impostor_compiler_axum::compile;
+use impostor_compiler_axum::{
+    admin_router, check_openapi, export_hurl, export_openapi, import_har, import_openapi,
+    record_router, run_tests, Mocks, Recording, Upstream,
+};
+use impostor_core::printer::print_impostor_file;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const DEFAULT_PORT: u16 = 3939;
 
 #[derive(Parser, Debug)]
 #[clap(name = "Impostor", version = "0.1.0", author = "Impostor Contributors")]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    #[clap(flatten)]
+    serve: ServeArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Proxy requests to a server, and write what it responds with as an
+    /// Impostor file when stopped with Ctrl-C
+    Record(RecordArgs),
+    /// Generate an Impostor file from another format
+    #[clap(subcommand)]
+    Import(ImportCommand),
+    /// Convert an Impostor file to another format
+    #[clap(subcommand)]
+    Export(ExportCommand),
+    /// Check that the entries of an Impostor file match an API description
+    Check(CheckArgs),
+    /// Run the Hurl-style tests of Impostor files against their mocks,
+    /// without a server
+    Test(TestArgs),
+}
+
+#[derive(Subcommand, Debug)]
+enum ImportCommand {
+    /// Generate mocks from the examples of an OpenAPI 3 document, in YAML or
+    /// JSON
+    Openapi(ImportArgs),
+    /// Generate mocks from the requests and responses of a HAR file, as
+    /// exported by browser devtools and proxies
+    Har(ImportHarArgs),
+}
+
+#[derive(Subcommand, Debug)]
+enum ExportCommand {
+    /// Describe the entries of an Impostor file as an OpenAPI 3 document, in
+    /// YAML
+    Openapi(ExportOpenapiArgs),
+    /// Generate a Hurl file that tests a server running the entries of an
+    /// Impostor file
+    Hurl(ExportHurlArgs),
+}
+
+#[derive(Args, Debug)]
+struct ExportOpenapiArgs {
     /// Path to an Impostor mock file
-    path_to_file: String,
+    path: PathBuf,
+
+    /// Path to write the document to, instead of printing it
+    #[clap(short, long)]
+    out: Option<PathBuf>,
+
+    /// Title of the API, defaults to the name of the file
+    #[clap(long)]
+    title: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct CheckArgs {
+    /// Path to an Impostor mock file
+    path: PathBuf,
+
+    /// Path to an OpenAPI 3 document, in YAML or JSON, to check the entries
+    /// against
+    #[clap(long)]
+    openapi: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct TestArgs {
+    /// Path to an Impostor mock file, or a directory of them. The tests of
+    /// `mocks.impostor` are in `mocks_test.hurl` or `mocks.hurl`, next to it
+    path: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct ExportHurlArgs {
+    /// Path to an Impostor mock file
+    path: PathBuf,
+
+    /// Path to write the Hurl file to, instead of printing it
+    #[clap(short, long)]
+    out: Option<PathBuf>,
+
+    /// URL of the server the requests are sent to
+    #[clap(long, default_value = "http://localhost:3939")]
+    base_url: String,
+}
+
+#[derive(Args, Debug)]
+struct ImportArgs {
+    /// Path to the file to import
+    path: PathBuf,
+
+    /// Path to write the Impostor file to, instead of printing it
+    #[clap(short, long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct ImportHarArgs {
+    #[clap(flatten)]
+    import: ImportArgs,
+
+    /// Only import requests to this host, e.g. api.example.com or
+    /// localhost:8080. Can be given more than once.
+    #[clap(long = "host", value_name = "HOST")]
+    hosts: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct ServeArgs {
+    /// Path to an Impostor mock file
+    #[clap(required = true)]
+    path_to_file: Option<String>,
+
+    /// Port to listen on, defaults to 3939
+    #[clap(short, long)]
+    port: Option<u16>,
+
+    /// Port to serve the admin API on. The admin API is disabled unless this
+    /// is set.
+    #[clap(long)]
+    admin_port: Option<u16>,
+
+    /// Explain why no entry matched a request in the body of its 404
+    /// response. The explanation is always logged at debug level.
+    #[clap(long = "verbose-404")]
+    verbose_404: bool,
+
+    /// URL of a server to forward requests that no entry matched to, e.g.
+    /// http://localhost:8080 or https://api.example.com, instead of responding
+    /// with a 404
+    #[clap(long, value_name = "URL")]
+    proxy_unmatched: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct RecordArgs {
+    /// URL of the server to record, e.g. http://localhost:8080 or
+    /// https://api.example.com
+    #[clap(long)]
+    upstream: String,
+
+    /// Path to write the recorded Impostor file to
+    #[clap(long)]
+    out: PathBuf,
 
     /// Port to listen on, defaults to 3939
     #[clap(short, long)]
@@ -26,19 +182,189 @@ async fn main() -> eyre::Result<()> {
 
     log::info!("Impostor v{}", VERSION);
 
-    let args = Cli::parse();
-    let contents = std::fs::read_to_string(&args.path_to_file)?;
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Record(args)) => record(args).await,
+        Some(Command::Import(ImportCommand::Openapi(args))) => {
+            let contents = std::fs::read_to_string(&args.path)?;
+            write_output(&print_impostor_file(&import_openapi(&contents)?), args.out)
+        }
+        Some(Command::Import(ImportCommand::Har(args))) => {
+            let contents = std::fs::read_to_string(&args.import.path)?;
+            let file = import_har(&contents, &args.hosts)?;
+            write_output(&print_impostor_file(&file), args.import.out)
+        }
+        Some(Command::Export(ExportCommand::Openapi(args))) => {
+            let contents = std::fs::read_to_string(&args.path)?;
+            let title = args.title.unwrap_or_else(|| {
+                args.path
+                    .file_stem()
+                    .map_or("Impostor".to_string(), |stem| {
+                        stem.to_string_lossy().into_owned()
+                    })
+            });
+            write_output(&export_openapi(&contents, &title)?, args.out)
+        }
+        Some(Command::Export(ExportCommand::Hurl(args))) => {
+            let contents = std::fs::read_to_string(&args.path)?;
+            write_output(&export_hurl(&contents, &args.base_url)?, args.out)
+        }
+        Some(Command::Check(args)) => check(args),
+        Some(Command::Test(args)) => test(args).await,
+        None => serve(cli.serve).await,
+    }
+}
+
+async fn serve(args: ServeArgs) -> eyre::Result<()> {
+    let path_to_file = args.path_to_file.expect("clap requires a path");
+    let mocks = Mocks::from_file(&path_to_file)?;
+    mocks.state().set_verbose_not_found(args.verbose_404);
+    if let Some(url) = &args.proxy_unmatched {
+        mocks.state().set_proxy_unmatched(Some(Upstream::new(url)?));
+        log::info!("Forwarding unmatched requests to {}", url);
+    }
 
-    let compiled =
-        compile(&contents)?.layer(axum::middleware::from_fn(log_middleware::log_middleware));
+    let compiled = mocks
+        .router()
+        .layer(axum::middleware::from_fn(log_middleware::log_middleware));
 
-    log::info!("Loaded file {}", &args.path_to_file);
+    log::info!("Loaded file {}", &path_to_file);
+
+    if let Some(admin_port) = args.admin_port {
+        let address = SocketAddrV4::new(std::net::Ipv4Addr::new(127, 0, 0, 1), admin_port);
+        let listener = tokio::net::TcpListener::bind(address).await?;
+        tokio::spawn(impostor_compiler_axum::serve(listener, admin_router(mocks)));
+        log::info!("Serving the admin API on port {}", admin_port);
+    }
 
     let port = args.port.unwrap_or(DEFAULT_PORT);
     let address = SocketAddrV4::new(std::net::Ipv4Addr::new(127, 0, 0, 1), port);
 
     let listener = tokio::net::TcpListener::bind(address).await.unwrap();
-    axum::serve(listener, compiled).await?;
+    impostor_compiler_axum::serve(listener, compiled).await;
+
+    Ok(())
+}
+
+async fn record(args: RecordArgs) -> eyre::Result<()> {
+    let recording = Arc::new(Recording::new(Upstream::new(&args.upstream)?));
+    let router = record_router(recording.clone())
+        .layer(axum::middleware::from_fn(log_middleware::log_middleware));
+
+    let port = args.port.unwrap_or(DEFAULT_PORT);
+    let address = SocketAddrV4::new(std::net::Ipv4Addr::new(127, 0, 0, 1), port);
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    log::info!(
+        "Recording {} on port {}, press Ctrl-C to stop",
+        &args.upstream,
+        port
+    );
+
+    tokio::select! {
+        _ = impostor_compiler_axum::serve(listener, router) => {}
+        result = tokio::signal::ctrl_c() => result?,
+    }
+
+    std::fs::write(&args.out, recording.to_impostor())?;
+    log::info!(
+        "Wrote {} entries to {}",
+        recording.len(),
+        args.out.display()
+    );
+
+    Ok(())
+}
+
+fn check(args: CheckArgs) -> eyre::Result<()> {
+    let contents = std::fs::read_to_string(&args.path)?;
+    let spec = std::fs::read_to_string(&args.openapi)?;
+    let violations = check_openapi(&contents, &spec)?;
+    for violation in &violations {
+        println!("{}:{}", args.path.display(), violation);
+    }
+    if !violations.is_empty() {
+        eyre::bail!(
+            "{} doesn't match {}: {} problem(s)",
+            args.path.display(),
+            args.openapi.display(),
+            violations.len()
+        );
+    }
+    log::info!("{} matches {}", args.path.display(), args.openapi.display());
+    Ok(())
+}
+
+async fn test(args: TestArgs) -> eyre::Result<()> {
+    let mock_files = if args.path.is_dir() {
+        let mut paths = vec![];
+        for dir_entry in std::fs::read_dir(&args.path)? {
+            let path = dir_entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "impostor")
+            {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        paths
+    } else {
+        vec![args.path.clone()]
+    };
+
+    let (mut passed, mut failed) = (0, 0);
+    for mock_file in mock_files {
+        let Some(test_file) = test_file(&mock_file) else {
+            log::warn!("No tests for {}", mock_file.display());
+            continue;
+        };
+        let router = Mocks::from_file(&mock_file)?.router();
+        let tests = std::fs::read_to_string(&test_file)?;
+        for result in run_tests(router, &tests).await? {
+            let location = format!("{}:{}", test_file.display(), result.line);
+            if result.passed() {
+                passed += 1;
+                println!("ok   {} {} ({})", result.method, result.url, location);
+                continue;
+            }
+            failed += 1;
+            println!("FAIL {} {} ({})", result.method, result.url, location);
+            for failure in &result.failures {
+                for line in failure.to_string().lines() {
+                    println!("    {}", line);
+                }
+            }
+        }
+    }
+
+    println!("{} passed, {} failed", passed, failed);
+    if passed + failed == 0 {
+        eyre::bail!("no tests found for {}", args.path.display());
+    }
+    if failed > 0 {
+        eyre::bail!("{} test(s) failed", failed);
+    }
+    Ok(())
+}
+
+/// Find the tests of an Impostor file: `mocks_test.hurl` or `mocks.hurl` for
+/// `mocks.impostor`.
+fn test_file(mock_file: &Path) -> Option<PathBuf> {
+    let stem = mock_file.file_stem()?.to_string_lossy();
+    [format!("{}_test.hurl", stem), format!("{}.hurl", stem)]
+        .into_iter()
+        .map(|name| mock_file.with_file_name(name))
+        .find(|path| path.is_file())
+}
 
+/// Write the output of a command to a file, or print it if there's no file.
+fn write_output(contents: &str, out: Option<PathBuf>) -> eyre::Result<()> {
+    match out {
+        Some(out) => {
+            std::fs::write(&out, contents)?;
+            log::info!("Wrote {}", out.display());
+        }
+        None => print!("{}", contents),
+    }
     Ok(())
 }
@@ -0,0 +1,288 @@
+use axum::{
+    extract::{Path, Query, State as StateExtractor},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{error, mocks::Mocks, state::Position};
+
+/// Build a router for the admin API of a set of mocks.
+///
+/// The admin API lets tests and humans see what a running mock server is
+/// doing, and control it. Every endpoint responds with JSON:
+///
+/// - `GET /entries` lists the loaded entries, where they are in their file,
+///   and how many requests they've handled.
+/// - `GET /scenarios` shows the current state of every scenario that has left
+///   its initial state.
+/// - `GET /requests` returns the journal of recent requests.
+/// - `POST /requests/verify` takes a request definition in Impostor syntax,
+///   e.g. with an `[Asserts]` section but no response, and returns the
+///   requests in the journal that match it along with their count.
+/// - `POST /mocks` takes entries in Impostor syntax and adds them to the
+///   running mocks. By default they're tried before every other entry; pass
+///   `?position=last` to try them after every other entry instead.
+/// - `DELETE /mocks/:id` removes an entry added with `POST /mocks`.
+/// - `POST /reset` resets scenarios, hit counts and the journal.
+/// - `POST /reload` compiles the mocks again from their file.
+///
+/// It should be served separately from the mocks, so that it can't clash with
+/// any of their routes.
+pub fn admin_router(mocks: Mocks) -> Router {
+    Router::new()
+        .route("/entries", get(entries))
+        .route("/scenarios", get(scenarios))
+        .route("/requests", get(requests))
+        .route("/requests/verify", post(verify))
+        .route("/mocks", post(register))
+        .route("/mocks/:id", delete(remove))
+        .route("/reset", post(reset))
+        .route("/reload", post(reload))
+        .with_state(mocks)
+}
+
+async fn entries(StateExtractor(mocks): StateExtractor<Mocks>) -> Response {
+    Json(mocks.state().entries()).into_response()
+}
+
+async fn scenarios(StateExtractor(mocks): StateExtractor<Mocks>) -> Response {
+    Json(mocks.state().scenarios()).into_response()
+}
+
+async fn requests(StateExtractor(mocks): StateExtractor<Mocks>) -> Response {
+    Json(mocks.state().journal()).into_response()
+}
+
+async fn verify(StateExtractor(mocks): StateExtractor<Mocks>, definition: String) -> Response {
+    match mocks.state().verify(&definition) {
+        Ok(requests) => Json(json!({
+            "count": requests.len(),
+            "requests": requests,
+        }))
+        .into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize)]
+struct RegisterParams {
+    #[serde(default)]
+    position: Position,
+}
+
+async fn register(
+    StateExtractor(mocks): StateExtractor<Mocks>,
+    Query(params): Query<RegisterParams>,
+    contents: String,
+) -> Response {
+    match mocks.state().register(&contents, params.position) {
+        Ok(entries) => (StatusCode::CREATED, Json(entries)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+async fn remove(StateExtractor(mocks): StateExtractor<Mocks>, Path(id): Path<usize>) -> Response {
+    if mocks.state().remove(id) {
+        Json(json!({})).into_response()
+    } else {
+        let error = format!("no entry added at runtime has the id {}", id);
+        (StatusCode::NOT_FOUND, Json(json!({ "error": error }))).into_response()
+    }
+}
+
+async fn reset(StateExtractor(mocks): StateExtractor<Mocks>) -> Response {
+    mocks.state().reset();
+    Json(json!({})).into_response()
+}
+
+async fn reload(StateExtractor(mocks): StateExtractor<Mocks>) -> Response {
+    match mocks.reload() {
+        Ok(()) => Json(mocks.state().entries()).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response(e: error::Error) -> Response {
+    let status_code = match e {
+        error::Error::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::UNPROCESSABLE_ENTITY,
+    };
+    (status_code, Json(json!({ "error": e.to_string() }))).into_response()
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use axum::body::Body;
+    use tower::util::ServiceExt;
+
+    use super::*;
+
+    async fn call(router: &Router, method: &str, uri: &str) -> (u16, serde_json::Value) {
+        call_with_body(router, method, uri, "").await
+    }
+
+    async fn call_with_body(
+        router: &Router,
+        method: &str,
+        uri: &str,
+        body: &str,
+    ) -> (u16, serde_json::Value) {
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status().as_u16();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = if body.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::from_slice(&body).unwrap()
+        };
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn test_admin_api() {
+        let contents = r#"
+# A comment before the entry.
+GET /hello
+[Options]
+scenario: greeting
+next-state: greeted
+
+HTTP 200
+"#;
+        let mocks = Mocks::from_str(contents).unwrap();
+        let router = mocks.router();
+        let admin = admin_router(mocks);
+
+        let (status, body) = call(&router, "GET", "/hello?name=world").await;
+        assert_eq!(status, 200);
+        assert_eq!(body, serde_json::Value::Null);
+        call(&router, "GET", "/nope").await;
+
+        let (_, entries) = call(&admin, "GET", "/entries").await;
+        assert_eq!(
+            entries,
+            json!([{
+                "id": 0,
+                "origin": "file",
+                "method": "GET",
+                "path": "/hello",
+                "line": 3,
+                "column": 1,
+                "responses": 1,
+                "scenario": "greeting",
+                "hits": 1,
+            }])
+        );
+
+        let (_, scenarios) = call(&admin, "GET", "/scenarios").await;
+        assert_eq!(scenarios, json!({ "greeting": "greeted" }));
+
+        let (_, requests) = call(&admin, "GET", "/requests").await;
+        assert_eq!(requests[0]["uri"], "/hello?name=world");
+        assert_eq!(requests[0]["entry"], 0);
+        assert_eq!(requests[0]["status"], 200);
+        assert_eq!(requests[1]["uri"], "/nope");
+        assert_eq!(requests[1]["entry"], serde_json::Value::Null);
+        assert_eq!(requests[1]["status"], 404);
+
+        let (status, verified) = call_with_body(
+            &admin,
+            "POST",
+            "/requests/verify",
+            "GET /hello\n[Asserts]\nqueryparam \"name\" == \"world\"\n",
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(verified["count"], 1);
+        assert_eq!(verified["requests"][0]["uri"], "/hello?name=world");
+
+        let (status, verified) = call_with_body(&admin, "POST", "/requests/verify", "GET").await;
+        assert_eq!(status, 422);
+        assert!(verified["error"].is_string());
+
+        let (status, _) = call(&admin, "POST", "/reset").await;
+        assert_eq!(status, 200);
+        let (_, entries) = call(&admin, "GET", "/entries").await;
+        assert_eq!(entries[0]["hits"], 0);
+        let (_, requests) = call(&admin, "GET", "/requests").await;
+        assert_eq!(requests, json!([]));
+        let (_, scenarios) = call(&admin, "GET", "/scenarios").await;
+        assert_eq!(scenarios, json!({}));
+
+        let (status, entries) = call(&admin, "POST", "/reload").await;
+        assert_eq!(status, 200);
+        assert_eq!(entries[0]["path"], "/hello");
+    }
+
+    #[tokio::test]
+    async fn test_admin_api_runtime_mocks() {
+        let mocks = Mocks::from_str("GET /hello\nHTTP 200\n`from file`\n").unwrap();
+        let router = mocks.router();
+        let admin = admin_router(mocks);
+
+        let (status, added) =
+            call_with_body(&admin, "POST", "/mocks", "GET /hello\nHTTP 200\n`added`\n").await;
+        assert_eq!(status, 201);
+        assert_eq!(added[0]["origin"], "runtime");
+        let id = added[0]["id"].as_u64().unwrap();
+
+        let (status, _) = call_with_body(
+            &admin,
+            "POST",
+            "/mocks?position=last",
+            "GET /other\nHTTP 200\n`other`\n",
+        )
+        .await;
+        assert_eq!(status, 201);
+
+        let request = axum::http::Request::builder()
+            .uri("/hello")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.as_ref(), b"added");
+        let request = axum::http::Request::builder()
+            .uri("/other")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let (status, _) = call(&admin, "DELETE", &format!("/mocks/{}", id)).await;
+        assert_eq!(status, 200);
+        let (status, _) = call(&admin, "DELETE", &format!("/mocks/{}", id)).await;
+        assert_eq!(status, 404);
+        let (status, _) = call(&admin, "DELETE", "/mocks/0").await;
+        assert_eq!(status, 404);
+
+        let request = axum::http::Request::builder()
+            .uri("/hello")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.as_ref(), b"from file");
+
+        let (status, error) = call_with_body(&admin, "POST", "/mocks", "GET /broken\n").await;
+        assert_eq!(status, 422);
+        assert!(error["error"].is_string());
+    }
+}
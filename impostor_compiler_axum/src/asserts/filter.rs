@@ -0,0 +1,179 @@
+use impostor_core::ast::{Filter as AstFilter, FilterValue as AstFilterValue};
+use regex::Regex;
+
+use crate::form;
+
+use super::{predicate::Predicate, AssertCompilationError};
+
+#[derive(Clone, Debug)]
+pub(super) enum Filter {
+    Count,
+    IgnoreCase,
+    MediaType,
+}
+
+impl TryFrom<AstFilter> for Filter {
+    type Error = AssertCompilationError;
+
+    fn try_from(value: AstFilter) -> Result<Self, Self::Error> {
+        match value.value {
+            AstFilterValue::Count => Ok(Filter::Count),
+            AstFilterValue::IgnoreCase => Ok(Filter::IgnoreCase),
+            AstFilterValue::MediaType => Ok(Filter::MediaType),
+            _ => Err(AssertCompilationError::InvalidFilterType),
+        }
+    }
+}
+
+impl std::fmt::Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Filter::Count => write!(f, "count"),
+            Filter::IgnoreCase => write!(f, "ignoreCase"),
+            Filter::MediaType => write!(f, "mediaType"),
+        }
+    }
+}
+
+impl Filter {
+    pub(super) fn apply(&self, value: serde_json::Value) -> serde_json::Value {
+        match self {
+            // Queries give a single value as it is, rather than in an array,
+            // so that it counts as one.
+            Filter::Count => match value {
+                serde_json::Value::Null => 0.into(),
+                serde_json::Value::Array(values) => values.len().into(),
+                serde_json::Value::Object(members) => members.len().into(),
+                _ => 1.into(),
+            },
+            Filter::IgnoreCase | Filter::MediaType => match value {
+                serde_json::Value::String(value) => self.normalize(&value).into(),
+                serde_json::Value::Array(values) => {
+                    values.into_iter().map(|value| self.apply(value)).collect()
+                }
+                value => value,
+            },
+        }
+    }
+
+    /// Make what a predicate compares values to like the values the filter
+    /// gives, so that e.g. `ignoreCase == "JSON"` matches `json`.
+    pub(super) fn normalize_predicate(&self, predicate: Predicate) -> Predicate {
+        if let Filter::Count = self {
+            return predicate;
+        }
+        let normalize = |value: serde_json::Value| match value {
+            serde_json::Value::String(value) => self.normalize(&value).into(),
+            value => value,
+        };
+        match predicate {
+            Predicate::Equal(value) => Predicate::Equal(normalize(value)),
+            Predicate::NotEqual(value) => Predicate::NotEqual(normalize(value)),
+            Predicate::Include(value) => Predicate::Include(normalize(value)),
+            Predicate::StartWith(value) => Predicate::StartWith(self.normalize(&value)),
+            Predicate::EndWith(value) => Predicate::EndWith(self.normalize(&value)),
+            Predicate::Contain(value) => Predicate::Contain(self.normalize(&value)),
+            Predicate::Match(regex) if matches!(self, Filter::IgnoreCase) => {
+                match Regex::new(&format!("(?i){}", regex.as_str())) {
+                    Ok(regex) => Predicate::Match(regex),
+                    Err(_) => Predicate::Match(regex),
+                }
+            }
+            predicate => predicate,
+        }
+    }
+
+    fn normalize(&self, value: &str) -> String {
+        match self {
+            Filter::Count => value.to_string(),
+            Filter::IgnoreCase => value.to_lowercase(),
+            Filter::MediaType => form::list(value)
+                .into_iter()
+                .map(media_type)
+                .collect::<Vec<String>>()
+                .join(", "),
+        }
+    }
+}
+
+/// Normalize a media type: lowercase its type, the names of its parameters,
+/// which are sorted, and its charset, and drop whitespace and quotes, e.g.
+/// `Text/HTML ; level="1";Charset=UTF-8` becomes `text/html; charset=utf-8;
+/// level=1`.
+fn media_type(value: &str) -> String {
+    let essence = value.split(';').next().unwrap_or_default();
+    let mut normalized = essence
+        .split('/')
+        .map(|part| part.trim().to_lowercase())
+        .collect::<Vec<String>>()
+        .join("/");
+    let mut parameters = form::parameters(value);
+    for (name, value) in &mut parameters {
+        *name = name.to_lowercase();
+        if name == "charset" {
+            *value = value.to_lowercase();
+        }
+    }
+    parameters.sort();
+    for (name, value) in parameters {
+        normalized.push_str(&format!("; {}={}", name, value));
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_apply_count() {
+        assert_eq!(Filter::Count.apply(json!(["a", "b"])), json!(2));
+        assert_eq!(Filter::Count.apply(json!({"a": 1})), json!(1));
+        assert_eq!(Filter::Count.apply(json!("a")), json!(1));
+        assert_eq!(Filter::Count.apply(json!(null)), json!(0));
+    }
+
+    #[test]
+    fn test_apply_ignore_case() {
+        assert_eq!(
+            Filter::IgnoreCase.apply(json!(["Application/JSON", "text/html"])),
+            json!(["application/json", "text/html"])
+        );
+        assert_eq!(Filter::IgnoreCase.apply(json!(null)), json!(null));
+    }
+
+    #[test]
+    fn test_apply_media_type() {
+        assert_eq!(
+            Filter::MediaType.apply(json!("Text/HTML ; level=\"1\";Charset=UTF-8")),
+            json!("text/html; charset=utf-8; level=1")
+        );
+        assert_eq!(
+            Filter::MediaType.apply(json!("application/json,text/*;q=0.8 , ")),
+            json!("application/json, text/*; q=0.8")
+        );
+        assert_eq!(
+            Filter::MediaType.apply(json!("text/plain; Note=\"A, B\", */*")),
+            json!("text/plain; note=A, B, */*")
+        );
+    }
+
+    #[test]
+    fn test_normalize_predicate() {
+        let Predicate::Equal(value) = Filter::MediaType
+            .normalize_predicate(Predicate::Equal(json!("application/json;charset=utf-8")))
+        else {
+            panic!("expected an equal predicate");
+        };
+        assert_eq!(value, json!("application/json; charset=utf-8"));
+
+        let Predicate::Match(regex) = Filter::IgnoreCase
+            .normalize_predicate(Predicate::Match(Regex::new("^Application/").unwrap()))
+        else {
+            panic!("expected a match predicate");
+        };
+        assert!(regex.is_match("application/json"));
+    }
+}
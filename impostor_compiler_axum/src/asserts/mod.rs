@@ -1,3 +1,4 @@
+mod filter;
 mod predicate;
 mod query;
 
@@ -6,12 +7,19 @@ use std::fmt::Display;
 use axum::extract::Request;
 use serde_json::json;
 
-use self::{predicate::Predicate, query::Query};
+use crate::near_miss::Mismatch;
+
+use self::{
+    filter::Filter,
+    predicate::Predicate,
+    query::{Query, QueryApplicationError},
+};
 
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum AssertCompilationError {
     InvalidQueryType,
+    InvalidFilterType,
     InvalidPredicate,
     InvalidPredicateValue(String),
 }
@@ -20,6 +28,7 @@ impl Display for AssertCompilationError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             AssertCompilationError::InvalidQueryType => write!(f, "invalid query type"),
+            AssertCompilationError::InvalidFilterType => write!(f, "invalid filter type"),
             AssertCompilationError::InvalidPredicate => write!(f, "invalid predicate"),
             AssertCompilationError::InvalidPredicateValue(message) => {
                 write!(f, "invalid predicate value - {}", message)
@@ -48,22 +57,37 @@ impl std::error::Error for AssertionError {}
 #[derive(Clone, Debug)]
 pub(crate) struct Assert {
     query: Query,
+    filters: Vec<Filter>,
     predicate: Predicate,
     not: bool,
+    /// The line of the file the assert is on.
+    line: usize,
 }
 
 impl TryFrom<impostor_core::ast::Assert> for Assert {
     type Error = AssertCompilationError;
 
     fn try_from(value: impostor_core::ast::Assert) -> Result<Self, Self::Error> {
+        let line = value.query.source_info.start.line;
         let query = value.query.try_into()?;
+        let filters = value
+            .filters
+            .into_iter()
+            .map(|(_, filter)| filter.try_into())
+            .collect::<Result<Vec<Filter>, _>>()?;
         let not = value.predicate.not;
-        let predicate = value.predicate.try_into()?;
+        let predicate = filters
+            .iter()
+            .fold(value.predicate.try_into()?, |predicate, filter| {
+                filter.normalize_predicate(predicate)
+            });
 
         Ok(Assert {
             query,
+            filters,
             predicate,
             not,
+            line,
         })
     }
 }
@@ -72,21 +96,24 @@ impl TryFrom<impostor_core::ast::Header> for Assert {
     type Error = AssertCompilationError;
 
     fn try_from(value: impostor_core::ast::Header) -> Result<Self, Self::Error> {
+        let line = value.key.source_info.start.line;
         let query = Query::Header(value.key.encoded());
         let not = false;
         let predicate = Predicate::Equal(json!(value.value.encoded()));
 
         Ok(Assert {
             query,
+            filters: vec![],
             predicate,
             not,
+            line,
         })
     }
 }
 
 impl Assert {
     pub fn apply(&self, request: &Request) -> Result<bool, AssertionError> {
-        let query_value = match self.query.apply(request) {
+        let query_value = match self.value(request) {
             Ok(value) => value,
             Err(e) => return Err(AssertionError::InvalidQueryValue(Box::new(e))),
         };
@@ -97,6 +124,47 @@ impl Assert {
             assertion_result
         })
     }
+
+    /// Get the value the predicate checks: the query's, through the filters.
+    fn value(&self, request: &Request) -> Result<serde_json::Value, QueryApplicationError> {
+        let value = self.query.apply(request)?;
+        Ok(self
+            .filters
+            .iter()
+            .fold(value, |value, filter| filter.apply(value)))
+    }
+
+    /// Explain why the assert fails for a request, if it does.
+    pub fn mismatch(&self, request: &Request) -> Option<Mismatch> {
+        let actual = match self.value(request) {
+            Ok(value) if self.predicate.apply(&value) != self.not => return None,
+            Ok(value) if self.not => value,
+            Ok(value) => self.predicate.explain(value),
+            // The assert fails if its query can't be applied, whatever its
+            // predicate is.
+            Err(e) => serde_json::Value::String(e.to_string()),
+        };
+
+        Some(Mismatch {
+            line: self.line,
+            expected: self.to_string(),
+            actual,
+        })
+    }
+}
+
+impl Display for Assert {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.query)?;
+        for filter in &self.filters {
+            write!(f, " {}", filter)?;
+        }
+        if self.not {
+            write!(f, " not {}", self.predicate)
+        } else {
+            write!(f, " {}", self.predicate)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -109,8 +177,10 @@ mod test {
     fn test_assert_header() {
         let assert = Assert {
             query: Query::Header("foo".to_string()),
+            filters: vec![],
             predicate: Predicate::Equal(json!("bar")),
             not: false,
+            line: 1,
         };
 
         let request = Request::builder()
@@ -130,8 +200,10 @@ mod test {
     fn test_assert_header_not() {
         let assert = Assert {
             query: Query::Header("foo".to_string()),
+            filters: vec![],
             predicate: Predicate::Equal(json!("bar")),
             not: true,
+            line: 1,
         };
 
         let request = Request::builder()
@@ -151,8 +223,10 @@ mod test {
     fn test_assert_query_param() {
         let assert = Assert {
             query: Query::QueryParam("foo".to_string()),
+            filters: vec![],
             predicate: Predicate::Equal(json!("bar")),
             not: false,
+            line: 1,
         };
 
         let request = Request::builder()
@@ -168,12 +242,110 @@ mod test {
         assert!(!assert.apply(&request).unwrap());
     }
 
+    #[test]
+    fn test_assert_repeated_header() {
+        let request = Request::builder()
+            .header("accept", "text/html")
+            .header("accept", "application/json")
+            .body(Body::empty())
+            .unwrap();
+        let assert = |filters: Vec<Filter>, predicate: Predicate| Assert {
+            query: Query::Header("accept".to_string()),
+            filters,
+            predicate,
+            not: false,
+            line: 1,
+        };
+
+        assert!(
+            assert(vec![], Predicate::Include(json!("application/json")))
+                .apply(&request)
+                .unwrap()
+        );
+        assert!(!assert(vec![], Predicate::Equal(json!("text/html")))
+            .apply(&request)
+            .unwrap());
+
+        let count = assert(vec![Filter::Count], Predicate::Equal(json!(2)));
+        assert_eq!(count.to_string(), "header \"accept\" count == 2");
+        assert!(count.apply(&request).unwrap());
+    }
+
+    #[test]
+    fn test_assert_single_value_includes() {
+        let assert = |query: Query| Assert {
+            query,
+            filters: vec![],
+            predicate: Predicate::Include(json!("a")),
+            not: false,
+            line: 1,
+        };
+
+        let request = Request::builder()
+            .uri("/?tag=a")
+            .header("x-tag", "a")
+            .header("cookie", "tag=b")
+            .body(Body::empty())
+            .unwrap();
+        assert!(assert(Query::QueryParam("tag".to_string()))
+            .apply(&request)
+            .unwrap());
+        assert!(assert(Query::Header("x-tag".to_string()))
+            .apply(&request)
+            .unwrap());
+        assert!(!assert(Query::Cookie("tag".to_string()))
+            .apply(&request)
+            .unwrap());
+        assert!(!assert(Query::Header("x-other".to_string()))
+            .apply(&request)
+            .unwrap());
+
+        let equal = Assert {
+            predicate: Predicate::Equal(json!("a")),
+            ..assert(Query::QueryParam("tag".to_string()))
+        };
+        assert!(equal.apply(&request).unwrap());
+    }
+
+    #[test]
+    fn test_assert_mismatch() {
+        let assert = Assert {
+            query: Query::Header("foo".to_string()),
+            filters: vec![],
+            predicate: Predicate::Equal(json!("bar")),
+            not: false,
+            line: 3,
+        };
+        assert_eq!(assert.to_string(), r#"header "foo" == "bar""#);
+
+        let request = Request::builder()
+            .header("foo", "bar")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(assert.mismatch(&request), None);
+
+        let request = Request::builder()
+            .header("foo", "baz")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            assert.mismatch(&request),
+            Some(Mismatch {
+                line: 3,
+                expected: r#"header "foo" == "bar""#.to_string(),
+                actual: json!("baz"),
+            })
+        );
+    }
+
     #[test]
     fn test_assert_header_value_invalid() {
         let assert = Assert {
             query: Query::Header("foo".to_string()),
+            filters: vec![],
             predicate: Predicate::Equal(json!("bar")),
             not: false,
+            line: 1,
         };
 
         let request = Request::builder()
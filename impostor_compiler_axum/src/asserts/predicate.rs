@@ -4,7 +4,7 @@ use impostor_core::ast::{
 };
 use regex::Regex;
 
-use crate::possibly_trim_surrounding_quotes;
+use crate::{json, schema, template_value};
 
 use super::AssertCompilationError;
 
@@ -19,6 +19,10 @@ pub(super) enum Number {
 pub(super) enum Predicate {
     Equal(serde_json::Value),
     NotEqual(serde_json::Value),
+    /// Compares to a JSON object or array, as JSON. Strings, like bodies, are
+    /// parsed first.
+    EqualJson(serde_json::Value),
+    NotEqualJson(serde_json::Value),
     GreaterThan(Number),
     GreaterThanOrEqual(Number),
     LessThan(Number),
@@ -27,7 +31,17 @@ pub(super) enum Predicate {
     EndWith(String),
     Contain(String),
     Include(serde_json::Value),
+    IncludeJson {
+        value: serde_json::Value,
+        unordered: bool,
+    },
     Match(Regex),
+    /// Checks JSON against a JSON Schema. `source` is where the schema comes
+    /// from, as it's written in the assert.
+    MatchSchema {
+        schema: schema::CompiledSchema,
+        source: String,
+    },
     IsInteger,
     IsFloat,
     IsBoolean,
@@ -42,6 +56,14 @@ impl TryFrom<AstPredicate> for Predicate {
 
     fn try_from(value: AstPredicate) -> Result<Self, Self::Error> {
         Ok(match value.predicate_func.value {
+            AstPredicateFuncValue::Equal {
+                value: value @ AstPredicateValue::Json(_),
+                ..
+            } => Predicate::EqualJson(try_into_json(value)?),
+            AstPredicateFuncValue::NotEqual {
+                value: value @ AstPredicateValue::Json(_),
+                ..
+            } => Predicate::NotEqualJson(try_into_json(value)?),
             AstPredicateFuncValue::Equal { value, .. } => {
                 Predicate::Equal(try_into_serde_value(value)?)
             }
@@ -72,7 +94,14 @@ impl TryFrom<AstPredicate> for Predicate {
             AstPredicateFuncValue::Include { value, .. } => {
                 Predicate::Include(try_into_serde_value(value)?)
             }
+            AstPredicateFuncValue::IncludeJson {
+                value, unordered, ..
+            } => Predicate::IncludeJson {
+                value: try_into_json(value)?,
+                unordered: unordered.is_some(),
+            },
             AstPredicateFuncValue::Match { value, .. } => Predicate::Match(try_into_regex(value)?),
+            AstPredicateFuncValue::MatchSchema { value, .. } => try_into_schema(value)?,
             AstPredicateFuncValue::IsInteger => Predicate::IsInteger,
             AstPredicateFuncValue::IsFloat => Predicate::IsFloat,
             AstPredicateFuncValue::IsBoolean => Predicate::IsBoolean,
@@ -85,11 +114,65 @@ impl TryFrom<AstPredicate> for Predicate {
     }
 }
 
+impl std::fmt::Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Number::Integer(value) => write!(f, "{}", value),
+            Number::Float(value) => write!(f, "{}", value),
+            Number::BigInteger(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl std::fmt::Display for Predicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Predicate::Equal(value) => write!(f, "== {}", value),
+            Predicate::NotEqual(value) => write!(f, "!= {}", value),
+            Predicate::EqualJson(value) => write!(f, "== {}", value),
+            Predicate::NotEqualJson(value) => write!(f, "!= {}", value),
+            Predicate::GreaterThan(value) => write!(f, "> {}", value),
+            Predicate::GreaterThanOrEqual(value) => write!(f, ">= {}", value),
+            Predicate::LessThan(value) => write!(f, "< {}", value),
+            Predicate::LessThanOrEqual(value) => write!(f, "<= {}", value),
+            Predicate::StartWith(value) => {
+                write!(f, "startsWith {}", serde_json::Value::from(value.as_str()))
+            }
+            Predicate::EndWith(value) => {
+                write!(f, "endsWith {}", serde_json::Value::from(value.as_str()))
+            }
+            Predicate::Contain(value) => {
+                write!(f, "contains {}", serde_json::Value::from(value.as_str()))
+            }
+            Predicate::Include(value) => write!(f, "includes {}", value),
+            Predicate::IncludeJson {
+                value,
+                unordered: false,
+            } => write!(f, "includesJson {}", value),
+            Predicate::IncludeJson {
+                value,
+                unordered: true,
+            } => write!(f, "includesJson unordered {}", value),
+            Predicate::Match(value) => write!(f, "matches /{}/", value),
+            Predicate::MatchSchema { source, .. } => write!(f, "matchesSchema {}", source),
+            Predicate::IsInteger => write!(f, "isInteger"),
+            Predicate::IsFloat => write!(f, "isFloat"),
+            Predicate::IsBoolean => write!(f, "isBoolean"),
+            Predicate::IsString => write!(f, "isString"),
+            Predicate::IsCollection => write!(f, "isCollection"),
+            Predicate::Exist => write!(f, "exists"),
+            Predicate::IsEmpty => write!(f, "isEmpty"),
+        }
+    }
+}
+
 impl Predicate {
     pub(super) fn apply(&self, against: &serde_json::Value) -> bool {
         match self {
             Predicate::Equal(value) => compare_eq(value, against),
             Predicate::NotEqual(value) => compare_ne(value, against),
+            Predicate::EqualJson(value) => compare_eq_json(value, against),
+            Predicate::NotEqualJson(value) => !compare_eq_json(value, against),
             // The next four are the other way around from the name!
             Predicate::GreaterThan(value) => compare_lt(value, against),
             Predicate::GreaterThanOrEqual(value) => compare_lteq(value, against),
@@ -99,7 +182,13 @@ impl Predicate {
             Predicate::EndWith(value) => compare_end_with(value, against),
             Predicate::Contain(value) => compare_contain(value, against),
             Predicate::Include(value) => compare_include(value, against),
+            Predicate::IncludeJson { value, unordered } => {
+                compare_include_json(value, *unordered, against)
+            }
             Predicate::Match(value) => compare_match(value, against),
+            Predicate::MatchSchema { schema, .. } => {
+                schema_errors(schema, against).is_some_and(|errors| errors.is_empty())
+            }
             Predicate::IsInteger => compare_is_integer(against),
             Predicate::IsFloat => compare_is_float(against),
             Predicate::IsBoolean => compare_is_boolean(against),
@@ -111,6 +200,25 @@ impl Predicate {
     }
 }
 
+impl Predicate {
+    /// Describe what a value the predicate fails for has instead: the value
+    /// itself, or for a schema, how the value doesn't match it.
+    pub(super) fn explain(&self, against: serde_json::Value) -> serde_json::Value {
+        let Predicate::MatchSchema { schema, .. } = self else {
+            return against;
+        };
+        match schema_errors(schema, &against) {
+            Some(errors) if !errors.is_empty() => errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<String>>()
+                .join("; ")
+                .into(),
+            _ => against,
+        }
+    }
+}
+
 fn compare_eq(first: &serde_json::Value, second: &serde_json::Value) -> bool {
     first == second
 }
@@ -119,6 +227,34 @@ fn compare_ne(first: &serde_json::Value, second: &serde_json::Value) -> bool {
     first != second
 }
 
+fn compare_eq_json(first: &serde_json::Value, second: &serde_json::Value) -> bool {
+    parse_json(second).is_some_and(|second| json::equal(first, &second))
+}
+
+fn compare_include_json(
+    first: &serde_json::Value,
+    unordered: bool,
+    second: &serde_json::Value,
+) -> bool {
+    parse_json(second).is_some_and(|second| json::includes(&second, first, unordered))
+}
+
+/// Validate a value against a schema, or get `None` if it isn't JSON.
+fn schema_errors(
+    schema: &schema::CompiledSchema,
+    against: &serde_json::Value,
+) -> Option<Vec<schema::SchemaError>> {
+    parse_json(against).map(|instance| schema.validate(&instance))
+}
+
+/// Parse a string, like a body, as JSON, or take any other value as it is.
+fn parse_json(value: &serde_json::Value) -> Option<serde_json::Value> {
+    match value {
+        serde_json::Value::String(value) => serde_json::from_str(value).ok(),
+        value => Some(value.clone()),
+    }
+}
+
 fn compare_gt(first: &Number, second: &serde_json::Value) -> bool {
     match second {
         serde_json::Value::Number(second) => match first {
@@ -236,7 +372,10 @@ fn compare_include(first: &serde_json::Value, second: &serde_json::Value) -> boo
     match second {
         serde_json::Value::Array(second) => second.contains(first),
         serde_json::Value::Object(second) => second.contains_key(first.as_str().unwrap()),
-        _ => false,
+        serde_json::Value::Null => false,
+        // Queries give a single value as it is, rather than in an array, so
+        // it's a list of one.
+        second => second == first,
     }
 }
 
@@ -292,9 +431,7 @@ fn try_into_serde_value(
     value: AstPredicateValue,
 ) -> Result<serde_json::Value, AssertCompilationError> {
     Ok(match value {
-        AstPredicateValue::String(value) => {
-            serde_json::Value::String(possibly_trim_surrounding_quotes(value.encoded()))
-        }
+        AstPredicateValue::String(value) => serde_json::Value::String(template_value(&value)),
         AstPredicateValue::Number(value) => match value {
             impostor_core::ast::Number::Float(f) => {
                 serde_json::to_value(f.value).expect("cannot fail")
@@ -314,6 +451,55 @@ fn try_into_serde_value(
     })
 }
 
+fn try_into_json(value: AstPredicateValue) -> Result<serde_json::Value, AssertCompilationError> {
+    match value {
+        AstPredicateValue::Json(value) => json::value(&value).ok_or_else(|| {
+            AssertCompilationError::InvalidPredicateValue(
+                "expressions aren't supported in JSON".into(),
+            )
+        }),
+        _ => Err(AssertCompilationError::InvalidPredicateValue(
+            "expected JSON".into(),
+        )),
+    }
+}
+
+/// Load the schema of a `matchesSchema` predicate, from a file or inline.
+fn try_into_schema(value: AstPredicateValue) -> Result<Predicate, AssertCompilationError> {
+    match value {
+        AstPredicateValue::File(file) => {
+            let name = file.filename.value;
+            let contents = std::fs::read_to_string(&name).map_err(|e| {
+                AssertCompilationError::InvalidPredicateValue(format!(
+                    "can't read schema {}: {}",
+                    name, e
+                ))
+            })?;
+            let schema = serde_json::from_str(&contents).map_err(|e| {
+                AssertCompilationError::InvalidPredicateValue(format!(
+                    "schema {} isn't JSON: {}",
+                    name, e
+                ))
+            })?;
+            compile_schema(schema, format!("file,{};", name))
+        }
+        value => {
+            let schema = try_into_json(value)?;
+            compile_schema(schema.clone(), schema.to_string())
+        }
+    }
+}
+
+fn compile_schema(
+    schema: serde_json::Value,
+    source: String,
+) -> Result<Predicate, AssertCompilationError> {
+    let schema = schema::CompiledSchema::compile(schema).map_err(|e| {
+        AssertCompilationError::InvalidPredicateValue(format!("schema {}: {}", source, e))
+    })?;
+    Ok(Predicate::MatchSchema { schema, source })
+}
+
 fn try_into_number(value: AstPredicateValue) -> Result<Number, AssertCompilationError> {
     Ok(match value {
         AstPredicateValue::Number(value) => match value {
@@ -331,7 +517,7 @@ fn try_into_number(value: AstPredicateValue) -> Result<Number, AssertCompilation
 
 fn try_into_string(value: AstPredicateValue) -> Result<String, AssertCompilationError> {
     Ok(match value {
-        AstPredicateValue::String(value) => possibly_trim_surrounding_quotes(value.encoded()),
+        AstPredicateValue::String(value) => template_value(&value),
         _ => {
             return Err(AssertCompilationError::InvalidPredicateValue(
                 "expected string".into(),
@@ -574,6 +760,58 @@ mod test {
         assert!(!predicate.apply(&json!(["other"])));
     }
 
+    #[test]
+    fn test_apply_equal_json() {
+        let predicate = Predicate::EqualJson(json!({"a": 1, "b": [1, 2]}));
+
+        assert!(predicate.apply(&json!("{\"b\": [1, 2.0],\n \"a\": 1}")));
+        assert!(predicate.apply(&json!({"a": 1, "b": [1, 2]})));
+        assert!(!predicate.apply(&json!("{\"a\": 1, \"b\": [2, 1]}")));
+        assert!(!predicate.apply(&json!("not json")));
+        assert!(Predicate::NotEqualJson(json!([1])).apply(&json!("[2]")));
+    }
+
+    #[test]
+    fn test_apply_include_json() {
+        let against = &json!("{\"name\": \"Ada\", \"tags\": [\"b\", \"a\"]}");
+
+        let predicate = Predicate::IncludeJson {
+            value: json!({"tags": ["a"]}),
+            unordered: false,
+        };
+        assert!(predicate.apply(against));
+
+        let predicate = Predicate::IncludeJson {
+            value: json!({"tags": ["a", "b"]}),
+            unordered: false,
+        };
+        assert!(!predicate.apply(against));
+
+        let predicate = Predicate::IncludeJson {
+            value: json!({"tags": ["a", "b"]}),
+            unordered: true,
+        };
+        assert!(predicate.apply(against));
+    }
+
+    #[test]
+    fn test_apply_match_schema() {
+        let predicate = Predicate::MatchSchema {
+            schema: schema::CompiledSchema::compile(json!({"type": "object", "required": ["id"], "properties": {"id": {"type": "integer"}}}))
+                .unwrap(),
+            source: "file,order.json;".to_string(),
+        };
+
+        assert!(predicate.apply(&json!("{\"id\": 1, \"note\": \"\"}")));
+        assert!(!predicate.apply(&json!("{\"id\": \"1\"}")));
+        assert!(!predicate.apply(&json!("not json")));
+        assert_eq!(
+            predicate.explain(json!("{\"note\": 1}")),
+            json!("$: missing required property id")
+        );
+        assert_eq!(predicate.explain(json!("not json")), json!("not json"));
+    }
+
     #[test]
     fn test_apply_match() {
         let predicate = Predicate::Match(Regex::new("^test$").unwrap());
@@ -1,21 +1,32 @@
 use std::collections::HashMap;
 
-use axum::extract::Request;
-use axum_extra::extract::CookieJar;
-use impostor_core::ast::{Query as AstQuery, QueryValue as AstQueryValue};
+use axum::{
+    extract::Request,
+    http::{
+        header::{CONTENT_TYPE, COOKIE, HOST},
+        uri::Authority,
+    },
+};
+use axum_extra::extract::cookie::Cookie;
+use impostor_core::ast::{MultipartAttributeName, Query as AstQuery, QueryValue as AstQueryValue};
 
-use crate::possibly_trim_surrounding_quotes;
+use crate::{form, journal::BufferedBody, template_value};
 
 use super::AssertCompilationError;
 
 #[derive(Clone, Debug)]
 pub(super) enum Query {
-    Path,
+    Url,
+    Host,
+    Scheme,
+    Port,
     Header(String),
     Cookie(String),
     Body,
     Jsonpath(String),
     QueryParam(String),
+    Form(String),
+    Multipart(String, Option<MultipartAttributeName>),
 }
 
 impl TryFrom<AstQuery> for Query {
@@ -23,25 +34,55 @@ impl TryFrom<AstQuery> for Query {
 
     fn try_from(value: AstQuery) -> Result<Self, Self::Error> {
         match value.value {
-            AstQueryValue::Path => Ok(Query::Path),
-            AstQueryValue::Header { name, .. } => Ok(Query::Header(
-                possibly_trim_surrounding_quotes(name.encoded()),
-            )),
-            AstQueryValue::Cookie { expr, .. } => Ok(Query::Cookie(
-                possibly_trim_surrounding_quotes(expr.name.encoded()),
-            )),
+            AstQueryValue::Url => Ok(Query::Url),
+            AstQueryValue::Host => Ok(Query::Host),
+            AstQueryValue::Scheme => Ok(Query::Scheme),
+            AstQueryValue::Port => Ok(Query::Port),
+            AstQueryValue::Header { name, .. } => Ok(Query::Header(template_value(&name))),
+            AstQueryValue::Cookie { expr, .. } => Ok(Query::Cookie(template_value(&expr.name))),
             AstQueryValue::Body => Ok(Query::Body),
-            AstQueryValue::Jsonpath { expr, .. } => Ok(Query::Jsonpath(
-                possibly_trim_surrounding_quotes(expr.encoded()),
-            )),
-            AstQueryValue::QueryParam { name, .. } => Ok(Query::QueryParam(
-                possibly_trim_surrounding_quotes(name.encoded()),
+            AstQueryValue::Jsonpath { expr, .. } => Ok(Query::Jsonpath(template_value(&expr))),
+            AstQueryValue::QueryParam { name, .. } => Ok(Query::QueryParam(template_value(&name))),
+            AstQueryValue::Form { name, .. } => Ok(Query::Form(template_value(&name))),
+            AstQueryValue::Multipart {
+                name, attribute, ..
+            } => Ok(Query::Multipart(
+                template_value(&name),
+                attribute.map(|attribute| attribute.name),
             )),
             _ => Err(AssertCompilationError::InvalidQueryType),
         }
     }
 }
 
+impl std::fmt::Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Query::Url => write!(f, "url"),
+            Query::Host => write!(f, "host"),
+            Query::Scheme => write!(f, "scheme"),
+            Query::Port => write!(f, "port"),
+            Query::Header(name) => write!(f, "header {}", serde_json::Value::from(name.as_str())),
+            Query::Cookie(name) => write!(f, "cookie {}", serde_json::Value::from(name.as_str())),
+            Query::Body => write!(f, "body"),
+            Query::Jsonpath(expr) => {
+                write!(f, "jsonpath {}", serde_json::Value::from(expr.as_str()))
+            }
+            Query::QueryParam(name) => {
+                write!(f, "queryparam {}", serde_json::Value::from(name.as_str()))
+            }
+            Query::Form(name) => write!(f, "form {}", serde_json::Value::from(name.as_str())),
+            Query::Multipart(name, attribute) => {
+                write!(f, "multipart {}", serde_json::Value::from(name.as_str()))?;
+                match attribute {
+                    Some(attribute) => write!(f, " {}", attribute.value()),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(super) enum QueryApplicationError {
     InvalidHeaderValue(String, Box<dyn std::error::Error>),
@@ -69,26 +110,63 @@ impl Query {
         request: &Request,
     ) -> Result<serde_json::Value, QueryApplicationError> {
         let serialized = match self {
-            Query::Path => serde_json::Value::String(request.uri().path().to_string()),
+            // Without a host, e.g. for HTTP/1.0 requests, the URL is just the
+            // path and query.
+            Query::Url => {
+                let path_and_query = request
+                    .uri()
+                    .path_and_query()
+                    .map(|path_and_query| path_and_query.as_str())
+                    .unwrap_or("/");
+                match authority(request) {
+                    Some(authority) => {
+                        format!("{}://{}{}", scheme(request), authority, path_and_query).into()
+                    }
+                    None => path_and_query.into(),
+                }
+            }
+            Query::Host => authority(request)
+                .map(|authority| authority.host().to_string())
+                .into(),
+            Query::Scheme => scheme(request).into(),
+            Query::Port => authority(request)
+                .map(|authority| {
+                    authority.port_u16().unwrap_or(match scheme(request) {
+                        "https" => 443,
+                        _ => 80,
+                    })
+                })
+                .into(),
             Query::Header(name) => {
-                if let Some(header_value) = request.headers().get(name) {
+                let mut header_values = vec![];
+                for header_value in request.headers().get_all(name) {
                     let header_as_str = header_value.to_str().map_err(|e| {
                         QueryApplicationError::InvalidHeaderValue(name.clone(), Box::new(e))
                     })?;
-                    serde_json::Value::String(header_as_str.to_string())
-                } else {
-                    serde_json::Value::Null
+                    header_values.push(header_as_str.to_string());
                 }
+                values(header_values)
             }
-            Query::Cookie(name) => {
-                let cookies = CookieJar::from_headers(request.headers());
-                if let Some(cookie_value) = cookies.get(name) {
-                    serde_json::Value::String(cookie_value.value_trimmed().to_string())
-                } else {
-                    serde_json::Value::Null
+            // Parsed like `CookieJar` does, but keeping cookies that have the
+            // same name, which a jar replaces with the last of them.
+            Query::Cookie(name) => values(
+                request
+                    .headers()
+                    .get_all(COOKIE)
+                    .into_iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .flat_map(|value| value.split(';'))
+                    .filter_map(|cookie| Cookie::parse_encoded(cookie.to_owned()).ok())
+                    .filter(|cookie| cookie.name() == name)
+                    .map(|cookie| cookie.value_trimmed().to_string())
+                    .collect(),
+            ),
+            Query::Body => match request.extensions().get::<BufferedBody>() {
+                Some(body) => {
+                    serde_json::Value::String(String::from_utf8_lossy(&body.0).into_owned())
                 }
-            }
-            Query::Body => todo!(),
+                None => serde_json::Value::Null,
+            },
             Query::Jsonpath(_) => todo!(),
             Query::QueryParam(name) => {
                 let query_string = match request.uri().query() {
@@ -96,8 +174,30 @@ impl Query {
                     None => return Ok(serde_json::Value::Null),
                 };
 
+                // Plain params, e.g. `tag` in `?tag=a&tag=b`, can be repeated.
+                // Others, like `tag[]` or `user[name]`, are left to `serde_qs`,
+                // without the rest of the params, which it would reject if any
+                // were repeated.
+                let param_values: Vec<String> = form_urlencoded::parse(query_string.as_bytes())
+                    .filter(|(param, _)| param == name)
+                    .map(|(_, value)| value.into_owned())
+                    .collect();
+                if !param_values.is_empty() {
+                    return Ok(values(param_values));
+                }
+                let nested = format!("{}[", name);
+                let query_string = query_string
+                    .split('&')
+                    .filter(|pair| {
+                        form_urlencoded::parse(pair.as_bytes())
+                            .next()
+                            .is_some_and(|(param, _)| param.starts_with(&nested))
+                    })
+                    .collect::<Vec<&str>>()
+                    .join("&");
+
                 let query_params =
-                    serde_qs::from_str::<HashMap<String, serde_json::Value>>(query_string);
+                    serde_qs::from_str::<HashMap<String, serde_json::Value>>(&query_string);
                 let query_params = match query_params {
                     Ok(query_params) => query_params,
                     Err(e) => {
@@ -114,12 +214,73 @@ impl Query {
                     None => serde_json::Value::Null,
                 }
             }
+            // Forms are only parsed if the request says they're forms.
+            Query::Form(name) => {
+                let (content_type, body) = content_type_and_body(request);
+                if !form::is_media_type(content_type, "application/x-www-form-urlencoded") {
+                    return Ok(serde_json::Value::Null);
+                }
+                form::urlencoded_field(body, name).into()
+            }
+            Query::Multipart(name, attribute) => {
+                let (content_type, body) = content_type_and_body(request);
+                let part = form::multipart_parts(content_type, body)
+                    .and_then(|parts| parts.into_iter().find(|part| part.name == *name));
+                let Some(part) = part else {
+                    return Ok(serde_json::Value::Null);
+                };
+                match attribute {
+                    None => String::from_utf8_lossy(&part.content).into_owned().into(),
+                    Some(MultipartAttributeName::Filename) => part.filename.into(),
+                    Some(MultipartAttributeName::ContentType) => part.content_type.into(),
+                }
+            }
         };
 
         Ok(serialized)
     }
 }
 
+/// Turn the values of something a request can have several of, like a header,
+/// into one value: `null` for none, a string for one, and an array for more.
+fn values(mut values: Vec<String>) -> serde_json::Value {
+    match values.len() {
+        0 => serde_json::Value::Null,
+        1 => values.remove(0).into(),
+        _ => values.into(),
+    }
+}
+
+/// Get the host and port a request is for, from its URI if it's absolute, as
+/// with HTTP/2, or from its `Host` header.
+fn authority(request: &Request) -> Option<Authority> {
+    if let Some(authority) = request.uri().authority() {
+        return Some(authority.clone());
+    }
+    let host = request.headers().get(HOST)?.to_str().ok()?;
+    host.parse().ok()
+}
+
+/// Get the scheme of a request, which is `http` unless its URI says otherwise,
+/// as Impostor doesn't serve HTTPS itself.
+fn scheme(request: &Request) -> &str {
+    request.uri().scheme_str().unwrap_or("http")
+}
+
+/// Get the `Content-Type` of a request, or an empty string, and its body.
+fn content_type_and_body(request: &Request) -> (&str, &[u8]) {
+    let content_type = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let body = match request.extensions().get::<BufferedBody>() {
+        Some(body) => &body.0[..],
+        None => &[],
+    };
+    (content_type, body)
+}
+
 #[cfg(test)]
 mod test {
     use axum::body::Body;
@@ -200,6 +361,134 @@ mod test {
         assert_eq!(result, serde_json::Value::String("qux".to_string()));
     }
 
+    fn create_form_request(content_type: &str, body: &'static [u8]) -> Request<Body> {
+        let mut request = Request::builder()
+            .header("content-type", content_type)
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(BufferedBody(axum::body::Bytes::from_static(body)));
+        request
+    }
+
+    #[test]
+    fn test_query_application_for_repeated_values() {
+        let request = Request::builder()
+            .uri("/?tag=a&tag=b+c&one=1&user[name]=Ada")
+            .header("accept", "text/html")
+            .header("accept", "application/json")
+            .header("cookie", "id=1; id=2")
+            .header("cookie", "theme=dark")
+            .body(Body::empty())
+            .unwrap();
+        let apply = |query: Query| query.apply(&request).unwrap();
+
+        assert_eq!(
+            apply(Query::QueryParam("tag".to_string())),
+            serde_json::json!(["a", "b c"])
+        );
+        assert_eq!(apply(Query::QueryParam("one".to_string())), "1");
+        assert_eq!(
+            apply(Query::QueryParam("user".to_string())),
+            serde_json::json!({"name": "Ada"})
+        );
+        assert_eq!(
+            apply(Query::Header("accept".to_string())),
+            serde_json::json!(["text/html", "application/json"])
+        );
+        assert_eq!(
+            apply(Query::Cookie("id".to_string())),
+            serde_json::json!(["1", "2"])
+        );
+        assert_eq!(apply(Query::Cookie("theme".to_string())), "dark");
+    }
+
+    #[test]
+    fn test_query_application_for_url() {
+        let apply = |query: Query, request: &Request<Body>| query.apply(request).unwrap();
+
+        let request = create_test_request();
+        assert_eq!(
+            apply(Query::Url, &request),
+            "http://localhost:3000/?foo=bar&baz=qux"
+        );
+        assert_eq!(apply(Query::Host, &request), "localhost");
+        assert_eq!(apply(Query::Scheme, &request), "http");
+        assert_eq!(apply(Query::Port, &request), 3000);
+
+        let request = Request::builder()
+            .uri("/users?id=1")
+            .header("host", "api.example.com")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            apply(Query::Url, &request),
+            "http://api.example.com/users?id=1"
+        );
+        assert_eq!(apply(Query::Host, &request), "api.example.com");
+        assert_eq!(apply(Query::Port, &request), 80);
+
+        let request = Request::builder()
+            .uri("/users?id=1")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(apply(Query::Url, &request), "/users?id=1");
+        assert_eq!(apply(Query::Host, &request), serde_json::Value::Null);
+        assert_eq!(apply(Query::Port, &request), serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_query_application_for_form() {
+        let request = create_form_request(
+            "application/x-www-form-urlencoded",
+            b"name=Ada+Lovelace&lang=en",
+        );
+        let result = Query::Form("name".to_string()).apply(&request).unwrap();
+        assert_eq!(result, serde_json::json!("Ada Lovelace"));
+        let result = Query::Form("age".to_string()).apply(&request).unwrap();
+        assert_eq!(result, serde_json::Value::Null);
+
+        let request = create_form_request("text/plain", b"name=Ada");
+        let result = Query::Form("name".to_string()).apply(&request).unwrap();
+        assert_eq!(result, serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_query_application_for_multipart() {
+        let request = create_form_request(
+            "multipart/form-data; boundary=b",
+            b"--b\r\n\
+            Content-Disposition: form-data; name=\"avatar\"; filename=\"me.png\"\r\n\
+            Content-Type: image/png\r\n\r\n\
+            png\r\n\
+            --b\r\n\
+            Content-Disposition: form-data; name=\"note\"\r\n\r\n\
+            hi\r\n\
+            --b--\r\n",
+        );
+        let apply = |name: &str, attribute| {
+            Query::Multipart(name.to_string(), attribute)
+                .apply(&request)
+                .unwrap()
+        };
+        assert_eq!(apply("avatar", None), serde_json::json!("png"));
+        assert_eq!(
+            apply("avatar", Some(MultipartAttributeName::Filename)),
+            serde_json::json!("me.png")
+        );
+        assert_eq!(
+            apply("avatar", Some(MultipartAttributeName::ContentType)),
+            serde_json::json!("image/png")
+        );
+        assert_eq!(
+            apply("note", Some(MultipartAttributeName::Filename)),
+            serde_json::Value::Null
+        );
+        assert_eq!(apply("note", None), serde_json::json!("hi"));
+        assert_eq!(apply("other", None), serde_json::Value::Null);
+    }
+
     #[test]
     fn test_query_application_for_nonexistent_cookie() {
         let request = create_test_request();
@@ -0,0 +1,427 @@
+//! Helpers to build the AST of Impostor files that aren't parsed from text,
+//! e.g. recordings and imports, so that they can be printed.
+//!
+//! Built nodes have no position, and print the way a person would write them:
+//! one space between tokens and a newline after each line.
+
+use impostor_core::{
+    ast::{
+        Assert, Base64, Body, Bytes, Comment, Filter, FilterValue, Header, JsonValue,
+        LineTerminator, Method, MultilineString, Number, Pos, Predicate, PredicateFunc,
+        PredicateFuncValue, PredicateValue, Query, QueryValue, Request, RequestSection,
+        RequestSectionValue, Response, SourceInfo, Status, Template, TemplateElement, Text,
+        Version, VersionValue, Whitespace,
+    },
+    parser::{parse_json, Reader},
+};
+
+/// Build a request with asserts. Every request but the first one of a file
+/// is separated from the entry before it by a blank line, and then by a line
+/// for each comment.
+pub(crate) fn request(
+    method: &str,
+    path: &str,
+    asserts: Vec<Assert>,
+    first: bool,
+    comments: &[String],
+) -> Request {
+    let mut sections = vec![];
+    if !asserts.is_empty() {
+        sections.push(RequestSection {
+            line_terminators: vec![],
+            space0: whitespace(""),
+            line_terminator0: newline(),
+            value: RequestSectionValue::Asserts(asserts),
+            source_info: source_info(),
+        });
+    }
+
+    Request {
+        line_terminators: line_terminators(first, comments),
+        space0: whitespace(""),
+        method: Method(method.to_string()),
+        space1: whitespace(" "),
+        path: template(None, path, encode_path(path)),
+        line_terminator0: newline(),
+        headers: vec![],
+        sections,
+        body: None,
+        source_info: source_info(),
+    }
+}
+
+/// Build the lines before an entry: a blank line, unless it's the first one
+/// of its file, and then a line for each comment.
+pub(crate) fn line_terminators(first: bool, comments: &[String]) -> Vec<LineTerminator> {
+    let mut line_terminators = if first { vec![] } else { vec![newline()] };
+    line_terminators.extend(comments.iter().map(|value| comment(value)));
+    line_terminators
+}
+
+pub(crate) fn response(status: u16, headers: Vec<Header>, body: Option<Body>) -> Response {
+    Response {
+        line_terminators: vec![],
+        space0: whitespace(""),
+        version: Version {
+            value: VersionValue::VersionAny,
+            source_info: source_info(),
+        },
+        space1: whitespace(" "),
+        status: Status {
+            value: status,
+            source_info: source_info(),
+        },
+        line_terminator0: newline(),
+        headers,
+        body,
+        source_info: source_info(),
+    }
+}
+
+pub(crate) fn assert(query: QueryValue, predicate: PredicateFuncValue) -> Assert {
+    Assert {
+        line_terminators: vec![],
+        space0: whitespace(""),
+        query: Query {
+            source_info: source_info(),
+            value: query,
+        },
+        filters: vec![],
+        space1: whitespace(" "),
+        predicate: Predicate {
+            not: false,
+            space0: whitespace(""),
+            predicate_func: PredicateFunc {
+                source_info: source_info(),
+                value: predicate,
+            },
+        },
+        line_terminator0: newline(),
+    }
+}
+
+/// Build an `== "value"` predicate.
+pub(crate) fn equal(value: &str) -> PredicateFuncValue {
+    PredicateFuncValue::Equal {
+        space0: whitespace(" "),
+        value: PredicateValue::String(quoted(value)),
+        operator: true,
+    }
+}
+
+/// Build an `includes "value"` predicate.
+pub(crate) fn include(value: &str) -> PredicateFuncValue {
+    PredicateFuncValue::Include {
+        space0: whitespace(" "),
+        value: PredicateValue::String(quoted(value)),
+    }
+}
+
+/// Build an assert that a query has `count` values, e.g.
+/// `queryparam "tag" count == 2`.
+pub(crate) fn count_assert(query: QueryValue, count: usize) -> Assert {
+    let mut assert = assert(
+        query,
+        PredicateFuncValue::Equal {
+            space0: whitespace(" "),
+            value: PredicateValue::Number(Number::Integer(count as i64)),
+            operator: true,
+        },
+    );
+    assert.filters.push((
+        whitespace(" "),
+        Filter {
+            source_info: source_info(),
+            value: FilterValue::Count,
+        },
+    ));
+    assert
+}
+
+/// Build a `contains "value"` predicate.
+pub(crate) fn contains(value: &str) -> PredicateFuncValue {
+    PredicateFuncValue::Contain {
+        space0: whitespace(" "),
+        value: PredicateValue::String(quoted(value)),
+    }
+}
+
+/// Build a `queryparam "name"` query.
+pub(crate) fn query_param(name: &str) -> QueryValue {
+    QueryValue::QueryParam {
+        space0: whitespace(" "),
+        name: quoted(name),
+    }
+}
+
+/// Build a `header "name"` query.
+pub(crate) fn header_query(name: &str) -> QueryValue {
+    QueryValue::Header {
+        space0: whitespace(" "),
+        name: quoted(name),
+    }
+}
+
+/// Build a header, with its name title-cased, e.g. `Content-Type`. Returns
+/// `None` if the name can't be written in an Impostor file.
+pub(crate) fn header(name: &str, value: &str) -> Option<Header> {
+    let writable = |c: char| c.is_ascii_alphanumeric() || ['_', '-', '.', '@', '$'].contains(&c);
+    if name.is_empty() || !name.chars().all(writable) {
+        return None;
+    }
+    let name = title_case(name);
+
+    Some(Header {
+        line_terminators: vec![],
+        space0: whitespace(""),
+        key: template(None, &name, name.clone()),
+        space1: whitespace(""),
+        space2: whitespace(" "),
+        value: template(None, value, escape(value, '#')),
+        line_terminator0: newline(),
+    })
+}
+
+pub(crate) fn body(value: Bytes) -> Body {
+    Body {
+        line_terminators: vec![],
+        space0: whitespace(""),
+        value,
+        line_terminator0: newline(),
+    }
+}
+
+/// Parse a JSON body, keeping its formatting, if it's nothing but JSON.
+pub(crate) fn json(text: &str) -> Option<JsonValue> {
+    // Strings in JSON bodies are templates, which would change `{{...}}`.
+    if text.contains("{{") {
+        return None;
+    }
+    let mut reader = Reader::new(text.trim_end());
+    let value = parse_json(&mut reader).ok()?;
+    reader.is_eof().then_some(value)
+}
+
+/// Build a text body: a backtick string if the text fits on one line, or a
+/// multiline string if it can be written as one. Returns `None` otherwise.
+pub(crate) fn text(text: &str) -> Option<Bytes> {
+    if !text.contains('\n') {
+        return Some(Bytes::OnelineString(template(
+            Some('`'),
+            text,
+            escape(text, '`'),
+        )));
+    }
+    is_multiline_text(text).then(|| {
+        Bytes::MultilineString(MultilineString::Text(Text {
+            space: whitespace(""),
+            newline: whitespace("\n"),
+            value: template(None, text, text.to_string()),
+        }))
+    })
+}
+
+pub(crate) fn base64_bytes(bytes: &[u8]) -> Bytes {
+    Bytes::Base64(Base64 {
+        space0: whitespace(""),
+        value: bytes.to_vec(),
+        encoded: base64(bytes),
+        space1: whitespace(""),
+    })
+}
+
+/// Check if text can be written as is in a multiline string. Multiline strings
+/// have no escape sequences, so the text can't end them early or contain a
+/// template, and the compiler would take quotes around all of it as
+/// delimiters.
+fn is_multiline_text(text: &str) -> bool {
+    let quoted = ['"', '\'', '`']
+        .iter()
+        .any(|quote| text.len() > 1 && text.starts_with(*quote) && text.ends_with(*quote));
+    !text.contains("```") && !text.contains("{{") && !text.ends_with('`') && !quoted
+}
+
+/// Escape a string for a template ended by `delimiter`, so that it parses
+/// back to the same string rather than a template.
+pub(crate) fn escape(s: &str, delimiter: char) -> String {
+    let mut escaped = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\x08' => escaped.push_str("\\b"),
+            '\x0c' => escaped.push_str("\\f"),
+            '{' if chars.peek() == Some(&'{') => escaped.push_str("\\u{7b}"),
+            c if c == delimiter => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent-encode the characters that can't be written in the path of a
+/// request.
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::new();
+    for c in path.chars() {
+        if c.is_ascii_alphanumeric() || ":/.-?=&_%*,@~+!$'();[]".contains(c) {
+            encoded.push(c);
+        } else {
+            let mut buffer = [0; 4];
+            for byte in c.encode_utf8(&mut buffer).bytes() {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+    encoded
+}
+
+/// Capitalize each word of a header name, e.g. `Content-Type`.
+fn title_case(name: &str) -> String {
+    name.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decode standard base64, with or without padding. Returns `None` if the
+/// text isn't base64.
+pub(crate) fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.trim_end_matches('=').bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => return None,
+        };
+        buffer = buffer << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+pub(crate) fn quoted(value: &str) -> Template {
+    template(Some('"'), value, escape(value, '"'))
+}
+
+fn template(delimiter: Option<char>, value: &str, encoded: String) -> Template {
+    let elements = if value.is_empty() {
+        vec![]
+    } else {
+        vec![TemplateElement::String {
+            value: value.to_string(),
+            encoded,
+        }]
+    };
+    Template {
+        delimiter,
+        elements,
+        source_info: source_info(),
+    }
+}
+
+/// Build a comment on a line of its own. Comments end at the end of the line,
+/// so line breaks in `value` are replaced with spaces.
+fn comment(value: &str) -> LineTerminator {
+    LineTerminator {
+        space0: whitespace(""),
+        comment: Some(Comment {
+            value: format!(" {}", value.replace(['\r', '\n'], " ")),
+            source_info: source_info(),
+        }),
+        newline: whitespace("\n"),
+    }
+}
+
+fn newline() -> LineTerminator {
+    LineTerminator {
+        space0: whitespace(""),
+        comment: None,
+        newline: whitespace("\n"),
+    }
+}
+
+fn whitespace(value: &str) -> Whitespace {
+    Whitespace {
+        value: value.to_string(),
+        source_info: source_info(),
+    }
+}
+
+/// Built nodes don't come from a file, so they have no position.
+fn source_info() -> SourceInfo {
+    SourceInfo::new(Pos::new(0, 0), Pos::new(0, 0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape(r#"a "b" \ {{c}}"#, '"'), r#"a \"b\" \\ \u{7b}{c}}"#);
+        assert_eq!(escape("a`b\tc", '`'), "a\\`b\\tc");
+        assert_eq!(escape("max-age=60 # cached", '#'), "max-age=60 \\# cached");
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(&[0, 159, 146, 150]), "AJ+Slg==");
+
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert_eq!(decode_base64("Zg==").unwrap(), b"f");
+        assert_eq!(decode_base64("Zm8").unwrap(), b"fo");
+        assert_eq!(decode_base64("AJ+S\nlg==").unwrap(), [0, 159, 146, 150]);
+        assert_eq!(decode_base64("Zm9v!"), None);
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use impostor_core::ast::Delay as AstDelay;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+
+/// A compiled `delay` option, which decides how long an entry waits before
+/// responding.
+#[derive(Clone, Debug)]
+pub(crate) enum Delay {
+    Fixed(Duration),
+    Range(Duration, Duration),
+    Normal(Normal<f64>),
+}
+
+impl From<AstDelay> for Delay {
+    fn from(delay: AstDelay) -> Self {
+        match delay {
+            AstDelay::Fixed(duration) => Delay::Fixed(Duration::from_millis(duration.as_millis())),
+            AstDelay::Range { min, max } => Delay::Range(
+                Duration::from_millis(min.as_millis()),
+                Duration::from_millis(max.as_millis()),
+            ),
+            AstDelay::Normal { mean, std_dev, .. } => Delay::Normal(
+                // Only fails if the standard deviation isn't finite, and a u64
+                // is always finite.
+                Normal::new(mean.as_millis() as f64, std_dev.as_millis() as f64)
+                    .expect("cannot fail"),
+            ),
+        }
+    }
+}
+
+impl Delay {
+    /// Picks how long to wait for a single request.
+    pub fn sample(&self) -> Duration {
+        match self {
+            Delay::Fixed(duration) => *duration,
+            Delay::Range(min, max) => rand::thread_rng().gen_range(*min..=*max),
+            // Samples below zero are clamped, since we can't respond before the
+            // request arrives.
+            Delay::Normal(normal) => {
+                Duration::from_millis(normal.sample(&mut rand::thread_rng()).max(0.0) as u64)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sample_fixed() {
+        let delay = Delay::Fixed(Duration::from_millis(250));
+        assert_eq!(delay.sample(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_sample_range() {
+        let delay = Delay::Range(Duration::from_millis(100), Duration::from_millis(200));
+        for _ in 0..100 {
+            let sample = delay.sample();
+            assert!(sample >= Duration::from_millis(100));
+            assert!(sample <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn test_sample_normal_clamps_negative_samples() {
+        let delay = Delay::Normal(Normal::new(-1000.0, 1.0).unwrap());
+        assert_eq!(delay.sample(), Duration::ZERO);
+    }
+}
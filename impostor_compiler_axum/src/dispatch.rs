@@ -0,0 +1,241 @@
+use std::sync::Arc;
+
+use axum::{
+    body::{Body, HttpBody},
+    extract::{Request, State as StateExtractor},
+    http::{
+        header::{ACCEPT, ALLOW, CONTENT_LENGTH},
+        HeaderMap, HeaderValue, Method, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::{entry::Entry, error, journal::MatchedEntry, near_miss::Report, state::State};
+
+/// The method of entries that handle requests with any method.
+pub(crate) const ANY_METHOD: &str = "ANY";
+
+/// The path of entries that handle requests on any path.
+pub(crate) const ANY_PATH: &str = "*";
+
+/// The entries of a router, indexed by path.
+///
+/// An axum router can't change once it's built, so instead of one axum route
+/// per path, every request goes through [`dispatch`], which looks up the
+/// entries to try here. Paths are matched the same way axum matches routes:
+/// when several paths match a request, static segments win over parameters,
+/// which win over wildcards. Entries that share a path are tried in order,
+/// except for fallback entries, which are tried after the others. Entries on
+/// any path (`*`) are tried after the entries of the request's path.
+#[derive(Default)]
+pub(crate) struct Routes {
+    entries: Vec<Arc<Entry>>,
+    paths: matchit::Router<Vec<usize>>,
+    any_path: Vec<usize>,
+}
+
+impl Routes {
+    /// Index entries by path. Entries are tried in the order they're given.
+    pub fn new(entries: Vec<Arc<Entry>>) -> error::Result<Routes> {
+        let mut by_path: Vec<(&str, Vec<usize>)> = vec![];
+        let mut any_path = vec![];
+        for (index, entry) in entries.iter().enumerate() {
+            if entry.path == ANY_PATH {
+                any_path.push(index);
+                continue;
+            }
+            match by_path.iter_mut().find(|(path, _)| *path == entry.path) {
+                Some((_, indexes)) => indexes.push(index),
+                None => by_path.push((&entry.path, vec![index])),
+            }
+        }
+
+        let mut paths = matchit::Router::new();
+        for (path, mut indexes) in by_path {
+            // The sort is stable, so entries keep their order otherwise.
+            indexes.sort_by_key(|index| entries[*index].fallback);
+            paths
+                .insert(path, indexes)
+                .map_err(|e| error::Error::InvalidPath(Box::new(e)))?;
+        }
+
+        Ok(Routes {
+            entries,
+            paths,
+            any_path,
+        })
+    }
+
+    /// Find the entries whose path matches `path`, in the order they should
+    /// be tried.
+    pub fn candidates(&self, path: &str) -> Vec<Arc<Entry>> {
+        let for_path = match self.paths.at(path) {
+            Ok(matched) => matched.value.as_slice(),
+            Err(_) => &[],
+        };
+        for_path
+            .iter()
+            .chain(&self.any_path)
+            .map(|index| self.entries[*index].clone())
+            .collect()
+    }
+}
+
+/// Check if a request path matches an entry's path.
+pub(crate) fn path_matches(pattern: &str, path: &str) -> bool {
+    if pattern == ANY_PATH {
+        return true;
+    }
+    let mut router = matchit::Router::new();
+    router.insert(pattern, ()).is_ok() && router.at(path).is_ok()
+}
+
+/// Check if a request method matches an entry's methods, e.g. `GET|HEAD`.
+pub(crate) fn method_matches(pattern: &str, method: &str) -> bool {
+    pattern
+        .split('|')
+        .any(|accepted| accepted == ANY_METHOD || accepted == method)
+}
+
+/// Handle a request with the first entry that matches it.
+///
+/// Like an axum router, this responds with a 405 if some entries have the
+/// request's path but none have its method, and handles `HEAD` requests with
+/// `GET` entries if there are no `HEAD` entries for the path. Responses to
+/// `HEAD` requests keep their headers but lose their body. If no entry
+/// matches, the response is a 404, unless there's a fallback entry on any path
+/// (`ANY *`) to handle it instead.
+///
+/// When no entry matches, a report of why is logged at debug level, and sent
+/// as the response body if the state asks for it. If the state has a server
+/// to proxy unmatched requests to, the request is forwarded there instead.
+pub(crate) async fn dispatch(
+    StateExtractor(state): StateExtractor<Arc<State>>,
+    request: Request,
+) -> Response {
+    let candidates = state.candidates(request.uri().path());
+
+    let is_head = request.method() == Method::HEAD;
+    let method = if is_head && !candidates.iter().any(|entry| entry.accepts(&Method::HEAD)) {
+        Method::GET
+    } else {
+        request.method().clone()
+    };
+
+    let candidates_for_method: Vec<Arc<Entry>> = candidates
+        .iter()
+        .filter(|entry| entry.accepts(&method))
+        .cloned()
+        .collect();
+    let has_path = candidates.iter().any(|entry| entry.path != ANY_PATH);
+    if candidates_for_method.is_empty() && has_path {
+        return unmatched(
+            &state,
+            request,
+            StatusCode::METHOD_NOT_ALLOWED,
+            allow(&candidates),
+        )
+        .await;
+    }
+
+    for entry in candidates_for_method {
+        if entry.matches(&request, &state) && entry.transition(&state) {
+            let mut response = entry.handler(&state, request).await;
+            if is_head {
+                response = without_body(response);
+            }
+            response.extensions_mut().insert(MatchedEntry(entry.id));
+            return response;
+        }
+    }
+
+    unmatched(&state, request, StatusCode::NOT_FOUND, HeaderMap::new()).await
+}
+
+/// Respond to a request that no entry matched.
+async fn unmatched(
+    state: &State,
+    request: Request,
+    status: StatusCode,
+    headers: HeaderMap,
+) -> Response {
+    let verbose = state.verbose_not_found();
+    let report =
+        (verbose || log::log_enabled!(log::Level::Debug)).then(|| Report::new(&request, state));
+    if let Some(report) = &report {
+        log::debug!("{}", report);
+    }
+
+    if let Some(upstream) = state.proxy_unmatched() {
+        return upstream.respond(request).await;
+    }
+    let Some(report) = report.filter(|_| verbose) else {
+        return (status, headers, vec![]).into_response();
+    };
+
+    let accepts_json = request.headers().get_all(ACCEPT).iter().any(|accept| {
+        accept
+            .to_str()
+            .is_ok_and(|a| a.contains("application/json"))
+    });
+    if accepts_json {
+        (status, headers, Json(report)).into_response()
+    } else {
+        (status, headers, report.to_string()).into_response()
+    }
+}
+
+/// Drop the body of a response to a `HEAD` request, but keep its length.
+fn without_body(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+    if let Some(length) = body.size_hint().exact() {
+        parts
+            .headers
+            .entry(CONTENT_LENGTH)
+            .or_insert(HeaderValue::from(length));
+    }
+    Response::from_parts(parts, Body::empty())
+}
+
+/// Build the `Allow` header for a path with these entries. Paths with `GET`
+/// entries allow `HEAD` too.
+fn allow(candidates: &[Arc<Entry>]) -> HeaderMap {
+    let mut methods: Vec<&str> = vec![];
+    for method in candidates.iter().flat_map(|entry| &entry.methods) {
+        if !methods.contains(&method.as_str()) {
+            methods.push(method.as_str());
+        }
+    }
+    if methods.contains(&"GET") && !methods.contains(&"HEAD") {
+        methods.push("HEAD");
+    }
+
+    let mut headers = HeaderMap::new();
+    if let Ok(allow) = HeaderValue::from_str(&methods.join(",")) {
+        headers.insert(ALLOW, allow);
+    }
+    headers
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_path_matches() {
+        assert!(path_matches("/orders/:id", "/orders/1"));
+        assert!(!path_matches("/orders/:id", "/orders"));
+        assert!(!path_matches("/orders/:id", "/orders/1/items"));
+        assert!(path_matches("*", "/orders/1/items"));
+    }
+
+    #[test]
+    fn test_method_matches() {
+        assert!(method_matches("GET", "GET"));
+        assert!(!method_matches("GET", "POST"));
+        assert!(method_matches("ANY", "POST"));
+        assert!(method_matches("GET|HEAD", "HEAD"));
+        assert!(!method_matches("GET|HEAD", "POST"));
+    }
+}
@@ -1,14 +1,29 @@
 use std::str::FromStr;
 
 use axum::{
+    body::Body,
     extract::Request,
-    http::{HeaderMap, HeaderName, HeaderValue},
+    http::{HeaderMap, HeaderName, HeaderValue, Method},
+    response::{IntoResponse, Response},
 };
-use impostor_core::ast::{Bytes as AstBytes, Entry as AstEntry};
+use impostor_core::ast::{
+    AfterLast, Bytes as AstBytes, Entry as AstEntry, Header as AstHeader, OptionKind, Pos,
+    Proxy as AstProxy, Request as AstRequest, RequestSectionValue, Response as AstResponse,
+};
+use serde_json::json;
 
 use crate::{
     asserts::{Assert, AssertCompilationError},
-    template::StringOrTemplate,
+    delay::Delay,
+    dispatch::{method_matches, path_matches},
+    fault::Fault,
+    near_miss::{Mismatch, NearMiss},
+    proxy::Upstream,
+    scenario::Scenario,
+    serve::RawResponse,
+    state::{EntryInfo, Origin, State},
+    template::{self, Context, StringOrTemplate},
+    template_value,
 };
 
 #[derive(Debug)]
@@ -16,8 +31,12 @@ pub enum EntryCompilationError {
     InvalidStatusCode(u16),
     InvalidMethod(String),
     InvalidHeaderName(Box<dyn std::error::Error + Send + Sync>),
+    InvalidProxy(Box<dyn std::error::Error + Send + Sync>),
     AssertCompilationError(AssertCompilationError),
     NotYetImplemented(String),
+    StateWithoutScenario,
+    /// A section that says what a request sends, which only tests can have.
+    TestOnlySection(String),
 }
 
 impl std::fmt::Display for EntryCompilationError {
@@ -35,7 +54,14 @@ impl std::fmt::Display for EntryCompilationError {
             EntryCompilationError::InvalidHeaderName(e) => {
                 write!(f, "invalid header name: {}", e)
             }
+            EntryCompilationError::InvalidProxy(e) => write!(f, "invalid proxy: {}", e),
             EntryCompilationError::AssertCompilationError(e) => write!(f, "invalid assert: {}", e),
+            EntryCompilationError::StateWithoutScenario => {
+                write!(f, "the state and next-state options need a scenario option")
+            }
+            EntryCompilationError::TestOnlySection(name) => {
+                write!(f, "the [{}] section is only for tests", name)
+            }
         }
     }
 }
@@ -43,72 +69,166 @@ impl std::fmt::Display for EntryCompilationError {
 /// An entry from the Impostor AST compiled for use as an axum handler.
 #[derive(Clone, Debug)]
 pub(crate) struct Entry {
+    /// The position of this entry in its file, which identifies it in the
+    /// router's shared state.
+    pub id: usize,
+    /// Where the entry's request starts in its file.
+    pub pos: Pos,
+
     // Attributes required for routing
     pub path: String,
-    pub method: axum::http::Method,
+    /// The methods the entry handles. `ANY` handles every method.
+    pub methods: Vec<Method>,
 
     // Attributes required for matching the request
     asserts: Vec<Assert>,
+    scenario: Option<Scenario>,
+    /// Whether the entry is only tried after the other entries for its path.
+    pub fallback: bool,
 
     // Attributes required for constructing the response
+    delay: Option<Delay>,
+    fault: Option<Fault>,
+    responses: Vec<EntryResponse>,
+    after_last: AfterLast,
+    /// Where requests are forwarded to, for entries that don't respond
+    /// themselves.
+    proxy: Option<EntryProxy>,
+}
+
+/// Where an entry forwards requests, and the headers it sets on them.
+#[derive(Clone, Debug)]
+struct EntryProxy {
+    upstream: Upstream,
+    headers: Vec<(HeaderName, StringOrTemplate)>,
+}
+
+/// One of the responses of an entry.
+#[derive(Clone, Debug)]
+struct EntryResponse {
     status_code: axum::http::StatusCode,
     headers: Vec<(HeaderName, StringOrTemplate)>,
-    body: Option<StringOrTemplate>,
+    body: Option<ResponseBody>,
 }
 
-impl TryFrom<AstEntry> for Entry {
-    type Error = EntryCompilationError;
+/// The body of a response, either text that may be a template, or raw bytes
+/// from a `base64,` or `hex,` body.
+#[derive(Clone, Debug)]
+enum ResponseBody {
+    Text(StringOrTemplate),
+    Bytes(Vec<u8>),
+}
 
-    fn try_from(entry: AstEntry) -> Result<Entry, EntryCompilationError> {
-        let path = entry.request.path.to_string();
-        let method = match axum::http::Method::from_str(&entry.request.method.0) {
-            Ok(method) => method,
-            Err(_) => return Err(EntryCompilationError::InvalidMethod(entry.request.method.0)),
-        };
+impl TryFrom<AstResponse> for EntryResponse {
+    type Error = EntryCompilationError;
 
-        let status_code = match axum::http::StatusCode::from_u16(entry.response.status.value) {
+    fn try_from(response: AstResponse) -> Result<EntryResponse, EntryCompilationError> {
+        let status_code = match axum::http::StatusCode::from_u16(response.status.value) {
             Ok(status_code) => status_code,
             Err(_) => {
                 return Err(EntryCompilationError::InvalidStatusCode(
-                    entry.response.status.value,
+                    response.status.value,
                 ))
             }
         };
 
-        let mut headers = Vec::new();
-        for header in entry.response.headers {
-            let header_name = match HeaderName::from_str(&header.key.encoded()) {
-                Ok(header_name) => header_name,
-                Err(e) => return Err(EntryCompilationError::InvalidHeaderName(Box::new(e))),
-            };
-            headers.push((
-                header_name,
-                StringOrTemplate::from_ast_template(header.value),
-            ));
+        let headers = compile_headers(response.headers)?;
+        let body = response.body.map(|body| compile_body(body.value));
+
+        Ok(EntryResponse {
+            status_code,
+            headers,
+            body,
+        })
+    }
+}
+
+impl TryFrom<AstProxy> for EntryProxy {
+    type Error = EntryCompilationError;
+
+    fn try_from(proxy: AstProxy) -> Result<EntryProxy, EntryCompilationError> {
+        let upstream = Upstream::new(&template_value(&proxy.url))
+            .map_err(|e| EntryCompilationError::InvalidProxy(Box::new(e)))?;
+        let headers = compile_headers(proxy.headers)?;
+        Ok(EntryProxy { upstream, headers })
+    }
+}
+
+impl TryFrom<AstEntry> for Entry {
+    type Error = EntryCompilationError;
+
+    fn try_from(entry: AstEntry) -> Result<Entry, EntryCompilationError> {
+        // The request's own position includes the blank lines and comments
+        // before it, which isn't where anyone would look for it.
+        let pos = entry.request.space0.source_info.end;
+        if let Some(name) = test_only_section(&entry.request) {
+            return Err(EntryCompilationError::TestOnlySection(name.to_string()));
         }
-        let body = entry.response.body.map(|body| compile_body(body.value));
-        let asserts: Result<Vec<Assert>, AssertCompilationError> = entry
+        let path = entry.request.path.to_string();
+        let methods = entry
             .request
-            .asserts()
-            .iter()
-            .map(|assert| assert.clone().try_into())
-            .chain(
-                entry
-                    .request
-                    .headers
-                    .iter()
-                    .map(|header| header.clone().try_into()),
-            )
-            .collect();
-        let asserts = asserts.map_err(EntryCompilationError::AssertCompilationError)?;
+            .method
+            .0
+            .split('|')
+            .map(|name| {
+                Method::from_str(name)
+                    .map_err(|_| EntryCompilationError::InvalidMethod(name.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let responses = entry
+            .responses
+            .into_iter()
+            .map(EntryResponse::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let proxy = entry.proxy.map(EntryProxy::try_from).transpose()?;
+        let asserts = compile_asserts(&entry.request)
+            .map_err(EntryCompilationError::AssertCompilationError)?;
+
+        let mut delay = None;
+        let mut fault = None;
+        let mut after_last = AfterLast::default();
+        let mut scenario_name = None;
+        let mut state = None;
+        let mut next_state = None;
+        let mut fallback = false;
+        for option in entry.request.options() {
+            match option.kind {
+                OptionKind::Delay(value) => delay = Some(value.into()),
+                OptionKind::Fault(value) => fault = Some(value.into()),
+                OptionKind::AfterLast(value) => after_last = value,
+                OptionKind::Scenario(value) => scenario_name = Some(value),
+                OptionKind::State(value) => state = Some(value),
+                OptionKind::NextState(value) => next_state = Some(value),
+                OptionKind::Fallback(value) => fallback = value,
+            }
+        }
+        let scenario = match scenario_name {
+            Some(name) => Some(Scenario {
+                name,
+                state,
+                next_state,
+            }),
+            None if state.is_some() || next_state.is_some() => {
+                return Err(EntryCompilationError::StateWithoutScenario)
+            }
+            None => None,
+        };
 
         Ok(Entry {
+            // Ids are given out by the router's state.
+            id: 0,
+            pos,
             path,
-            method,
-            status_code,
+            methods,
+            delay,
+            fault,
             asserts,
-            headers,
-            body,
+            scenario,
+            fallback,
+            responses,
+            after_last,
+            proxy,
         })
     }
 }
@@ -126,17 +246,237 @@ impl std::fmt::Display for InternalError {
 impl Entry {
     /// Handle an axum request with this entry.
     ///
-    /// The returned tuple implements the axum `IntoResponse` trait, so it can
-    /// be returned directly from an axum handler.
-    pub fn handler(&self, _request: Request) -> (axum::http::StatusCode, HeaderMap, Vec<u8>) {
-        let status_code = self.status_code;
+    /// The hit is recorded before anything else, and the entry's scenario has
+    /// already moved to its next state in [`Entry::transition`], so that
+    /// requests that come in while this one is delayed see them. How many
+    /// requests this entry handled before
+    /// this one picks the response to send. If the entry has a `delay`
+    /// option, this waits for the delay before building the response. If it
+    /// has a `fault` option, the response misbehaves accordingly.
+    ///
+    /// Entries with a proxy forward the request instead, and send back the
+    /// upstream server's response, faults included.
+    pub async fn handler(&self, state: &State, request: Request) -> Response {
+        let hits = state.hit(self.id);
+
+        if let Some(delay) = &self.delay {
+            tokio::time::sleep(delay.sample()).await;
+        }
+
+        if let Some(proxy) = &self.proxy {
+            let raw_response = request.extensions().get::<RawResponse>().cloned();
+            let (parts, body) = match proxy.forward(request).await {
+                Ok(response) => response.into_parts(),
+                Err(response) => return response,
+            };
+            return match &self.fault {
+                Some(fault) => {
+                    fault
+                        .respond(raw_response, parts.status, parts.headers, body.to_vec())
+                        .await
+                }
+                None => Response::from_parts(parts, Body::from(body)),
+            };
+        }
+
+        let Some(response) = self.response(hits) else {
+            return (axum::http::StatusCode::NOT_FOUND, HeaderMap::new(), vec![]).into_response();
+        };
+
+        let context = if response.has_templates() {
+            template::context(&request)
+        } else {
+            Context::new()
+        };
+        let (headers, body) = match response.execute_templates(&context) {
+            Ok(parts) => parts,
+            Err(e) => {
+                return (
+                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    HeaderMap::new(),
+                    format!("template error: {}", e),
+                )
+                    .into_response()
+            }
+        };
+
+        match &self.fault {
+            Some(fault) => {
+                let raw_response = request.extensions().get::<RawResponse>().cloned();
+                fault
+                    .respond(raw_response, response.status_code, headers, body)
+                    .await
+            }
+            None => (response.status_code, headers, body).into_response(),
+        }
+    }
 
+    /// Check if this entry handles requests with `method`.
+    pub fn accepts(&self, method: &Method) -> bool {
+        self.methods
+            .iter()
+            .any(|accepted| method_matches(accepted.as_str(), method.as_str()))
+    }
+
+    /// The methods of this entry the way they're written in its file, e.g.
+    /// `GET|HEAD`.
+    pub fn method(&self) -> String {
+        let methods: Vec<&str> = self.methods.iter().map(Method::as_str).collect();
+        methods.join("|")
+    }
+
+    /// Describe this entry, with no hits.
+    pub fn info(&self, origin: Origin) -> EntryInfo {
+        EntryInfo {
+            id: self.id,
+            origin,
+            method: self.method(),
+            path: self.path.clone(),
+            line: self.pos.line,
+            column: self.pos.column,
+            responses: self.responses.len(),
+            scenario: self.scenario.as_ref().map(|s| s.name.clone()),
+            hits: 0,
+        }
+    }
+
+    /// Pick the response to send after `hits` previous requests, if any.
+    fn response(&self, hits: usize) -> Option<&EntryResponse> {
+        let count = self.responses.len();
+        if hits < count {
+            return self.responses.get(hits);
+        }
+        match self.after_last {
+            AfterLast::RepeatLast => self.responses.last(),
+            AfterLast::Cycle => self.responses.get(hits % count),
+            AfterLast::NotFound => None,
+        }
+    }
+
+    /// Check if this entry matches a request.
+    ///
+    /// An entry matches a request if its scenario is in the state it
+    /// requires, and all of its asserts pass. Matching the request path isn't
+    /// handled here, and should be handled by the axum router.
+    pub fn matches(&self, request: &Request, state: &State) -> bool {
+        if let Some(scenario) = &self.scenario {
+            if !scenario.matches(state) {
+                return false;
+            }
+        }
+
+        self.asserts
+            .iter()
+            .all(|a| a.apply(request).is_ok_and(|r| r))
+    }
+
+    /// Move this entry's scenario to its next state, provided it's still in the
+    /// state the entry requires, and return whether it was. A request the
+    /// entry matches is only handled once this succeeds, so that of two
+    /// requests that match it at the same time, only one takes its transition.
+    pub fn transition(&self, state: &State) -> bool {
+        self.scenario
+            .as_ref()
+            .is_none_or(|scenario| scenario.transition(state))
+    }
+
+    /// Explain why this entry doesn't match a request, including its path and
+    /// method, e.g. to tell users why a request wasn't handled.
+    pub fn near_miss(&self, request: &Request, state: &State) -> NearMiss {
+        let line = self.pos.line;
+        let mut mismatches = vec![];
+
+        let path = request.uri().path();
+        if !path_matches(&self.path, path) {
+            mismatches.push(Mismatch {
+                line,
+                expected: format!("path {}", self.path),
+                actual: json!(path),
+            });
+        }
+
+        let method = request.method();
+        let is_head_for_get = method == Method::HEAD && self.accepts(&Method::GET);
+        if !self.accepts(method) && !is_head_for_get {
+            mismatches.push(Mismatch {
+                line,
+                expected: format!("method {}", self.method()),
+                actual: json!(method.as_str()),
+            });
+        }
+
+        if let Some(scenario) = &self.scenario {
+            if !scenario.matches(state) {
+                let required = scenario.state.as_deref().unwrap_or_default();
+                mismatches.push(Mismatch {
+                    line,
+                    expected: format!("scenario {} in state {}", scenario.name, required),
+                    actual: json!(state.scenario_state(&scenario.name)),
+                });
+            }
+        }
+
+        mismatches.extend(self.asserts.iter().filter_map(|a| a.mismatch(request)));
+
+        NearMiss {
+            entry: self.id,
+            method: self.method(),
+            path: self.path.clone(),
+            line,
+            mismatches,
+        }
+    }
+}
+
+impl EntryProxy {
+    /// Set the proxy's headers on a request and forward it.
+    async fn forward(
+        &self,
+        mut request: Request,
+    ) -> Result<axum::http::Response<axum::body::Bytes>, Response> {
+        let context = if self.headers.iter().any(|(_, value)| value.is_template()) {
+            template::context(&request)
+        } else {
+            Context::new()
+        };
+        for (name, value) in &self.headers {
+            let value = value
+                .execute(&context)
+                .map_err(|e| e.to_string())
+                .and_then(|value| HeaderValue::from_str(&value).map_err(|e| e.to_string()));
+            match value {
+                Ok(value) => {
+                    request.headers_mut().insert(name.clone(), value);
+                }
+                Err(e) => {
+                    return Err((
+                        axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        HeaderMap::new(),
+                        format!("template error: {}", e),
+                    )
+                        .into_response())
+                }
+            }
+        }
+        self.upstream.forward_request(request).await
+    }
+}
+
+impl EntryResponse {
+    /// Check if building the response needs a template context.
+    fn has_templates(&self) -> bool {
+        self.headers.iter().any(|(_, value)| value.is_template())
+            || matches!(&self.body, Some(ResponseBody::Text(body)) if body.is_template())
+    }
+
+    /// Build the headers and body of the response from their templates.
+    fn execute_templates(&self, context: &Context) -> Result<(HeaderMap, Vec<u8>), InternalError> {
         let headers: Result<HeaderMap, _> = self
             .headers
             .iter()
             .map(|(k, v)| {
                 let header_name = k.to_owned();
-                let header_value = v.execute();
+                let header_value = v.execute(context);
                 let header_value = match header_value {
                     Ok(header_value) => {
                         HeaderValue::from_str(&header_value).map_err(|e| InternalError {
@@ -153,52 +493,78 @@ impl Entry {
                 }
             })
             .collect();
-        let headers = match headers {
-            Ok(headers) => headers,
-            Err(e) => {
-                return (
-                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                    HeaderMap::new(),
-                    format!("template error: {}", e).into(),
-                )
-            }
+
+        let body = match &self.body {
+            Some(ResponseBody::Text(body)) => body
+                .execute(context)
+                .map_err(|e| InternalError {
+                    inner_error: Box::new(e),
+                })?
+                .into_bytes(),
+            Some(ResponseBody::Bytes(bytes)) => bytes.clone(),
+            None => vec![],
         };
 
-        if let Some(body) = &self.body {
-            match body.execute() {
-                Ok(string_body) => (status_code, headers, string_body.into_bytes()),
-                Err(e) => (
-                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                    HeaderMap::new(),
-                    format!("template error: {}", e).into(),
-                ),
-            }
-        } else {
-            (self.status_code, headers, vec![])
-        }
+        Ok((headers?, body))
     }
+}
 
-    /// Check if this entry matches a request.
-    ///
-    /// An entry matches a request if all of its asserts pass. Matching the
-    /// request path isn't handled here, and should be handled by the axum
-    /// router.
-    pub fn matches(&self, request: &Request) -> bool {
-        self.asserts
-            .iter()
-            // TODO: Log failures
-            .all(|a| a.apply(request).is_ok_and(|r| r))
-    }
+/// Compile the asserts a request has to pass, from both its `[Asserts]`
+/// section and its headers.
+pub(crate) fn compile_asserts(request: &AstRequest) -> Result<Vec<Assert>, AssertCompilationError> {
+    request
+        .asserts()
+        .iter()
+        .map(|assert| assert.clone().try_into())
+        .chain(
+            request
+                .headers
+                .iter()
+                .map(|header| header.clone().try_into()),
+        )
+        .collect()
+}
+
+/// Find the name of a section of a request that only tests can have, like
+/// `[QueryStringParams]` or `[Cookies]`, which say what a request sends rather
+/// than what a mock matches.
+pub(crate) fn test_only_section(request: &AstRequest) -> Option<&str> {
+    request
+        .sections
+        .iter()
+        .find(|section| {
+            matches!(
+                section.value,
+                RequestSectionValue::QueryParams(_) | RequestSectionValue::Cookies(_)
+            )
+        })
+        .map(|section| section.name())
+}
+
+fn compile_headers(
+    headers: Vec<AstHeader>,
+) -> Result<Vec<(HeaderName, StringOrTemplate)>, EntryCompilationError> {
+    headers
+        .into_iter()
+        .map(|header| {
+            let header_name = HeaderName::from_str(&header.key.encoded())
+                .map_err(|e| EntryCompilationError::InvalidHeaderName(Box::new(e)))?;
+            Ok((
+                header_name,
+                StringOrTemplate::from_ast_template(header.value),
+            ))
+        })
+        .collect()
 }
 
-fn compile_body(body: AstBytes) -> StringOrTemplate {
+fn compile_body(body: AstBytes) -> ResponseBody {
     match body {
-        AstBytes::Json(val) => StringOrTemplate::String(val.encoded()),
-        AstBytes::Xml(val) => StringOrTemplate::String(val),
-        AstBytes::MultilineString(val) => val.value().into(),
-        AstBytes::OnelineString(val) => val.into(),
-        AstBytes::Base64(_) => todo!(),
+        AstBytes::Json(val) => ResponseBody::Text(StringOrTemplate::String(val.encoded())),
+        AstBytes::Xml(val) => ResponseBody::Text(StringOrTemplate::String(val)),
+        AstBytes::MultilineString(val) => ResponseBody::Text(val.value().into()),
+        AstBytes::OnelineString(val) => ResponseBody::Text(val.into()),
+        AstBytes::Base64(val) => ResponseBody::Bytes(val.value),
         AstBytes::File(_) => todo!(),
-        AstBytes::Hex(_) => todo!(),
+        AstBytes::Hex(val) => ResponseBody::Bytes(val.value),
     }
 }
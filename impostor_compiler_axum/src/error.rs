@@ -12,6 +12,11 @@ pub enum Error {
     ParseError(impostor_core::parser::Error),
     EntryCompilationError(EntryCompilationError),
     InvalidMethod(Box<dyn std::error::Error + Send + Sync>),
+    InvalidPath(Box<dyn std::error::Error + Send + Sync>),
+    InvalidUpstream(String),
+    InvalidOpenApi(String),
+    InvalidHar(String),
+    Io(std::io::Error),
 }
 
 impl std::fmt::Display for Error {
@@ -21,6 +26,11 @@ impl std::fmt::Display for Error {
             Error::ParseError(e) => write!(f, "parse error: {}", e),
             Error::EntryCompilationError(e) => write!(f, "entry compilation error: {}", e),
             Error::InvalidMethod(e) => write!(f, "invalid method: {}", e),
+            Error::InvalidPath(e) => write!(f, "invalid path: {}", e),
+            Error::InvalidUpstream(message) => write!(f, "invalid upstream: {}", message),
+            Error::InvalidOpenApi(message) => write!(f, "invalid OpenAPI document: {}", message),
+            Error::InvalidHar(message) => write!(f, "invalid HAR file: {}", message),
+            Error::Io(e) => write!(f, "io error: {}", e),
         }
     }
 }
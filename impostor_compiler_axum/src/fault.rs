@@ -0,0 +1,125 @@
+use std::io;
+
+use axum::{
+    body::Body,
+    http::{header::CONTENT_LENGTH, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+};
+use impostor_core::ast::Fault as AstFault;
+
+use crate::serve::RawResponse;
+
+/// What a `malformed-response` fault sends: it looks like the start of an HTTP
+/// response, but has no status code.
+pub(crate) const MALFORMED_RESPONSE: &[u8] = b"HTTP/1.1 OOPS\r\nnot a header\r\n\r\n";
+
+/// A compiled `fault` option.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Fault {
+    EmptyResponse,
+    ConnectionReset,
+    TruncatedBody,
+    Stall,
+    MalformedResponse,
+}
+
+impl From<AstFault> for Fault {
+    fn from(fault: AstFault) -> Self {
+        match fault {
+            AstFault::EmptyResponse => Fault::EmptyResponse,
+            AstFault::ConnectionReset => Fault::ConnectionReset,
+            AstFault::TruncatedBody => Fault::TruncatedBody,
+            AstFault::Stall => Fault::Stall,
+            AstFault::MalformedResponse => Fault::MalformedResponse,
+        }
+    }
+}
+
+impl Fault {
+    /// Build a response that misbehaves in the way this fault describes.
+    ///
+    /// The status, headers and body are the ones the entry would have sent
+    /// without the fault, which some faults send partially. Sending a
+    /// malformed response needs the connection's `RawResponse` handle.
+    pub async fn respond(
+        &self,
+        raw_response: Option<RawResponse>,
+        status_code: StatusCode,
+        mut headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Response {
+        match self {
+            Fault::EmptyResponse => Response::new(partial_body(None, true)),
+            Fault::ConnectionReset => {
+                let mut body = body;
+                body.truncate(body.len() / 2);
+                let mut response = Response::new(partial_body(Some(body), true));
+                *response.status_mut() = status_code;
+                *response.headers_mut() = headers;
+                response
+            }
+            Fault::TruncatedBody => {
+                // The declared length has to be more than what's sent, even if
+                // the entry has no body to cut in half.
+                let declared_length = body.len().max(1);
+                let mut body = body;
+                body.truncate(body.len() / 2);
+                headers.insert(CONTENT_LENGTH, HeaderValue::from(declared_length));
+                let mut response = Response::new(partial_body(Some(body), false));
+                *response.status_mut() = status_code;
+                *response.headers_mut() = headers;
+                response
+            }
+            Fault::Stall => std::future::pending().await,
+            Fault::MalformedResponse => match raw_response {
+                Some(raw_response) => {
+                    raw_response.send(MALFORMED_RESPONSE.to_vec());
+                    Response::new(Body::empty())
+                }
+                // Without access to the connection, dropping it is the closest
+                // we can get.
+                None => Response::new(partial_body(None, true)),
+            },
+        }
+    }
+}
+
+enum Step {
+    Send(Vec<u8>),
+    Flush,
+    End,
+    Done,
+}
+
+/// A body that sends `chunk`, and then either ends early or aborts the
+/// connection.
+///
+/// Ending early only misbehaves if the headers declared a longer body. Without a
+/// chunk, not even the status and headers are sent before aborting.
+fn partial_body(chunk: Option<Vec<u8>>, abort: bool) -> Body {
+    let first_step = match chunk {
+        Some(chunk) if chunk.is_empty() => Step::Flush,
+        Some(chunk) => Step::Send(chunk),
+        None => Step::End,
+    };
+    let stream = futures_util::stream::unfold(first_step, move |mut step| async move {
+        loop {
+            match step {
+                Step::Send(chunk) => return Some((Ok(chunk), Step::Flush)),
+                Step::Flush => {
+                    // hyper only flushes what it has written once the body
+                    // isn't ready, so without this, aborting would drop the
+                    // chunk too.
+                    tokio::task::yield_now().await;
+                    step = Step::End;
+                }
+                Step::End if abort => {
+                    let error = io::Error::new(io::ErrorKind::ConnectionAborted, "injected fault");
+                    return Some((Err(error), Step::Done));
+                }
+                Step::End | Step::Done => return None,
+            }
+        }
+    });
+    Body::from_stream(stream)
+}
@@ -0,0 +1,232 @@
+//! Parsing of the bodies HTML forms send: `application/x-www-form-urlencoded`
+//! ones, and `multipart/form-data` ones, which can have files.
+
+/// A part of a multipart body.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Part {
+    pub name: String,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub content: Vec<u8>,
+}
+
+/// Check if a `Content-Type` is for a media type, whatever its parameters.
+pub(crate) fn is_media_type(content_type: &str, media_type: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .eq_ignore_ascii_case(media_type)
+}
+
+/// Find the value of a field of a URL-encoded body.
+pub(crate) fn urlencoded_field(body: &[u8], name: &str) -> Option<String> {
+    form_urlencoded::parse(body)
+        .find(|(field, _)| field == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// Split a multipart body into its parts, with the boundary its `Content-Type`
+/// gives, or get `None` if it isn't `multipart/form-data`.
+///
+/// Parts without a name are left out, and so is anything that isn't between
+/// two boundaries.
+pub(crate) fn multipart_parts(content_type: &str, body: &[u8]) -> Option<Vec<Part>> {
+    if !is_media_type(content_type, "multipart/form-data") {
+        return None;
+    }
+    let (_, boundary) = parameters(content_type)
+        .into_iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("boundary"))?;
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let next_delimiter = format!("\r\n--{}", boundary).into_bytes();
+
+    let mut parts = vec![];
+    let mut start = find(body, &delimiter, 0)? + delimiter.len();
+    // The last boundary is followed by `--`.
+    while !body[start..].starts_with(b"--") {
+        let Some(end) = find(body, &next_delimiter, start) else {
+            break;
+        };
+        let part = body[start..end].strip_prefix(b"\r\n").unwrap_or_default();
+        if let Some(part) = self::part(part) {
+            parts.push(part);
+        }
+        start = end + next_delimiter.len();
+    }
+    Some(parts)
+}
+
+fn part(part: &[u8]) -> Option<Part> {
+    let (head, content) = match find(part, b"\r\n\r\n", 0) {
+        Some(index) => (&part[..index], &part[index + 4..]),
+        // A part with no headers starts with an empty line.
+        None => (&b""[..], part.strip_prefix(b"\r\n")?),
+    };
+    let head = String::from_utf8_lossy(head);
+
+    let mut disposition = vec![];
+    let mut content_type = None;
+    for line in head.split("\r\n") {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if name.trim().eq_ignore_ascii_case("content-disposition") {
+            disposition = parameters(value);
+        } else if name.trim().eq_ignore_ascii_case("content-type") {
+            content_type = Some(value.trim().to_string());
+        }
+    }
+    let parameter = |name: &str| {
+        disposition
+            .iter()
+            .find(|(parameter, _)| parameter.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    };
+
+    Some(Part {
+        name: parameter("name")?,
+        filename: parameter("filename"),
+        content_type,
+        content: content.to_vec(),
+    })
+}
+
+/// Parse the parameters of a header value, e.g. `name` and `filename` in
+/// `form-data; name="a"; filename="a;b.txt"`, where values can be quoted.
+pub(crate) fn parameters(value: &str) -> Vec<(String, String)> {
+    let mut parameters = vec![];
+    let mut rest = value;
+    while let Some(index) = rest.find(';') {
+        rest = &rest[index + 1..];
+        let Some((name, after)) = rest.split_once('=') else {
+            break;
+        };
+        let after = after.trim_start();
+        let (value, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = quoted.len();
+                while let Some((index, c)) = chars.next() {
+                    match c {
+                        '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                        '"' => {
+                            end = index + 1;
+                            break;
+                        }
+                        c => value.push(c),
+                    }
+                }
+                (value, &quoted[end..])
+            }
+            None => {
+                let end = after.find(';').unwrap_or(after.len());
+                (after[..end].trim().to_string(), &after[end..])
+            }
+        };
+        parameters.push((name.trim().to_string(), value));
+        rest = remaining;
+    }
+    parameters
+}
+
+/// Split a comma-separated header value, e.g. the media types of an `Accept`
+/// header, leaving commas in quoted parameter values alone.
+pub(crate) fn list(value: &str) -> Vec<&str> {
+    let mut items = vec![];
+    let mut start = 0;
+    let mut quoted = false;
+    let mut chars = value.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' if quoted => {
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                items.push(value[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(value[start..].trim());
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+/// Find where a sequence of bytes first is, from an index on.
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|index| index + from)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_urlencoded_field() {
+        let body = b"name=Ada+Lovelace&lang=en%2Dgb&empty=";
+        assert_eq!(
+            urlencoded_field(body, "name").as_deref(),
+            Some("Ada Lovelace")
+        );
+        assert_eq!(urlencoded_field(body, "lang").as_deref(), Some("en-gb"));
+        assert_eq!(urlencoded_field(body, "empty").as_deref(), Some(""));
+        assert_eq!(urlencoded_field(body, "age"), None);
+    }
+
+    #[test]
+    fn test_list() {
+        assert_eq!(
+            list("text/html, text/plain; note=\"a, \\\"b\\\"\" ,, */*"),
+            vec!["text/html", "text/plain; note=\"a, \\\"b\\\"\"", "*/*"]
+        );
+        assert!(list(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_multipart_parts() {
+        let content_type = "multipart/form-data; boundary=\"XyZ\"";
+        let body = b"preamble\r\n--XyZ\r\n\
+            Content-Disposition: form-data; name=\"note\"\r\n\
+            \r\n\
+            hello\r\nworld\r\n\
+            --XyZ\r\n\
+            content-disposition: form-data; name=\"avatar\"; filename=\"me;1.png\"\r\n\
+            Content-Type: image/png\r\n\
+            \r\n\
+            \x89PNG\r\n\
+            --XyZ--\r\n";
+        assert_eq!(
+            multipart_parts(content_type, body).unwrap(),
+            vec![
+                Part {
+                    name: "note".to_string(),
+                    filename: None,
+                    content_type: None,
+                    content: b"hello\r\nworld".to_vec(),
+                },
+                Part {
+                    name: "avatar".to_string(),
+                    filename: Some("me;1.png".to_string()),
+                    content_type: Some("image/png".to_string()),
+                    content: b"\x89PNG".to_vec(),
+                },
+            ]
+        );
+
+        assert_eq!(multipart_parts("text/plain", body), None);
+        assert_eq!(multipart_parts("multipart/form-data", body), None);
+        assert_eq!(
+            multipart_parts("multipart/form-data; boundary=XyZ", b"--XyZ--"),
+            Some(vec![])
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Imports of HAR files, the HTTP archives that browser devtools and proxies
+//! export, as Impostor files.
+
+use axum::{
+    body::Bytes,
+    http::{
+        header::{CONTENT_ENCODING, CONTENT_TYPE},
+        HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri,
+    },
+};
+use impostor_core::ast::ImpostorFile;
+use serde::Deserialize;
+
+use crate::{
+    build, error, proxy,
+    record::{self, Exchange},
+};
+
+#[derive(Deserialize)]
+struct Har {
+    log: Log,
+}
+
+#[derive(Deserialize)]
+struct Log {
+    #[serde(default)]
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Deserialize)]
+struct HarEntry {
+    request: HarRequest,
+    response: HarResponse,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    #[serde(default)]
+    post_data: Option<PostData>,
+}
+
+#[derive(Deserialize)]
+struct PostData {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct HarResponse {
+    status: u16,
+    #[serde(default)]
+    headers: Vec<NameValue>,
+    #[serde(default)]
+    content: Content,
+}
+
+#[derive(Deserialize)]
+struct NameValue {
+    name: String,
+    value: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    #[serde(default)]
+    mime_type: String,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    encoding: Option<String>,
+}
+
+/// Generate an Impostor file from the requests and responses of a HAR file,
+/// with an entry per distinct request, in the order they were first sent.
+///
+/// Entries are built the way recordings build them: query strings become
+/// `queryparam` asserts, request bodies become `body` asserts, and response
+/// bodies are written as JSON, text or base64. Only requests to `hosts` are
+/// imported, unless it's empty, where a host matches with or without its
+/// port. Requests that never got a response are left out.
+pub fn import_har(contents: &str, hosts: &[String]) -> error::Result<ImpostorFile> {
+    let har: Har =
+        serde_json::from_str(contents).map_err(|e| error::Error::InvalidHar(e.to_string()))?;
+
+    let mut exchanges: Vec<Exchange> = vec![];
+    for entry in har.log.entries {
+        let Some(exchange) = exchange(entry, hosts) else {
+            continue;
+        };
+        if !exchanges.iter().any(|e| e.same_request(&exchange)) {
+            exchanges.push(exchange);
+        }
+    }
+    Ok(record::impostor_file(&exchanges))
+}
+
+fn exchange(entry: HarEntry, hosts: &[String]) -> Option<Exchange> {
+    let HarEntry { request, response } = entry;
+    let uri: Uri = request.url.parse().ok()?;
+    let authority = uri.authority()?;
+    let is_imported = hosts.is_empty()
+        || hosts.iter().any(|host| {
+            host.eq_ignore_ascii_case(authority.host())
+                || host.eq_ignore_ascii_case(authority.as_str())
+        });
+    if !is_imported {
+        return None;
+    }
+
+    // Blocked and aborted requests have a status of 0.
+    let status = StatusCode::from_u16(response.status)
+        .ok()
+        .filter(|status| status.as_u16() >= 100)?;
+    // The method has to be written the way the parser reads it.
+    let method = Method::from_bytes(request.method.as_bytes())
+        .ok()
+        .filter(|method| method.as_str().chars().all(|c| c.is_ascii_uppercase()));
+    let Some(method) = method else {
+        log::warn!(
+            "Not importing {} {}: unsupported method",
+            request.method,
+            uri
+        );
+        return None;
+    };
+
+    let content = response.content;
+    let body = match (content.text, content.encoding.as_deref()) {
+        (None, _) => Bytes::new(),
+        (Some(text), Some("base64")) => match build::decode_base64(&text) {
+            Some(bytes) => Bytes::from(bytes),
+            None => {
+                log::warn!("Not importing {} {}: invalid base64 body", method, uri);
+                return None;
+            }
+        },
+        (Some(text), _) => Bytes::from(text),
+    };
+
+    Some(Exchange {
+        method,
+        path: uri.path().to_string(),
+        query: uri.query().map(str::to_string),
+        request_body: Bytes::from(request.post_data.map(|data| data.text).unwrap_or_default()),
+        status,
+        headers: headers(response.headers, &content.mime_type),
+        body,
+    })
+}
+
+/// Build the headers of a response, without the hop-by-hop ones, as if it had
+/// been recorded. HAR files have bodies as they were decoded, so
+/// `Content-Encoding` is left out too, and so are headers that aren't valid,
+/// like HTTP/2's `:status`. Without a `Content-Type`, the media type HAR gives
+/// the body is used.
+fn headers(har_headers: Vec<NameValue>, mime_type: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for NameValue { name, value } in har_headers {
+        let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) else {
+            continue;
+        };
+        headers.append(name, value);
+    }
+    proxy::remove_hop_by_hop(&mut headers);
+    headers.remove(CONTENT_ENCODING);
+    if !headers.contains_key(CONTENT_TYPE) {
+        if let Ok(value) = HeaderValue::from_str(mime_type) {
+            if !value.is_empty() {
+                headers.insert(CONTENT_TYPE, value);
+            }
+        }
+    }
+    headers
+}
+
+#[cfg(test)]
+mod test {
+    use impostor_core::printer::print_impostor_file;
+
+    use super::*;
+
+    const HAR: &str = r#"{
+      "log": {
+        "version": "1.2",
+        "entries": [
+          {
+            "request": {"method": "GET", "url": "https://api.example.com/users?page=2&sort=name", "headers": []},
+            "response": {
+              "status": 200,
+              "headers": [
+                {"name": ":status", "value": "200"},
+                {"name": "content-type", "value": "application/json"},
+                {"name": "content-encoding", "value": "gzip"},
+                {"name": "transfer-encoding", "value": "chunked"}
+              ],
+              "content": {"size": 22, "mimeType": "application/json", "text": "[{\"name\": \"Ada\"}]"}
+            }
+          },
+          {
+            "request": {"method": "GET", "url": "https://api.example.com/users?page=2&sort=name"},
+            "response": {"status": 304, "headers": [], "content": {}}
+          },
+          {
+            "request": {
+              "method": "POST",
+              "url": "https://api.example.com:8443/users",
+              "postData": {"mimeType": "application/json", "text": "{\"name\": \"Grace\"}"}
+            },
+            "response": {"status": 201, "headers": [], "content": {"mimeType": "text/plain", "text": "created"}}
+          },
+          {
+            "request": {"method": "GET", "url": "https://api.example.com/logo.png"},
+            "response": {"status": 200, "headers": [], "content": {"mimeType": "image/png", "text": "AJ+Slg==", "encoding": "base64"}}
+          },
+          {
+            "request": {"method": "GET", "url": "https://cdn.example.com/app.js"},
+            "response": {"status": 200, "headers": [], "content": {"mimeType": "text/javascript", "text": "app"}}
+          },
+          {
+            "request": {"method": "GET", "url": "https://api.example.com/blocked"},
+            "response": {"status": 0, "headers": [], "content": {}}
+          }
+        ]
+      }
+    }"#;
+
+    #[test]
+    fn test_import_har() {
+        let file = import_har(HAR, &["api.example.com".to_string()]).unwrap();
+        assert_eq!(
+            print_impostor_file(&file),
+            r#"GET /users
+[Asserts]
+queryparam "page" == "2"
+queryparam "sort" == "name"
+HTTP 200
+Content-Type: application/json
+[{"name": "Ada"}]
+
+POST /users
+[Asserts]
+body == "{\"name\": \"Grace\"}"
+HTTP 201
+Content-Type: text/plain
+`created`
+
+GET /logo.png
+HTTP 200
+Content-Type: image/png
+base64,AJ+Slg==;
+"#
+        );
+
+        let file = import_har(HAR, &["api.example.com:8443".to_string()]).unwrap();
+        assert_eq!(file.entries.len(), 1);
+        assert_eq!(import_har(HAR, &[]).unwrap().entries.len(), 4);
+    }
+
+    #[test]
+    fn test_import_har_with_repeated_params() {
+        let har = r#"{"log": {"entries": [
+          {
+            "request": {"method": "GET", "url": "https://api.example.com/items"},
+            "response": {"status": 200, "content": {"mimeType": "text/plain", "text": "all"}}
+          },
+          {
+            "request": {"method": "GET", "url": "https://api.example.com/items?tag=a&tag=b"},
+            "response": {"status": 200, "content": {"mimeType": "text/plain", "text": "a and b"}}
+          },
+          {
+            "request": {"method": "GET", "url": "https://api.example.com/items?tag=b&tag=a"},
+            "response": {"status": 200, "content": {"mimeType": "text/plain", "text": "b and a"}}
+          }
+        ]}}"#;
+        let file = import_har(har, &[]).unwrap();
+        assert_eq!(
+            print_impostor_file(&file),
+            r#"GET /items
+[Asserts]
+queryparam "tag" includes "a"
+queryparam "tag" includes "b"
+queryparam "tag" count == 2
+HTTP 200
+Content-Type: text/plain
+`a and b`
+
+GET /items
+HTTP 200
+Content-Type: text/plain
+`all`
+"#
+        );
+    }
+
+    #[test]
+    fn test_import_invalid_har() {
+        assert!(import_har("{}", &[]).is_err());
+        assert!(import_har(r#"{"log": {}}"#, &[])
+            .unwrap()
+            .entries
+            .is_empty());
+    }
+}
@@ -0,0 +1,875 @@
+//! Exports of Impostor files as Hurl files, which test that a server running
+//! the mocks responds the way they say it does.
+//!
+//! Impostor's syntax comes from Hurl's, so a Hurl file is built as an Impostor
+//! AST, with full URLs for paths, and printed like any other file.
+
+use impostor_core::{
+    ast::{
+        Assert as AstAssert, Bytes as AstBytes, Entry as AstEntry, Header as AstHeader,
+        ImpostorFile, JsonValue, OptionKind, PredicateFuncValue, PredicateValue, QueryValue,
+        Request as AstRequest, Response as AstResponse, Template, TemplateElement,
+    },
+    printer::print_impostor_file,
+};
+
+use axum::http::uri::Authority;
+
+use crate::{
+    build,
+    dispatch::{self, ANY_METHOD, ANY_PATH},
+    entry, error, parse,
+    scenario::INITIAL_STATE,
+    template_value,
+};
+
+/// Generate a Hurl file that tests a server running the entries of an
+/// Impostor file, e.g. `impostor` itself, at `base_url`.
+///
+/// Each entry gets a request per response, in order, which expects that
+/// response's status, headers and body. Requests are made up to satisfy the
+/// entry's asserts: `queryparam`, `header`, `cookie`, `body` and `host` asserts
+/// give the values of their query params, headers, cookies, body and `Host`
+/// header, a `url` that's known in full gives the path, query and `Host` header,
+/// and path parameters are filled in with their names, e.g. `/users/:id`
+/// becomes `/users/id`. A value that only has to exist is one no other entry
+/// compares it to, so that the request doesn't match those entries instead.
+///
+/// Entries whose asserts can't be satisfied that way, e.g. because only the
+/// start of their `url` is known, entries with a fault, entries on any path
+/// (`*`) and entries with a proxy are skipped, with a comment saying why. Headers and bodies with templates aren't checked,
+/// since they change from one response to the next.
+pub fn export_hurl(contents: &str, base_url: &str) -> error::Result<String> {
+    let file = parse(contents)?;
+    let base_url = base_url.trim_end_matches('/');
+    let compared = compared_values(&file.entries);
+
+    let mut entries: Vec<AstEntry> = vec![];
+    let mut notes = vec![];
+
+    let mut scenarios = Scenarios::default();
+    let mut remaining: Vec<&AstEntry> = file.entries.iter().collect();
+    while let Some(entry) = scenarios.next(&mut remaining) {
+        let request = match synthesize(entry, &compared) {
+            Ok(request) => request,
+            Err(reason) => {
+                notes.push(skipped(entry, &reason));
+                continue;
+            }
+        };
+        scenarios.transition(entry);
+        for response in &entry.responses {
+            let first = entries.is_empty();
+            entries.push(AstEntry {
+                request: request.to_ast(base_url, first, &std::mem::take(&mut notes)),
+                responses: vec![expected_response(response)],
+                proxy: None,
+            });
+        }
+    }
+    for entry in remaining {
+        let (name, state, _) = scenario(entry).expect("only entries in a scenario remain");
+        let reason = format!(
+            "scenario {} never gets to state {}",
+            name,
+            state.unwrap_or_default()
+        );
+        notes.push(skipped(entry, &reason));
+    }
+
+    let line_terminators = if notes.is_empty() {
+        vec![]
+    } else {
+        build::line_terminators(entries.is_empty(), &notes)
+    };
+    Ok(print_impostor_file(&ImpostorFile {
+        entries,
+        line_terminators,
+    }))
+}
+
+/// Say why an entry has no requests.
+fn skipped(entry: &AstEntry, reason: &str) -> String {
+    format!(
+        "Skipped {} {} on line {}: {}.",
+        entry.request.method, entry.request.path, entry.request.space0.source_info.end.line, reason
+    )
+}
+
+/// The states of the scenarios of a file, as the requests for its entries
+/// would move them.
+#[derive(Default)]
+struct Scenarios {
+    states: Vec<(String, String)>,
+}
+
+impl Scenarios {
+    fn state(&self, name: &str) -> &str {
+        self.states
+            .iter()
+            .find(|(scenario, _)| scenario == name)
+            .map_or(INITIAL_STATE, |(_, state)| state)
+    }
+
+    /// Take the next entry to send requests for: the first one whose scenario
+    /// is in the state it requires and that leaves it there, so that other
+    /// entries in that state get their turn, or else the first one whose
+    /// scenario is in the state it requires. Returns `None` once no entry
+    /// left can match.
+    fn next<'a>(&self, remaining: &mut Vec<&'a AstEntry>) -> Option<&'a AstEntry> {
+        let is_ready = |entry: &&AstEntry| match scenario(entry) {
+            Some((name, Some(state), _)) => state == self.state(&name),
+            _ => true,
+        };
+        let keeps_state = |entry: &&AstEntry| match scenario(entry) {
+            Some((name, _, Some(next_state))) => next_state == self.state(&name),
+            _ => true,
+        };
+        let index = remaining
+            .iter()
+            .position(|entry| is_ready(entry) && keeps_state(entry))
+            .or_else(|| remaining.iter().position(is_ready))?;
+        Some(remaining.remove(index))
+    }
+
+    fn transition(&mut self, entry: &AstEntry) {
+        let Some((name, _, Some(next_state))) = scenario(entry) else {
+            return;
+        };
+        self.states.retain(|(scenario, _)| *scenario != name);
+        self.states.push((name, next_state));
+    }
+}
+
+/// The scenario of an entry, if it's in one, along with the state it requires
+/// and the state it moves it to.
+fn scenario(entry: &AstEntry) -> Option<(String, Option<String>, Option<String>)> {
+    let (mut name, mut state, mut next_state) = (None, None, None);
+    for option in entry.request.options() {
+        match option.kind {
+            OptionKind::Scenario(value) => name = Some(value),
+            OptionKind::State(value) => state = Some(value),
+            OptionKind::NextState(value) => next_state = Some(value),
+            _ => {}
+        }
+    }
+    Some((name?, state, next_state))
+}
+
+/// A request made up for an entry.
+#[derive(Debug, Default)]
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    cookies: Vec<(String, String)>,
+    body: Option<String>,
+}
+
+impl Request {
+    fn to_ast(&self, base_url: &str, first: bool, comments: &[String]) -> AstRequest {
+        let mut url = format!("{}{}", base_url, self.path);
+        for (index, (name, value)) in self.query.iter().enumerate() {
+            url.push(if index == 0 { '?' } else { '&' });
+            url.push_str(&encode_component(name));
+            url.push('=');
+            url.push_str(&encode_component(value));
+        }
+
+        let mut request = build::request(&self.method, &url, vec![], first, comments);
+        let mut headers = self.headers.clone();
+        if !self.cookies.is_empty() {
+            let cookies: Vec<String> = self
+                .cookies
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect();
+            headers.push(("Cookie".to_string(), cookies.join("; ")));
+        }
+        request.headers = headers
+            .iter()
+            .filter_map(|(name, value)| build::header(name, value))
+            .collect();
+        request.body = self.body.as_ref().map(|body| {
+            build::body(build::text(body).unwrap_or_else(|| build::base64_bytes(body.as_bytes())))
+        });
+        request
+    }
+}
+
+/// What an assert is on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Target {
+    Url,
+    QueryParam(String),
+    /// A header, by its lowercase name.
+    Header(String),
+    Cookie(String),
+    Body,
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Target::Url => write!(f, "url"),
+            Target::QueryParam(name) => write!(f, "queryparam \"{}\"", name),
+            Target::Header(name) => write!(f, "header \"{}\"", name),
+            Target::Cookie(name) => write!(f, "cookie \"{}\"", name),
+            Target::Body => write!(f, "body"),
+        }
+    }
+}
+
+/// What the asserts of an entry say about one of its targets.
+#[derive(Debug, Default, PartialEq)]
+struct Constraint {
+    equal: Option<String>,
+    not_equal: Vec<String>,
+    prefix: String,
+    infixes: Vec<String>,
+    suffix: String,
+    exists: bool,
+    not_exists: bool,
+}
+
+impl Constraint {
+    /// Apply an assert's predicate. Returns `None` if it's one no value can be
+    /// made up for.
+    fn add(&mut self, assert: &AstAssert) -> Option<()> {
+        let predicate = &assert.predicate;
+        match (&predicate.predicate_func.value, predicate.not) {
+            (PredicateFuncValue::Exist, false) => self.exists = true,
+            (PredicateFuncValue::Exist, true) => self.not_exists = true,
+            (PredicateFuncValue::Equal { value, .. }, false)
+            | (PredicateFuncValue::IncludeJson { value, .. }, false) => {
+                let value = predicate_value(value)?;
+                if self.equal.as_ref().is_some_and(|equal| *equal != value) {
+                    return None;
+                }
+                self.equal = Some(value);
+            }
+            (PredicateFuncValue::Equal { value, .. }, true)
+            | (PredicateFuncValue::NotEqual { value, .. }, false) => {
+                self.not_equal.push(predicate_value(value)?)
+            }
+            (PredicateFuncValue::StartWith { value, .. }, false) => {
+                self.prefix = longer(&self.prefix, &predicate_value(value)?, |a, b| {
+                    a.starts_with(b)
+                })?
+            }
+            (PredicateFuncValue::EndWith { value, .. }, false) => {
+                self.suffix = longer(&self.suffix, &predicate_value(value)?, |a, b| {
+                    a.ends_with(b)
+                })?
+            }
+            (PredicateFuncValue::Contain { value, .. }, false) => {
+                self.infixes.push(predicate_value(value)?)
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    /// Make up a value that satisfies the constraint, or `None` for a target
+    /// that mustn't exist. `compared` are the values other entries compare the
+    /// target to.
+    fn value(&self, compared: &[String]) -> Result<Option<String>, ()> {
+        if self.not_exists {
+            return if self.accepts(None) {
+                Ok(None)
+            } else {
+                Err(())
+            };
+        }
+
+        let value = match &self.equal {
+            Some(value) => value.clone(),
+            None => {
+                let value = format!("{}{}{}", self.prefix, self.infixes.concat(), self.suffix);
+                if !value.is_empty() {
+                    value
+                } else {
+                    // Nothing says what the value is, so it's one that doesn't
+                    // match other entries.
+                    (1..)
+                        .map(|n| n.to_string())
+                        .find(|value| !compared.contains(value) && !self.not_equal.contains(value))
+                        .expect("there's always a number nothing is compared to")
+                }
+            }
+        };
+        if self.accepts(Some(&value)) {
+            Ok(Some(value))
+        } else {
+            Err(())
+        }
+    }
+
+    /// Check if a value, or `None` for a missing one, satisfies the constraint.
+    fn accepts(&self, value: Option<&str>) -> bool {
+        let Some(value) = value else {
+            return self.equal.is_none()
+                && !self.exists
+                && self.prefix.is_empty()
+                && self.suffix.is_empty()
+                && self.infixes.is_empty();
+        };
+        !self.not_exists
+            && self.equal.as_ref().is_none_or(|equal| equal == value)
+            && value.starts_with(&self.prefix)
+            && value.ends_with(&self.suffix)
+            && self.infixes.iter().all(|infix| value.contains(infix))
+            && !self.not_equal.iter().any(|not_equal| not_equal == value)
+    }
+}
+
+/// Pick the longer of two strings when one of them satisfies the predicate
+/// for the other, e.g. a prefix of the other, since it satisfies both.
+fn longer(a: &str, b: &str, predicate: fn(&str, &str) -> bool) -> Option<String> {
+    if predicate(a, b) {
+        Some(a.to_string())
+    } else if predicate(b, a) {
+        Some(b.to_string())
+    } else {
+        None
+    }
+}
+
+/// Make up a request that the entry matches, or say why there isn't one.
+fn synthesize(entry: &AstEntry, compared: &[(Target, String)]) -> Result<Request, String> {
+    if entry.responses.is_empty() {
+        return Err("it proxies requests instead of responding".to_string());
+    }
+    let options = entry.request.options();
+    if options
+        .iter()
+        .any(|option| matches!(option.kind, OptionKind::Fault(_)))
+    {
+        return Err("its fault can't be tested".to_string());
+    }
+    let path = &entry.request.path;
+    if path.to_string() == ANY_PATH {
+        return Err("it's on any path".to_string());
+    }
+    if has_expressions(path) {
+        return Err("its path is a template".to_string());
+    }
+    if let Some(name) = entry::test_only_section(&entry.request) {
+        return Err(format!("its [{}] section is only for tests", name));
+    }
+
+    let mut constraints: Vec<(Target, Constraint)> = vec![];
+    for header in &entry.request.headers {
+        if has_expressions(&header.key) || has_expressions(&header.value) {
+            return Err("its headers are templates".to_string());
+        }
+        let target = Target::Header(template_value(&header.key).to_lowercase());
+        let value = template_value(&header.value);
+        let (target, constraint) = constraint(&mut constraints, target);
+        if constraint.equal.get_or_insert(value.clone()) != &value {
+            return Err(format!("no value can be made up for {}", target));
+        }
+    }
+    for assert in entry.request.asserts() {
+        let (target, constraint) = constraint(&mut constraints, target(&assert)?);
+        if constraint.add(&assert).is_none() {
+            return Err(format!("no value can be made up for {}", target));
+        }
+    }
+
+    let method = match entry.request.method.0.split('|').next() {
+        Some(ANY_METHOD) | None => "GET",
+        Some(method) => method,
+    };
+    let mut request = Request {
+        method: method.to_string(),
+        path: path
+            .to_string()
+            .split('/')
+            .map(|segment| segment.trim_start_matches([':', '*']))
+            .collect::<Vec<&str>>()
+            .join("/"),
+        ..Request::default()
+    };
+    if let Some(index) = constraints.iter().position(|(t, _)| *t == Target::Url) {
+        let (_, url) = constraints.remove(index);
+        apply_url(&url, &path.to_string(), &mut request, &mut constraints)?;
+    }
+    for (target, constraint) in &constraints {
+        let compared: Vec<String> = compared
+            .iter()
+            .filter(|(t, _)| t == target)
+            .map(|(_, value)| value.clone())
+            .collect();
+        let Ok(value) = constraint.value(&compared) else {
+            return Err(format!("no value can be made up for {}", target));
+        };
+        let Some(value) = value else {
+            continue;
+        };
+        match target {
+            Target::Url => unreachable!("the url is applied first"),
+            Target::QueryParam(name) => request.query.push((name.clone(), value)),
+            Target::Header(name) => request.headers.push((name.clone(), value)),
+            Target::Cookie(name) => request.cookies.push((name.clone(), value)),
+            Target::Body => request.body = Some(value),
+        }
+    }
+    Ok(request)
+}
+
+/// Make a request's URL the one a `url` constraint says it is: its path, its
+/// query in place of the query param constraints, which it has to satisfy, and
+/// its host as the `Host` header. Only an `http` URL that's known in full and
+/// is on the entry's path can be made up.
+fn apply_url(
+    url: &Constraint,
+    pattern: &str,
+    request: &mut Request,
+    constraints: &mut Vec<(Target, Constraint)>,
+) -> Result<(), String> {
+    if url.equal.is_none() {
+        if !url.prefix.is_empty() || !url.suffix.is_empty() || !url.infixes.is_empty() {
+            return Err("only part of its url is known".to_string());
+        }
+        if url.not_exists {
+            return Err("no value can be made up for url".to_string());
+        }
+        return Ok(());
+    }
+    let Ok(Some(url)) = url.value(&[]) else {
+        return Err("no value can be made up for url".to_string());
+    };
+    let Some(rest) = url.strip_prefix("http://") else {
+        return Err("its url isn't a full http one".to_string());
+    };
+    let (authority, path_and_query) = match rest.find(['/', '?']) {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+    let (path, query) = match path_and_query.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path_and_query, None),
+    };
+    let path = if path.is_empty() { "/" } else { path };
+    if !dispatch::path_matches(pattern, path) {
+        return Err(format!("its url's path {} isn't on its path", path));
+    }
+    request.path = path.to_string();
+
+    let Ok(host) = authority.parse::<Authority>() else {
+        return Err(format!("its url's host {} isn't valid", authority));
+    };
+    let (target, constraint) = constraint(constraints, Target::Header("host".to_string()));
+    match &constraint.equal {
+        // A `host` assert compares the host without the port.
+        Some(equal) if equal != authority && equal != host.host() => {
+            return Err(format!("no value can be made up for {}", target));
+        }
+        _ => constraint.equal = Some(authority.to_string()),
+    }
+
+    let params: Vec<(String, String)> =
+        form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+    let mut encoded = String::new();
+    for (index, (name, value)) in params.iter().enumerate() {
+        if index > 0 {
+            encoded.push('&');
+        }
+        encoded.push_str(&format!(
+            "{}={}",
+            encode_component(name),
+            encode_component(value)
+        ));
+    }
+    if query.is_some_and(|query| query != encoded) {
+        return Err("its url's query isn't encoded the way requests are".to_string());
+    }
+    for (target, constraint) in constraints.iter() {
+        let Target::QueryParam(name) = target else {
+            continue;
+        };
+        let values: Vec<&str> = params
+            .iter()
+            .filter(|(param, _)| param == name)
+            .map(|(_, value)| value.as_str())
+            .collect();
+        let accepted = match values[..] {
+            [] => constraint.accepts(None),
+            [value] => constraint.accepts(Some(value)),
+            // Several values are a list, which only `exists` is sure to accept.
+            _ => {
+                *constraint
+                    == Constraint {
+                        exists: true,
+                        ..Constraint::default()
+                    }
+            }
+        };
+        if !accepted {
+            return Err(format!("its url's query doesn't satisfy {}", target));
+        }
+    }
+    constraints.retain(|(target, _)| !matches!(target, Target::QueryParam(_)));
+    request.query = params;
+    Ok(())
+}
+
+/// Get the path and query of a URL, which may just be them.
+pub(crate) fn url_path(url: &str) -> String {
+    let Some((_, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    match rest.find('/') {
+        Some(index) => rest[index..].to_string(),
+        None => "/".to_string(),
+    }
+}
+
+/// Get the constraint on a target, adding it if there isn't one yet.
+fn constraint(
+    constraints: &mut Vec<(Target, Constraint)>,
+    target: Target,
+) -> &mut (Target, Constraint) {
+    let index = match constraints.iter().position(|(t, _)| *t == target) {
+        Some(index) => index,
+        None => {
+            constraints.push((target, Constraint::default()));
+            constraints.len() - 1
+        }
+    };
+    &mut constraints[index]
+}
+
+/// Find what an assert is on, or say why a value for it can't be made up.
+fn target(assert: &AstAssert) -> Result<Target, String> {
+    if !assert.filters.is_empty() {
+        return Err("its asserts have filters".to_string());
+    }
+    let target = match &assert.query.value {
+        QueryValue::Url => Target::Url,
+        QueryValue::Host => Target::Header("host".to_string()),
+        QueryValue::QueryParam { name, .. } => Target::QueryParam(template_value(name)),
+        QueryValue::Header { name, .. } => Target::Header(template_value(name).to_lowercase()),
+        QueryValue::Cookie { expr, .. } if expr.attribute.is_none() => {
+            Target::Cookie(template_value(&expr.name))
+        }
+        QueryValue::Body => Target::Body,
+        _ => return Err("its asserts query more than values".to_string()),
+    };
+    Ok(target)
+}
+
+/// The string a predicate compares to, if it's one.
+fn predicate_value(value: &PredicateValue) -> Option<String> {
+    match value {
+        PredicateValue::String(template) if !has_expressions(template) => {
+            Some(template_value(template))
+        }
+        PredicateValue::Number(number) => Some(number.to_string()),
+        PredicateValue::Bool(value) => Some(value.to_string()),
+        PredicateValue::Json(value) if !json_has_expressions(value) => Some(value.encoded()),
+        _ => None,
+    }
+}
+
+/// List the values entries compare each target to with `==`, to make up
+/// values that are none of them.
+fn compared_values(entries: &[AstEntry]) -> Vec<(Target, String)> {
+    let mut compared = vec![];
+    for entry in entries {
+        for assert in entry.request.asserts() {
+            let (Ok(target), PredicateFuncValue::Equal { value, .. }) =
+                (target(&assert), &assert.predicate.predicate_func.value)
+            else {
+                continue;
+            };
+            if let Some(value) = predicate_value(value) {
+                compared.push((target, value));
+            }
+        }
+    }
+    compared
+}
+
+/// Build the response a request expects: the entry's, without the headers
+/// and body that are templates.
+fn expected_response(response: &AstResponse) -> AstResponse {
+    let headers: Vec<AstHeader> = response
+        .headers
+        .iter()
+        .filter(|header| !has_expressions(&header.key) && !has_expressions(&header.value))
+        .filter_map(|header| {
+            build::header(&template_value(&header.key), &template_value(&header.value))
+        })
+        .collect();
+    let body = response
+        .body
+        .as_ref()
+        .filter(|body| !bytes_have_expressions(&body.value))
+        .map(|body| build::body(body.value.clone()));
+    build::response(response.status.value, headers, body)
+}
+
+fn has_expressions(template: &Template) -> bool {
+    template
+        .elements
+        .iter()
+        .any(|element| matches!(element, TemplateElement::Expression(_)))
+}
+
+fn bytes_have_expressions(bytes: &AstBytes) -> bool {
+    match bytes {
+        AstBytes::Json(value) => json_has_expressions(value),
+        AstBytes::OnelineString(template) => has_expressions(template),
+        AstBytes::MultilineString(multiline) => has_expressions(&multiline.value()),
+        _ => false,
+    }
+}
+
+fn json_has_expressions(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Expression(_) => true,
+        JsonValue::String(template) => has_expressions(template),
+        JsonValue::List { elements, .. } => elements
+            .iter()
+            .any(|element| json_has_expressions(&element.value)),
+        JsonValue::Object { elements, .. } => elements
+            .iter()
+            .any(|element| has_expressions(&element.name) || json_has_expressions(&element.value)),
+        _ => false,
+    }
+}
+
+/// Percent-encode a query param name or value.
+pub(crate) fn encode_component(s: &str) -> String {
+    let mut encoded = String::new();
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{
+        body::{self, Body},
+        http::{self, StatusCode},
+    };
+    use impostor_core::parser::parse_impostor_file;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_export_hurl() {
+        let contents = r#"GET /offers
+[Asserts]
+queryparam "id" == "1"
+HTTP 200
+{"name": "Offer 1"}
+
+GET /offers
+Accept: application/json
+[Asserts]
+queryparam "id" exists
+queryparam "q" startsWith "a b"
+queryparam "q" endsWith "c"
+cookie "session" == "abc"
+HTTP 404
+Content-Type: application/json
+X-Request-Id: {{uuid}}
+{"error": "Offer not found"}
+
+POST /offers/:id
+[Asserts]
+body contains "\"name\""
+queryparam "debug" not exists
+HTTP 201
+
+GET /better_is_authorized
+[Asserts]
+queryparam "user_name" matches /J(oh)?n/
+HTTP 200
+
+GET /eventually
+HTTP 503
+HTTP 200
+`finally up`
+"#;
+        assert_eq!(
+            export_hurl(contents, "http://localhost:3939/").unwrap(),
+            r#"GET http://localhost:3939/offers?id=1
+HTTP 200
+{"name": "Offer 1"}
+
+GET http://localhost:3939/offers?id=2&q=a%20bc
+Accept: application/json
+Cookie: session=abc
+HTTP 404
+Content-Type: application/json
+{"error": "Offer not found"}
+
+POST http://localhost:3939/offers/id
+`"name"`
+HTTP 201
+
+# Skipped GET /better_is_authorized on line 25: no value can be made up for queryparam "user_name".
+GET http://localhost:3939/eventually
+HTTP 503
+
+GET http://localhost:3939/eventually
+HTTP 200
+`finally up`
+"#
+        );
+
+        assert_eq!(
+            export_hurl(
+                "GET /a\n[QueryStringParams]\nx: 1 2\n[Cookies]\ns: t\nHTTP 200\n",
+                "http://localhost"
+            )
+            .unwrap(),
+            "# Skipped GET /a on line 1: its [QueryStringParams] section is only for tests.\n"
+        );
+        assert_eq!(
+            export_hurl(
+                "POST /a\n[Asserts]\nbody includesJson {\"x\": [1, 2]}\nHTTP 200\n",
+                "http://localhost"
+            )
+            .unwrap(),
+            "POST http://localhost/a\n`{\"x\": [1, 2]}`\nHTTP 200\n"
+        );
+        assert_eq!(
+            export_hurl(
+                "GET /a/:id\n[Asserts]\nhost == \"api.example.com\"\nurl == \"http://api.example.com:8080/a/1?x=1\"\nqueryparam \"x\" exists\nHTTP 200\n",
+                "http://localhost"
+            )
+            .unwrap(),
+            "GET http://localhost/a/1?x=1\nHost: api.example.com:8080\nHTTP 200\n"
+        );
+        assert_eq!(
+            export_hurl(
+                r#"GET /a
+[Asserts]
+url == "http://localhost:3939/a?x=1"
+queryparam "y" exists
+HTTP 200
+
+GET /b
+[Asserts]
+url startsWith "http://api."
+HTTP 200
+
+GET /a
+[Asserts]
+url == "http://api.example.com/a/b"
+HTTP 200
+"#,
+                "http://localhost"
+            )
+            .unwrap(),
+            r#"# Skipped GET /a on line 1: its url's query doesn't satisfy queryparam "y".
+# Skipped GET /b on line 7: only part of its url is known.
+# Skipped GET /a on line 12: its url's path /a/b isn't on its path.
+"#
+        );
+        assert_eq!(
+            export_hurl("ANY *\nPROXY http://localhost:8080\n", "http://localhost").unwrap(),
+            "# Skipped ANY * on line 1: it proxies requests instead of responding.\n"
+        );
+    }
+
+    /// The requests exported from the tour get the responses they expect from
+    /// the tour's mocks.
+    #[tokio::test]
+    async fn test_export_hurl_tour() {
+        let tour = include_str!("../../samples/tour.impostor");
+        assert!(assert_exported_requests_pass(tour).await >= 25);
+    }
+
+    #[tokio::test]
+    async fn test_export_hurl_url() {
+        let mocks = r#"GET /orders/:id
+[Asserts]
+url == "http://api.example.com/orders/1?expand=lines"
+HTTP 200
+`first`
+
+GET /orders/:id
+HTTP 200
+`second`
+"#;
+        assert_eq!(assert_exported_requests_pass(mocks).await, 2);
+    }
+
+    /// Send the requests exported from mocks to them, checking that they get
+    /// the responses they expect, and count them.
+    async fn assert_exported_requests_pass(mocks: &str) -> usize {
+        let exported = export_hurl(mocks, "http://localhost:3939").unwrap();
+        let router = crate::compile(mocks).unwrap();
+
+        let hurl = parse_impostor_file(&exported).unwrap();
+        let count = hurl.entries.len();
+        for entry in hurl.entries {
+            let request = entry.request;
+            let uri = template_value(&request.path).replace("http://localhost:3939", "");
+            let mut builder = http::Request::builder()
+                .method(request.method.0.as_str())
+                .uri(&uri);
+            for header in &request.headers {
+                builder =
+                    builder.header(template_value(&header.key), template_value(&header.value));
+            }
+            let body = match request.body.map(|body| body.value) {
+                Some(AstBytes::OnelineString(template)) => template_value(&template),
+                _ => String::new(),
+            };
+            let response = router
+                .clone()
+                .oneshot(builder.body(Body::from(body)).unwrap())
+                .await
+                .unwrap();
+
+            let expected = &entry.responses[0];
+            let label = format!("{} {}", request.method, uri);
+            assert_eq!(
+                response.status(),
+                StatusCode::from_u16(expected.status.value).unwrap(),
+                "{}",
+                label
+            );
+            for header in &expected.headers {
+                assert_eq!(
+                    response.headers()[template_value(&header.key)],
+                    template_value(&header.value),
+                    "{}",
+                    label
+                );
+            }
+            let body = body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            match expected.body.as_ref().map(|body| &body.value) {
+                Some(AstBytes::Json(value)) => assert_eq!(
+                    serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+                    serde_json::from_str::<serde_json::Value>(&value.encoded()).unwrap(),
+                    "{}",
+                    label
+                ),
+                Some(AstBytes::OnelineString(template)) => {
+                    assert_eq!(body, template_value(template), "{}", label)
+                }
+                _ => {}
+            }
+        }
+        count
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State as StateExtractor},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Serialize, Serializer};
+
+use crate::{read_body, state::State};
+
+/// How many requests the journal keeps before it starts dropping the oldest
+/// ones.
+const JOURNAL_CAPACITY: usize = 1000;
+
+/// A request received by a compiled router, as recorded in its journal.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct JournalEntry {
+    pub method: String,
+    pub uri: String,
+    pub headers: Vec<Header>,
+    /// The request body, as it was received. It's serialized as text, with
+    /// invalid UTF-8 replaced.
+    #[serde(serialize_with = "serialize_lossy")]
+    pub body: Bytes,
+    /// The id of the entry that handled the request, if any did.
+    pub entry: Option<usize>,
+    pub status: u16,
+    /// When the request was received, in milliseconds since the Unix epoch.
+    pub received_at: u64,
+    /// How long it took to respond, in milliseconds.
+    pub duration: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Header {
+    pub name: String,
+    pub value: String,
+}
+
+impl JournalEntry {
+    /// Rebuild the request this entry was recorded from, so that asserts can
+    /// be applied to it.
+    pub(crate) fn to_request(&self) -> Request {
+        let mut builder = Request::builder()
+            .method(self.method.as_str())
+            .uri(self.uri.as_str());
+        for header in &self.headers {
+            builder = builder.header(&header.name, &header.value);
+        }
+        let body = self.body.clone();
+        let mut request = builder
+            .body(Body::from(body.clone()))
+            .expect("the request was valid when it was recorded");
+        request.extensions_mut().insert(BufferedBody(body));
+        request
+    }
+}
+
+fn serialize_lossy<S: Serializer>(body: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&String::from_utf8_lossy(body))
+}
+
+/// The most recent requests received by a compiled router, oldest first.
+#[derive(Debug, Default)]
+pub(crate) struct Journal(VecDeque<JournalEntry>);
+
+impl Journal {
+    pub fn record(&mut self, entry: JournalEntry) {
+        if self.0.len() == JOURNAL_CAPACITY {
+            self.0.pop_front();
+        }
+        self.0.push_back(entry);
+    }
+
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        self.0.iter().cloned().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// Marks a response as sent by an entry, so that the journal can tell which
+/// entry handled a request.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MatchedEntry(pub usize);
+
+/// The body of a request, read ahead of time so that both the journal and
+/// `body` queries can get at it.
+#[derive(Clone, Debug)]
+pub(crate) struct BufferedBody(pub Bytes);
+
+/// Middleware that records every request and its response in the router's
+/// journal.
+///
+/// The request body is read in full before the request is handled, and added
+/// to the request's extensions as a [`BufferedBody`]. Requests whose body is
+/// larger than [`crate::MAX_BODY_SIZE`] get a 413 instead, and aren't recorded.
+pub(crate) async fn record(
+    StateExtractor(state): StateExtractor<Arc<State>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let received_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_millis() as u64);
+    let start = Instant::now();
+
+    let (parts, body) = request.into_parts();
+    let body = match read_body(body).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let mut request = Request::from_parts(parts, Body::from(body.clone()));
+    request.extensions_mut().insert(BufferedBody(body.clone()));
+
+    let method = request.method().to_string();
+    let uri = request.uri().to_string();
+    let headers = request
+        .headers()
+        .iter()
+        .map(|(name, value)| Header {
+            name: name.to_string(),
+            value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+        })
+        .collect();
+
+    let response = next.run(request).await;
+
+    state.record(JournalEntry {
+        method,
+        uri,
+        headers,
+        body,
+        entry: response
+            .extensions()
+            .get::<MatchedEntry>()
+            .map(|matched| matched.0),
+        status: response.status().as_u16(),
+        received_at,
+        duration: start.elapsed().as_millis() as u64,
+    });
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn journal_entry(status: u16) -> JournalEntry {
+        JournalEntry {
+            method: "POST".to_string(),
+            uri: "/orders?id=1".to_string(),
+            headers: vec![Header {
+                name: "x-foo".to_string(),
+                value: "bar".to_string(),
+            }],
+            body: Bytes::from_static(b"hello \xff"),
+            entry: None,
+            status,
+            received_at: 0,
+            duration: 0,
+        }
+    }
+
+    #[test]
+    fn test_journal_drops_oldest_entries() {
+        let mut journal = Journal::default();
+        for status in 0..JOURNAL_CAPACITY + 2 {
+            journal.record(journal_entry(status as u16));
+        }
+
+        let entries = journal.entries();
+        assert_eq!(entries.len(), JOURNAL_CAPACITY);
+        assert_eq!(entries[0].status, 2);
+    }
+
+    #[test]
+    fn test_to_request() {
+        let request = journal_entry(200).to_request();
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.uri(), "/orders?id=1");
+        assert_eq!(request.headers().get("x-foo").unwrap(), "bar");
+        assert_eq!(
+            request.extensions().get::<BufferedBody>().unwrap().0,
+            &b"hello \xff"[..]
+        );
+    }
+
+    #[test]
+    fn test_serialize_body_lossily() {
+        let serialized = serde_json::to_value(journal_entry(200)).unwrap();
+        assert_eq!(serialized["body"], "hello \u{fffd}");
+    }
+}
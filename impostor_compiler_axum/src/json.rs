@@ -0,0 +1,150 @@
+//! Comparisons of JSON values by what they mean rather than how they're
+//! written, so that key order, whitespace and e.g. `1` and `1.0` don't matter.
+
+use impostor_core::ast::JsonValue;
+use serde_json::Value;
+
+use crate::template_value;
+
+/// Turn a JSON value of the AST into a JSON value, or `None` if it has
+/// expressions outside strings.
+pub(crate) fn value(value: &JsonValue) -> Option<Value> {
+    Some(match value {
+        JsonValue::Expression(_) => return None,
+        JsonValue::Number(number) => serde_json::from_str(number).ok()?,
+        JsonValue::String(template) => template_value(template).into(),
+        JsonValue::Boolean(value) => (*value).into(),
+        JsonValue::List { elements, .. } => Value::Array(
+            elements
+                .iter()
+                .map(|element| self::value(&element.value))
+                .collect::<Option<_>>()?,
+        ),
+        JsonValue::Object { elements, .. } => Value::Object(
+            elements
+                .iter()
+                .map(|element| Some((element.name.to_string(), self::value(&element.value)?)))
+                .collect::<Option<_>>()?,
+        ),
+        JsonValue::Null => Value::Null,
+    })
+}
+
+/// Check if two JSON values are equal, with numbers compared by value. Integers
+/// are compared exactly, so that ones too big for a float don't round to each
+/// other.
+pub(crate) fn equal(first: &Value, second: &Value) -> bool {
+    match (first, second) {
+        (Value::Number(first), Value::Number(second)) => {
+            first == second
+                || ((first.is_f64() || second.is_f64()) && first.as_f64() == second.as_f64())
+        }
+        (Value::Array(first), Value::Array(second)) => {
+            first.len() == second.len()
+                && first
+                    .iter()
+                    .zip(second)
+                    .all(|(first, second)| equal(first, second))
+        }
+        (Value::Object(first), Value::Object(second)) => {
+            first.len() == second.len()
+                && first.iter().all(|(name, first)| {
+                    second.get(name).is_some_and(|second| equal(first, second))
+                })
+        }
+        (first, second) => first == second,
+    }
+}
+
+/// Check if a JSON value has at least what another one has: each member of
+/// its objects, and each element of its arrays, in the same order unless
+/// `unordered`. Other values have to be equal.
+pub(crate) fn includes(actual: &Value, expected: &Value, unordered: bool) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => {
+            expected.iter().all(|(name, expected)| {
+                actual
+                    .get(name)
+                    .is_some_and(|actual| includes(actual, expected, unordered))
+            })
+        }
+        (Value::Array(actual), Value::Array(expected)) if unordered => {
+            includes_unordered(actual, expected, &mut vec![false; actual.len()])
+        }
+        (Value::Array(actual), Value::Array(expected)) => {
+            let mut actual = actual.iter();
+            expected.iter().all(|expected| {
+                actual
+                    .by_ref()
+                    .any(|actual| includes(actual, expected, unordered))
+            })
+        }
+        (actual, expected) => equal(actual, expected),
+    }
+}
+
+/// Check if each of the expected elements is included by a different one of
+/// the actual elements that isn't `used` yet.
+fn includes_unordered(actual: &[Value], expected: &[Value], used: &mut [bool]) -> bool {
+    let Some((first, rest)) = expected.split_first() else {
+        return true;
+    };
+    for (index, element) in actual.iter().enumerate() {
+        if used[index] || !includes(element, first, true) {
+            continue;
+        }
+        used[index] = true;
+        if includes_unordered(actual, rest, used) {
+            return true;
+        }
+        used[index] = false;
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_equal() {
+        assert!(equal(
+            &json!({"a": 1, "b": [1, 2.0]}),
+            &json!({"b": [1.0, 2], "a": 1})
+        ));
+        assert!(!equal(&json!({"a": 1}), &json!({"a": 1, "b": 2})));
+        assert!(!equal(&json!([1, 2]), &json!([2, 1])));
+        assert!(!equal(&json!("1"), &json!(1)));
+        assert!(!equal(
+            &json!(9007199254740993u64),
+            &json!(9007199254740992u64)
+        ));
+        assert!(!equal(
+            &json!(-9007199254740993i64),
+            &json!(-9007199254740992i64)
+        ));
+    }
+
+    #[test]
+    fn test_includes() {
+        let actual = json!({"name": "Ada", "tags": ["a", "b", "c"], "address": {"city": "London", "zip": "N1"}});
+        assert!(includes(&actual, &json!({}), false));
+        assert!(includes(
+            &actual,
+            &json!({"tags": ["a", "c"], "address": {"city": "London"}}),
+            false
+        ));
+        assert!(!includes(&actual, &json!({"tags": ["c", "a"]}), false));
+        assert!(includes(&actual, &json!({"tags": ["c", "a"]}), true));
+        assert!(!includes(&actual, &json!({"tags": ["a", "a"]}), true));
+        assert!(!includes(&actual, &json!({"name": "Grace"}), false));
+        assert!(!includes(&actual, &json!({"age": 36}), false));
+
+        // Elements are matched to different elements, whatever order they're
+        // tried in.
+        let actual = json!([{"id": 1, "role": "admin"}, {"id": 2}]);
+        assert!(includes(&actual, &json!([{}, {"role": "admin"}]), true));
+    }
+}
@@ -1,79 +1,140 @@
 //! A compiler from Impostor AST to an axum router.
 
-use std::collections::HashMap;
+use std::sync::Arc;
 
 use axum::{
-    extract::Request,
-    http::{HeaderMap, Method},
-    routing::{MethodFilter, MethodRouter},
+    body::{Body, Bytes},
+    http::StatusCode,
+    response::{IntoResponse, Response},
 };
-use impostor_core::ast::ImpostorFile;
+use impostor_core::ast::{ImpostorFile, Template};
 
 use crate::entry::Entry;
 
+mod admin;
 mod asserts;
+mod build;
+mod delay;
+mod dispatch;
 mod entry;
 mod error;
+mod fault;
+mod form;
+mod har;
+mod hurl;
+mod journal;
+mod json;
+mod mocks;
+mod near_miss;
+mod openapi;
+mod proxy;
+mod record;
+mod scenario;
+mod schema;
+mod self_test;
+mod serve;
+mod state;
 pub(crate) mod template;
+mod verify;
+
+pub use admin::admin_router;
+pub use error::Error;
+pub use har::import_har;
+pub use hurl::export_hurl;
+pub use journal::{Header, JournalEntry};
+pub use mocks::Mocks;
+pub use openapi::{check_openapi, export_openapi, import_openapi, Violation};
+pub use proxy::Upstream;
+pub use record::{record_router, Recording};
+pub use self_test::{run_tests, Failure, TestResult};
+pub use serve::serve;
+pub use state::{EntryInfo, Origin, Position, State};
 
 /// Compile the AST for a complete Impostor File into an axum router.
 pub fn compile_ast(ast: ImpostorFile) -> error::Result<axum::Router> {
-    let entries: Vec<Entry> = ast
-        .entries
+    compile_ast_with_state(ast).map(|(router, _)| router)
+}
+
+/// Compile the AST for a complete Impostor File into an axum router, along
+/// with a handle to the router's state.
+///
+/// The handle can be used to reset the state of the mocks, e.g. between
+/// tests, or to add and remove entries while the router is serving.
+pub fn compile_ast_with_state(ast: ImpostorFile) -> error::Result<(axum::Router, Arc<State>)> {
+    let state = Arc::new(State::new(compile_entries(ast)?)?);
+    Ok((state_router(state.clone()), state))
+}
+
+/// Compile an Impostor file into an axum router.
+pub fn compile(contents: &str) -> error::Result<axum::Router> {
+    compile_ast(parse(contents)?)
+}
+
+/// Compile an Impostor file into an axum router, along with a handle to the
+/// router's state.
+pub fn compile_with_state(contents: &str) -> error::Result<(axum::Router, Arc<State>)> {
+    compile_ast_with_state(parse(contents)?)
+}
+
+pub(crate) fn parse(contents: &str) -> error::Result<ImpostorFile> {
+    impostor_core::parser::parse_impostor_file(contents).map_err(error::Error::ParseError)
+}
+
+/// Compile the entries of an Impostor file. Entries don't have their ids yet,
+/// which they get when they're added to a router's state.
+pub(crate) fn compile_entries(ast: ImpostorFile) -> error::Result<Vec<Entry>> {
+    ast.entries
         .into_iter()
         .map(|entry| {
             entry
                 .try_into()
                 .map_err(error::Error::EntryCompilationError)
         })
-        .collect::<Result<Vec<_>, _>>()?;
-
-    let mut routes_to_entries: HashMap<(String, Method), Vec<Entry>> = HashMap::new();
-
-    for entry in entries {
-        let route = entry.path.clone();
-        let method = entry.method.clone();
-        let entries_for_this_route = routes_to_entries
-            .entry((route, method))
-            .or_insert(Vec::new());
-        entries_for_this_route.push(entry);
-    }
-
-    let mut router = axum::Router::new();
-
-    for ((route, method), entries) in routes_to_entries {
-        let method_filter: MethodFilter = match method.try_into() {
-            Ok(method_filter) => method_filter,
-            Err(e) => return Err(error::Error::InvalidMethod(Box::new(e))),
-        };
-
-        router = router.route(
-            &route,
-            MethodRouter::<()>::new().on(method_filter, |request: Request| async {
-                for entry in entries {
-                    let asserts_passed = entry.matches(&request);
-                    if asserts_passed {
-                        return entry.handler(request);
-                    }
-                }
-
-                (axum::http::StatusCode::NOT_FOUND, HeaderMap::new(), vec![])
-            }),
-        );
-    }
+        .collect()
+}
 
-    Ok(router)
+/// Build a router that serves the entries in `state`.
+pub(crate) fn state_router(state: Arc<State>) -> axum::Router {
+    axum::Router::new()
+        .fallback(dispatch::dispatch)
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            journal::record,
+        ))
+        .with_state(state)
 }
 
-/// Compile an Impostor file into an axum router.
-pub fn compile(contents: &str) -> error::Result<axum::Router> {
-    let parse_result = impostor_core::parser::parse_impostor_file(contents);
-    let ast = match parse_result {
-        Ok(ast) => ast,
-        Err(e) => return Err(error::Error::ParseError(e)),
-    };
+/// The largest body, of a request or of a response from an upstream server,
+/// that's read into memory, e.g. to match asserts against it or record it.
+pub(crate) const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// Read the body of a request into memory, or get the response to send
+/// instead: a 413 if it's larger than [`MAX_BODY_SIZE`], or a 400 if it can't
+/// be read.
+pub(crate) async fn read_body(body: Body) -> Result<Bytes, Response> {
+    axum::body::to_bytes(body, MAX_BODY_SIZE)
+        .await
+        .map_err(|e| {
+            let e = e.into_inner();
+            if e.downcast_ref::<http_body_util::LengthLimitError>()
+                .is_some()
+            {
+                let message = format!("body is larger than {} bytes", MAX_BODY_SIZE);
+                (StatusCode::PAYLOAD_TOO_LARGE, message).into_response()
+            } else {
+                let message = format!("failed to read body: {}", e);
+                (StatusCode::BAD_REQUEST, message).into_response()
+            }
+        })
+}
 
-    compile_ast(ast)
+/// The text of a template without expressions, with its escape sequences
+/// decoded and without the quotes around it, e.g. `a"b` for `"a\"b"`.
+pub(crate) fn template_value(template: &Template) -> String {
+    match template.delimiter {
+        Some(_) => template.to_string(),
+        None => possibly_trim_surrounding_quotes(template.to_string()),
+    }
 }
 
 /// Trim the first and last characters from a string if they match any of ', "
@@ -381,6 +442,137 @@ mod test {
         assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
     }
 
+    #[test]
+    fn test_compile_test_only_sections() {
+        let result = compile("GET /hello\n[Cookies]\nsession: abc\nHTTP 200\n");
+        assert!(matches!(
+            result,
+            Err(error::Error::EntryCompilationError(
+                entry::EntryCompilationError::TestOnlySection(name)
+            )) if name == "Cookies"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_compile_route_with_json_body_asserts() {
+        let contents = r#"
+            POST /users
+            [Asserts]
+            body == {"name": "Ada", "tags": [1, 2]}
+
+            HTTP 201
+
+            PUT /users
+            [Asserts]
+            body includesJson unordered {"tags": [2, 1]}
+
+            HTTP 200
+        "#;
+
+        let router = compile(contents).unwrap();
+
+        let status = |method: &'static str, body: &'static str| {
+            let request = axum::http::Request::builder()
+                .method(method)
+                .uri("/users")
+                .body(Body::from(body))
+                .unwrap();
+            let router = router.clone();
+            async move { router.oneshot(request).await.unwrap().status().as_u16() }
+        };
+
+        assert_eq!(
+            status("POST", r#"{ "tags": [1, 2.0], "name": "Ada" }"#).await,
+            201
+        );
+        assert_eq!(
+            status("POST", r#"{"name": "Ada", "tags": [2, 1]}"#).await,
+            404
+        );
+        assert_eq!(status("PUT", r#"{"id": 1, "tags": [3, 2, 1]}"#).await, 200);
+        assert_eq!(status("PUT", r#"{"id": 1, "tags": [2]}"#).await, 404);
+    }
+
+    #[tokio::test]
+    async fn test_compile_route_with_form_asserts() {
+        let contents = r#"
+            POST /signup
+            [Asserts]
+            form "plan" == "pro"
+
+            HTTP 201
+
+            POST /avatar
+            [Asserts]
+            multipart "avatar" contentType == "image/png"
+
+            HTTP 201
+        "#;
+
+        let router = compile(contents).unwrap();
+
+        let status = |uri: &'static str, content_type: &'static str, body: &'static str| {
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", content_type)
+                .body(Body::from(body))
+                .unwrap();
+            let router = router.clone();
+            async move { router.oneshot(request).await.unwrap().status().as_u16() }
+        };
+
+        let form = "application/x-www-form-urlencoded";
+        assert_eq!(status("/signup", form, "email=a%40b.c&plan=pro").await, 201);
+        assert_eq!(status("/signup", form, "plan=free").await, 404);
+        assert_eq!(status("/signup", "text/plain", "plan=pro").await, 404);
+
+        let multipart = "multipart/form-data; boundary=b";
+        let body = "--b\r\n\
+            Content-Disposition: form-data; name=\"avatar\"; filename=\"me.png\"\r\n\
+            Content-Type: image/png\r\n\r\n\
+            png\r\n\
+            --b--\r\n";
+        assert_eq!(status("/avatar", multipart, body).await, 201);
+        assert_eq!(status("/avatar", form, body).await, 404);
+    }
+
+    #[tokio::test]
+    async fn test_compile_route_with_normalizing_filters() {
+        let contents = r#"
+            POST /orders
+            [Asserts]
+            header "Content-Type" mediaType == "application/json;charset=utf-8"
+            header "X-Client" ignoreCase startsWith "Mobile"
+
+            HTTP 201
+        "#;
+
+        let router = compile(contents).unwrap();
+
+        let status = |content_type: &'static str, client: &'static str| {
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .uri("/orders")
+                .header("content-type", content_type)
+                .header("x-client", client)
+                .body(Body::empty())
+                .unwrap();
+            let router = router.clone();
+            async move { router.oneshot(request).await.unwrap().status().as_u16() }
+        };
+
+        assert_eq!(
+            status("Application/JSON ; charset=utf-8", "MOBILE-ios").await,
+            201
+        );
+        assert_eq!(status("application/json", "mobile-ios").await, 404);
+        assert_eq!(status("application/json; charset=utf-8", "web").await, 404);
+
+        let result = compile("GET /a\n[Asserts]\nheader \"b\" toInt == 1\nHTTP 200\n");
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_compile_multiple_entries_for_route() {
         let contents = r#"
@@ -481,6 +673,736 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_compile_routes_match_like_axum() {
+        let contents = r#"
+            GET /orders/:id
+            HTTP 200
+            `by id`
+
+            GET /orders/new
+            HTTP 200
+            `new`
+        "#;
+
+        let router = compile(contents).unwrap();
+
+        let request = axum::http::Request::builder()
+            .uri("/orders/new")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.as_ref(), b"new");
+
+        let request = axum::http::Request::builder()
+            .method("HEAD")
+            .uri("/orders/1")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/orders/1")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::METHOD_NOT_ALLOWED
+        );
+        assert_eq!(response.headers().get("allow").unwrap(), "GET,HEAD");
+    }
+
+    #[tokio::test]
+    async fn test_compile_verbose_not_found() {
+        let contents = r#"
+GET /orders/:id
+x-api-key: secret
+[Asserts]
+queryparam "expand" exists
+HTTP 200
+"#;
+
+        let (router, state) = compile_with_state(contents).unwrap();
+        state.set_verbose_not_found(true);
+
+        let request = axum::http::Request::builder()
+            .uri("/orders/1")
+            .header("x-api-key", "wrong")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            "No entry matched GET /orders/1.\n\
+             Entry at line 2 (GET /orders/:id) didn't match:\n  \
+             line 5: expected queryparam \"expand\" exists, got null\n  \
+             line 3: expected header \"x-api-key\" == \"secret\", got \"wrong\"\n"
+        );
+
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/order/1/")
+            .header("accept", "application/json")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let mismatches = &report["near_misses"][0]["mismatches"];
+        assert_eq!(mismatches[0]["expected"], "path /orders/:id");
+        assert_eq!(mismatches[0]["actual"], "/order/1/");
+        assert_eq!(mismatches[1]["expected"], "method GET");
+
+        state.set_verbose_not_found(false);
+        let request = axum::http::Request::builder()
+            .uri("/orders/1")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_compile_verbose_not_found_schema_errors() {
+        let contents = r#"
+POST /orders
+[Asserts]
+body matchesSchema {"type": "object", "required": ["id"], "properties": {"id": {"type": "integer"}}}
+HTTP 201
+"#;
+
+        let (router, state) = compile_with_state(contents).unwrap();
+        state.set_verbose_not_found(true);
+
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/orders")
+            .body(Body::from(r#"{"id": "1"}"#))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            "No entry matched POST /orders.\n\
+             Entry at line 2 (POST /orders) didn't match:\n  \
+             line 4: expected body matchesSchema {\"properties\":{\"id\":{\"type\":\"integer\"}},\"required\":[\"id\"],\"type\":\"object\"}, \
+             got \"$.id: expected integer, got \\\"1\\\"\"\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compile_fallbacks() {
+        let contents = r#"
+GET /orders/:id
+[Options]
+fallback: true
+HTTP 404
+`no such order`
+
+GET /orders/:id
+[Asserts]
+url == "/orders/1"
+HTTP 200
+`order 1`
+
+ANY *
+HTTP 501
+```
+No mock for:
+{{request}}```
+"#;
+
+        let router = compile(contents).unwrap();
+        async fn call(router: &axum::Router, method: &str, uri: &str) -> (u16, String) {
+            let request = axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("x-foo", "bar")
+                .body(Body::from("hello"))
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            let status = response.status().as_u16();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, String::from_utf8(body.to_vec()).unwrap())
+        }
+
+        assert_eq!(
+            call(&router, "GET", "/orders/1").await,
+            (200, "order 1".to_string())
+        );
+        assert_eq!(
+            call(&router, "GET", "/orders/2").await,
+            (404, "no such order".to_string())
+        );
+        assert_eq!(
+            call(&router, "DELETE", "/orders/1?force=true").await,
+            (
+                501,
+                "No mock for:\nDELETE /orders/1?force=true\nx-foo: bar\n\nhello".to_string()
+            )
+        );
+        assert_eq!(call(&router, "GET", "/nope").await.0, 501);
+    }
+
+    #[tokio::test]
+    async fn test_compile_method_wildcards() {
+        let contents = r#"
+GET|HEAD /file
+HTTP 200
+Content-Type: text/plain
+`file`
+
+GET /page
+HTTP 200
+`page`
+
+ANY /health
+HTTP 200
+
+PURGE /cache
+HTTP 204
+"#;
+
+        let router = compile(contents).unwrap();
+        async fn call(router: &axum::Router, method: &str, uri: &str) -> axum::response::Response {
+            let request = axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            router.clone().oneshot(request).await.unwrap()
+        }
+
+        for method in ["GET", "POST", "DELETE", "PROPFIND"] {
+            assert_eq!(call(&router, method, "/health").await.status(), 200);
+        }
+        assert_eq!(call(&router, "PURGE", "/cache").await.status(), 204);
+        assert_eq!(call(&router, "POST", "/file").await.status(), 405);
+
+        for uri in ["/file", "/page"] {
+            let response = call(&router, "HEAD", uri).await;
+            assert_eq!(response.status(), 200);
+            assert_eq!(response.headers().get("content-length").unwrap(), "4");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert!(body.is_empty());
+        }
+        let response = call(&router, "HEAD", "/file").await;
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "text/plain"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_compile_route_with_delay_option() {
+        let contents = r#"
+            GET /slow
+            [Options]
+            delay: 2s
+
+            HTTP 200
+            `finally`
+        "#;
+
+        let router = compile(contents).unwrap();
+
+        let request = axum::http::Request::builder()
+            .uri("/slow")
+            .body(Body::empty())
+            .unwrap();
+
+        let start = tokio::time::Instant::now();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert!(start.elapsed() >= std::time::Duration::from_secs(2));
+
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body_as_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body_as_bytes.as_ref(), b"finally");
+    }
+
+    #[tokio::test]
+    async fn test_compile_route_with_sequenced_responses() {
+        let contents = r#"
+            GET /flaky
+
+            HTTP 503
+
+            HTTP 503
+
+            HTTP 200
+            `up`
+        "#;
+
+        let router = compile(contents).unwrap();
+
+        let mut statuses = vec![];
+        for _ in 0..4 {
+            let request = axum::http::Request::builder()
+                .uri("/flaky")
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            statuses.push(response.status().as_u16());
+        }
+        assert_eq!(statuses, vec![503, 503, 200, 200]);
+    }
+
+    #[tokio::test]
+    async fn test_compile_route_with_sequenced_responses_after_last() {
+        let contents = r#"
+            GET /cycle
+            [Options]
+            after-last: cycle
+
+            HTTP 201
+
+            HTTP 202
+
+            GET /once
+            [Options]
+            after-last: not-found
+
+            HTTP 200
+        "#;
+
+        let router = compile(contents).unwrap();
+
+        let mut statuses = vec![];
+        for uri in ["/cycle", "/cycle", "/cycle", "/once", "/once"] {
+            let request = axum::http::Request::builder()
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            statuses.push(response.status().as_u16());
+        }
+        assert_eq!(statuses, vec![201, 202, 201, 200, 404]);
+    }
+
+    #[tokio::test]
+    async fn test_compile_routes_with_scenario() {
+        let contents = r#"
+            POST /orders
+            [Options]
+            scenario: checkout
+            next-state: pending
+
+            HTTP 201
+
+            POST /orders/capture
+            [Options]
+            scenario: checkout
+            state: pending
+            next-state: paid
+
+            HTTP 200
+
+            GET /orders
+            [Options]
+            scenario: checkout
+            state: pending
+
+            HTTP 200
+            `pending`
+
+            GET /orders
+            [Options]
+            scenario: checkout
+            state: paid
+
+            HTTP 200
+            `paid`
+        "#;
+
+        let (router, state) = compile_with_state(contents).unwrap();
+
+        async fn call(router: &axum::Router, method: &str, uri: &str) -> (u16, String) {
+            let request = axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            let status = response.status().as_u16();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, String::from_utf8(body.to_vec()).unwrap())
+        }
+
+        assert_eq!(call(&router, "GET", "/orders").await.0, 404);
+        assert_eq!(call(&router, "POST", "/orders/capture").await.0, 404);
+        assert_eq!(call(&router, "POST", "/orders").await.0, 201);
+        assert_eq!(call(&router, "GET", "/orders").await.1, "pending");
+        assert_eq!(call(&router, "POST", "/orders/capture").await.0, 200);
+        assert_eq!(call(&router, "GET", "/orders").await.1, "paid");
+
+        state.reset();
+        assert_eq!(call(&router, "GET", "/orders").await.0, 404);
+    }
+
+    #[test]
+    fn test_compile_state_without_scenario() {
+        let contents = r#"
+            GET /orders
+            [Options]
+            state: paid
+
+            HTTP 200
+        "#;
+
+        let result = compile(contents);
+        assert!(matches!(
+            result,
+            Err(error::Error::EntryCompilationError(
+                entry::EntryCompilationError::StateWithoutScenario
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_compile_proxy() {
+        // The upstream server echoes the requests it gets.
+        let upstream = axum::Router::new().fallback(|request: axum::extract::Request| async move {
+            let (parts, body) = request.into_parts();
+            let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+            let header = |name| {
+                parts
+                    .headers
+                    .get(name)
+                    .map_or("-", |value| value.to_str().unwrap())
+                    .to_string()
+            };
+            format!(
+                "{} {} {} {} {}",
+                parts.method,
+                parts.uri,
+                header("x-api-key"),
+                header("x-foo"),
+                String::from_utf8_lossy(&body)
+            )
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, upstream));
+
+        let contents = format!(
+            r#"
+            GET /orders/local
+            HTTP 200
+            `local`
+
+            ANY /orders/:id
+            PROXY http://{address}/api/
+            X-Api-Key: test
+            "#
+        );
+        let (router, state) = compile_with_state(&contents).unwrap();
+
+        async fn call(router: &axum::Router, method: &str, uri: &str) -> (u16, String) {
+            let request = axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("x-foo", "bar")
+                .body(Body::from("hello"))
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            let status = response.status().as_u16();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, String::from_utf8(body.to_vec()).unwrap())
+        }
+
+        assert_eq!(
+            call(&router, "GET", "/orders/local").await,
+            (200, "local".to_string())
+        );
+        assert_eq!(
+            call(&router, "PUT", "/orders/1?dry=true").await,
+            (200, "PUT /api/orders/1?dry=true test bar hello".to_string())
+        );
+        assert_eq!(call(&router, "GET", "/users").await.0, 404);
+
+        state.set_proxy_unmatched(Some(Upstream::new(&format!("http://{address}")).unwrap()));
+        assert_eq!(
+            call(&router, "POST", "/users").await,
+            (200, "POST /users - bar hello".to_string())
+        );
+        assert_eq!(
+            call(&router, "GET", "/orders/local").await,
+            (200, "local".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compile_proxy_with_large_response() {
+        let upstream = axum::Router::new().fallback(|| async { vec![b'x'; MAX_BODY_SIZE + 1] });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, upstream));
+
+        let router = compile(&format!("ANY *\nPROXY http://{}\n", address)).unwrap();
+        let response = router
+            .oneshot(
+                axum::http::Request::get("/large")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn test_compile_invalid_proxy() {
+        let result = compile("GET /orders\nPROXY ftp://localhost\n");
+        assert!(matches!(
+            result,
+            Err(error::Error::EntryCompilationError(
+                entry::EntryCompilationError::InvalidProxy(_)
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_compile_route_with_body_assert() {
+        let contents = r#"
+            POST /orders
+            [Asserts]
+            body contains "express"
+
+            HTTP 201
+        "#;
+
+        let (router, state) = compile_with_state(contents).unwrap();
+
+        for body in ["shipping: express", "shipping: standard"] {
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .uri("/orders")
+                .body(Body::from(body))
+                .unwrap();
+            router.clone().oneshot(request).await.unwrap();
+        }
+
+        let journal = state.journal();
+        assert_eq!(journal[0].status, 201);
+        assert_eq!(journal[0].entry, Some(0));
+        assert_eq!(journal[1].status, 404);
+        assert_eq!(journal[1].body, "shipping: standard");
+
+        let verified = state
+            .verify("POST /orders\n[Asserts]\nbody contains \"standard\"\n")
+            .unwrap();
+        assert_eq!(verified, vec![journal[1].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_compile_route_with_large_body() {
+        let (router, state) = compile_with_state("POST /uploads\nHTTP 201\n").unwrap();
+
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/uploads")
+            .body(Body::from(vec![b'x'; MAX_BODY_SIZE + 1]))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(state.journal().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_compile_route_with_truncated_body_fault() {
+        let contents = r#"
+            GET /truncated
+            [Options]
+            fault: truncated-body
+
+            HTTP 200
+            `0123456789`
+        "#;
+
+        let router = compile(contents).unwrap();
+
+        let request = axum::http::Request::builder()
+            .uri("/truncated")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        assert_eq!(response.headers().get("content-length").unwrap(), "10");
+        let body_as_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body_as_bytes.as_ref(), b"01234");
+    }
+
+    #[tokio::test]
+    async fn test_compile_route_with_connection_reset_fault() {
+        let contents = r#"
+            GET /reset
+            [Options]
+            fault: connection-reset
+
+            HTTP 200
+            `0123456789`
+        "#;
+
+        let router = compile(contents).unwrap();
+
+        let request = axum::http::Request::builder()
+            .uri("/reset")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await;
+        assert!(body.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_serve_with_malformed_response_fault() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let contents = r#"
+            GET /malformed
+            [Options]
+            fault: malformed-response
+
+            HTTP 200
+            `never sent`
+        "#;
+
+        let router = compile(contents).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, router));
+
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(b"GET /malformed HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = vec![];
+        stream.read_to_end(&mut response).await.unwrap();
+        assert_eq!(response, fault::MALFORMED_RESPONSE);
+    }
+
+    #[tokio::test]
+    async fn test_serve_http2() {
+        let router = compile("GET /hello\nHTTP 200\n`hello`\n").unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, router));
+
+        let stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        let (mut sender, connection) = hyper::client::conn::http2::handshake(
+            hyper_util::rt::TokioExecutor::new(),
+            hyper_util::rt::TokioIo::new(stream),
+        )
+        .await
+        .unwrap();
+        tokio::spawn(connection);
+        let request = axum::http::Request::get("http://localhost/hello")
+            .body(Body::empty())
+            .unwrap();
+        let response = sender.send_request(request).await.unwrap();
+        assert_eq!(response.version(), axum::http::Version::HTTP_2);
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = axum::body::to_bytes(Body::new(response.into_body()), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_serve_with_empty_response_fault() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let contents = r#"
+            GET /empty
+            [Options]
+            fault: empty-response
+
+            HTTP 200
+            `never sent`
+        "#;
+
+        let router = compile(contents).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, router));
+
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(b"GET /empty HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        // The connection may be closed or reset, but nothing comes before.
+        let mut response = vec![];
+        let _ = stream.read_to_end(&mut response).await;
+        assert_eq!(response, b"");
+    }
+
+    #[tokio::test]
+    async fn test_serve_with_stall_fault() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let contents = r#"
+            GET /stall
+            [Options]
+            fault: stall
+
+            HTTP 200
+            `never sent`
+        "#;
+
+        let router = compile(contents).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, router));
+
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(b"GET /stall HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut buffer = [0; 1024];
+        let read = tokio::time::timeout(
+            std::time::Duration::from_millis(300),
+            stream.read(&mut buffer),
+        )
+        .await;
+        assert!(read.is_err(), "expected no response, got {:?}", read);
+    }
+
     #[test]
     fn test_trim_quotes_for_single_quotes() {
         let result = possibly_trim_surrounding_quotes("'foo'".to_string());
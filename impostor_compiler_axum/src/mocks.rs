@@ -0,0 +1,186 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use axum::Router;
+use impostor_core::ast::{ImpostorFile, PredicateFuncValue, PredicateValue, RequestSectionValue};
+
+use crate::{compile_entries, entry::Entry, error, parse, state::State, state_router};
+
+/// Where a set of mocks was compiled from.
+#[derive(Clone, Debug)]
+enum Source {
+    File(PathBuf),
+    Contents(String),
+}
+
+impl Source {
+    fn compile(&self) -> error::Result<Vec<Entry>> {
+        match self {
+            Source::File(path) => {
+                let contents = std::fs::read_to_string(path).map_err(error::Error::Io)?;
+                let mut file = parse(&contents)?;
+                if let Some(dir) = path.parent() {
+                    resolve_files(&mut file, dir);
+                }
+                compile_entries(file)
+            }
+            Source::Contents(contents) => compile_entries(parse(contents)?),
+        }
+    }
+}
+
+/// Make the paths of the files asserts read, like schemas, relative to the
+/// directory of the Impostor file rather than the working directory.
+fn resolve_files(file: &mut ImpostorFile, dir: &Path) {
+    for entry in &mut file.entries {
+        for section in &mut entry.request.sections {
+            let RequestSectionValue::Asserts(asserts) = &mut section.value else {
+                continue;
+            };
+            for assert in asserts {
+                if let PredicateFuncValue::MatchSchema {
+                    value: PredicateValue::File(file),
+                    ..
+                } = &mut assert.predicate.predicate_func.value
+                {
+                    file.filename.value = dir.join(&file.filename.value).display().to_string();
+                }
+            }
+        }
+    }
+}
+
+/// A compiled Impostor file that can be reloaded while it's being served.
+#[derive(Clone)]
+pub struct Mocks {
+    source: Source,
+    state: Arc<State>,
+}
+
+impl Mocks {
+    /// Compile the Impostor file at `path`.
+    pub fn from_file(path: impl Into<PathBuf>) -> error::Result<Mocks> {
+        Mocks::new(Source::File(path.into()))
+    }
+
+    fn new(source: Source) -> error::Result<Mocks> {
+        let state = State::new(source.compile()?)?;
+        Ok(Mocks {
+            source,
+            state: Arc::new(state),
+        })
+    }
+
+    /// Compile the mocks again from their source, re-reading the file if they
+    /// came from one.
+    ///
+    /// Requests that are already being handled finish with the old entries.
+    /// Entries added at runtime are kept, but the state is reset. If compiling
+    /// fails, the old entries are kept.
+    pub fn reload(&self) -> error::Result<()> {
+        self.state.replace_file_entries(self.source.compile()?)?;
+        self.state.reset();
+        Ok(())
+    }
+
+    /// Get the state of the mocks.
+    pub fn state(&self) -> Arc<State> {
+        self.state.clone()
+    }
+
+    /// Build a router that serves the mocks.
+    pub fn router(&self) -> Router {
+        state_router(self.state.clone())
+    }
+}
+
+impl FromStr for Mocks {
+    type Err = error::Error;
+
+    /// Compile the contents of an Impostor file.
+    fn from_str(contents: &str) -> error::Result<Mocks> {
+        Mocks::new(Source::Contents(contents.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::body::Body;
+    use tower::util::ServiceExt;
+
+    use super::*;
+
+    async fn status(router: &Router, uri: &str) -> u16 {
+        let request = axum::http::Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        router
+            .clone()
+            .oneshot(request)
+            .await
+            .unwrap()
+            .status()
+            .as_u16()
+    }
+
+    #[tokio::test]
+    async fn test_reload_from_file() {
+        let path = std::env::temp_dir().join(format!(
+            "impostor_test_reload_{}.impostor",
+            std::process::id()
+        ));
+        std::fs::write(&path, "GET /before\nHTTP 200\n").unwrap();
+
+        let mocks = Mocks::from_file(&path).unwrap();
+        let router = mocks.router();
+        assert_eq!(status(&router, "/before").await, 200);
+        assert_eq!(mocks.state().entries()[0].hits, 1);
+
+        std::fs::write(&path, "GET /after\nHTTP 200\n").unwrap();
+        mocks.reload().unwrap();
+        assert_eq!(status(&router, "/before").await, 404);
+        assert_eq!(status(&router, "/after").await, 200);
+
+        std::fs::write(&path, "GET /broken\n").unwrap();
+        assert!(mocks.reload().is_err());
+        assert_eq!(status(&router, "/after").await, 200);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_schema_files_are_relative_to_the_file() {
+        let dir =
+            std::env::temp_dir().join(format!("impostor_test_schemas_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("schemas")).unwrap();
+        std::fs::write(
+            dir.join("schemas/order.json"),
+            r#"{"type": "object", "required": ["id"]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("api.impostor"),
+            "POST /orders\n[Asserts]\nbody matchesSchema file,schemas/order.json;\nHTTP 201\n",
+        )
+        .unwrap();
+
+        let router = Mocks::from_file(dir.join("api.impostor")).unwrap().router();
+        let status = |body: &'static str| {
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .uri("/orders")
+                .body(Body::from(body))
+                .unwrap();
+            let router = router.clone();
+            async move { router.oneshot(request).await.unwrap().status().as_u16() }
+        };
+        assert_eq!(status(r#"{"id": 1}"#).await, 201);
+        assert_eq!(status(r#"{"name": "Ada"}"#).await, 404);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
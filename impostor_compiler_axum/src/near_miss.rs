@@ -0,0 +1,196 @@
+use std::fmt::Display;
+
+use axum::extract::Request;
+use serde::Serialize;
+
+use crate::{dispatch::ANY_PATH, state::State};
+
+/// Something an entry expects that a request doesn't have.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct Mismatch {
+    /// The line of the file the expectation comes from.
+    pub line: usize,
+    /// The expectation, e.g. `header "x-foo" == "bar"`.
+    pub expected: String,
+    /// What the request has instead.
+    pub actual: serde_json::Value,
+}
+
+/// An entry that could have handled a request, and why it didn't.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct NearMiss {
+    pub entry: usize,
+    pub method: String,
+    pub path: String,
+    /// Where the entry's request starts in its file.
+    pub line: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// An explanation of why no entry handled a request.
+///
+/// It lists the entries whose path matches the request, or if there are none
+/// besides the entries on any path, the entries whose path is close to the
+/// request's, e.g. because of a typo in one of its segments or a trailing
+/// slash.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct Report {
+    pub method: String,
+    pub uri: String,
+    pub near_misses: Vec<NearMiss>,
+}
+
+impl Report {
+    pub fn new(request: &Request, state: &State) -> Report {
+        let path = request.uri().path();
+        let mut candidates = state.candidates(path);
+        if candidates.iter().all(|entry| entry.path == ANY_PATH) {
+            candidates.splice(0..0, state.similar(path));
+        }
+
+        Report {
+            method: request.method().to_string(),
+            uri: request.uri().to_string(),
+            near_misses: candidates
+                .iter()
+                .map(|entry| entry.near_miss(request, state))
+                .collect(),
+        }
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.near_misses.is_empty() {
+            return writeln!(
+                f,
+                "No entry matched {} {}, and no entry has a similar path.",
+                self.method, self.uri
+            );
+        }
+
+        writeln!(f, "No entry matched {} {}.", self.method, self.uri)?;
+        for near_miss in &self.near_misses {
+            writeln!(
+                f,
+                "Entry at line {} ({} {}) didn't match:",
+                near_miss.line, near_miss.method, near_miss.path
+            )?;
+            for mismatch in &near_miss.mismatches {
+                writeln!(
+                    f,
+                    "  line {}: expected {}, got {}",
+                    mismatch.line, mismatch.expected, mismatch.actual
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check if a request path is close to an entry's path: it has as many
+/// segments, ignoring trailing slashes, and at most one of them is different,
+/// either next to another one that's the same or close enough to be a typo.
+pub(crate) fn path_is_similar(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.trim_end_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    if pattern.len() != path.len() {
+        return false;
+    }
+
+    let literals: Vec<(&str, &str)> = pattern
+        .iter()
+        .zip(&path)
+        .filter(|(expected, _)| !expected.starts_with(':') && !expected.starts_with('*'))
+        // The empty segment before the first `/` is the same in every path.
+        .filter(|(expected, _)| !expected.is_empty())
+        .map(|(expected, actual)| (*expected, *actual))
+        .collect();
+    let different: Vec<&(&str, &str)> = literals
+        .iter()
+        .filter(|(expected, actual)| expected != actual)
+        .collect();
+    match different[..] {
+        [] => true,
+        [(expected, actual)] => literals.len() > 1 || is_typo(expected, actual),
+        _ => false,
+    }
+}
+
+/// Check if a segment differs from the expected one by at most two edits, and
+/// by fewer than its length, e.g. `order` for `orders` but not `b` for `a`.
+fn is_typo(expected: &str, actual: &str) -> bool {
+    let expected: Vec<char> = expected.chars().collect();
+    let actual: Vec<char> = actual.chars().collect();
+    // The Levenshtein distance, a row at a time.
+    let mut distances: Vec<usize> = (0..=actual.len()).collect();
+    for (i, e) in expected.iter().enumerate() {
+        let mut previous = distances[0];
+        distances[0] = i + 1;
+        for (j, a) in actual.iter().enumerate() {
+            let substitution = previous + usize::from(e != a);
+            previous = distances[j + 1];
+            distances[j + 1] = substitution.min(previous + 1).min(distances[j] + 1);
+        }
+    }
+    let distance = distances[actual.len()];
+    distance <= 2 && distance < expected.len().min(actual.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_path_is_similar() {
+        assert!(path_is_similar("/orders/:id", "/order/1"));
+        assert!(path_is_similar("/orders", "/orders/"));
+        assert!(path_is_similar("/orders/:id/items", "/orders/1/item"));
+        assert!(!path_is_similar("/orders/:id/items", "/order/1/item"));
+        assert!(!path_is_similar("/orders/:id", "/orders"));
+    }
+
+    #[test]
+    fn test_one_segment_path_is_similar() {
+        assert!(path_is_similar("/users", "/user"));
+        assert!(path_is_similar("/users", "/uesrs"));
+        assert!(path_is_similar("/users/", "/users"));
+        assert!(!path_is_similar("/users", "/health"));
+        assert!(!path_is_similar("/users", "/posts"));
+        assert!(!path_is_similar("/a", "/b"));
+    }
+
+    #[test]
+    fn test_report_display() {
+        let report = Report {
+            method: "GET".to_string(),
+            uri: "/orders/1".to_string(),
+            near_misses: vec![NearMiss {
+                entry: 0,
+                method: "GET".to_string(),
+                path: "/orders/:id".to_string(),
+                line: 3,
+                mismatches: vec![Mismatch {
+                    line: 5,
+                    expected: r#"header "x-foo" == "bar""#.to_string(),
+                    actual: serde_json::Value::Null,
+                }],
+            }],
+        };
+        assert_eq!(
+            report.to_string(),
+            "No entry matched GET /orders/1.\n\
+             Entry at line 3 (GET /orders/:id) didn't match:\n  \
+             line 5: expected header \"x-foo\" == \"bar\", got null\n"
+        );
+
+        let report = Report {
+            near_misses: vec![],
+            ..report
+        };
+        assert_eq!(
+            report.to_string(),
+            "No entry matched GET /orders/1, and no entry has a similar path.\n"
+        );
+    }
+}
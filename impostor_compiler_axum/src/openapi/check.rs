@@ -0,0 +1,365 @@
+use impostor_core::ast::{
+    Bytes as AstBytes, Entry as AstEntry, Response as AstResponse, SourceInfo,
+};
+use serde_yaml::Value;
+
+use crate::{
+    dispatch::{ANY_METHOD, ANY_PATH},
+    error, json,
+    openapi::{Operation, Spec},
+    parse, schema, template_value,
+};
+
+/// A way in which an entry doesn't match an OpenAPI document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// Where in the Impostor file the violation is.
+    pub source_info: SourceInfo,
+    pub message: String,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.source_info.start.line, self.source_info.start.column, self.message
+        )
+    }
+}
+
+/// Check the entries of an Impostor file against an OpenAPI 3 document, in
+/// YAML or JSON, so that mocks don't drift from the API they stand in for.
+///
+/// Each method and path of an entry must be an operation of the document, the
+/// status of each of its responses must be declared for the operation, its
+/// responses must have the headers the document requires, and its JSON bodies
+/// must match the schema of their media type. Paths match if they're the same
+/// but for path parameters, e.g. `/users/:id` and `/users/1` both match
+/// `/users/{id}`, and `HEAD` matches a `get` operation.
+///
+/// Entries with any method (`ANY`) or on any path (`*`) aren't checked, and
+/// neither are bodies with expressions outside strings, which aren't JSON
+/// until they're rendered.
+pub fn check_openapi(contents: &str, spec: &str) -> error::Result<Vec<Violation>> {
+    let file = parse(contents)?;
+    let spec = Spec::parse(spec)?;
+    let root = spec.json();
+    let operations = spec.operations();
+
+    let mut violations = vec![];
+    for entry in &file.entries {
+        check_entry(entry, &spec, &root, &operations, &mut violations);
+    }
+    Ok(violations)
+}
+
+fn check_entry(
+    entry: &AstEntry,
+    spec: &Spec,
+    root: &serde_json::Value,
+    operations: &[Operation],
+    violations: &mut Vec<Violation>,
+) {
+    let path = entry.request.path.to_string();
+    if path == ANY_PATH {
+        return;
+    }
+
+    for method in entry.request.method.0.split('|') {
+        if method == ANY_METHOD {
+            continue;
+        }
+        let Some(operation) = find_operation(operations, &method.to_lowercase(), &path) else {
+            violations.push(Violation {
+                // The request starts with the lines before it, so this is
+                // where its method and path are instead.
+                source_info: SourceInfo::new(
+                    entry.request.space0.source_info.end,
+                    entry.request.path.source_info.end,
+                ),
+                message: format!("{} {} isn't an operation of the document", method, path),
+            });
+            continue;
+        };
+
+        for response in &entry.responses {
+            check_response(response, spec, root, operation, violations);
+        }
+    }
+}
+
+fn check_response(
+    response: &AstResponse,
+    spec: &Spec,
+    root: &serde_json::Value,
+    operation: &Operation,
+    violations: &mut Vec<Violation>,
+) {
+    let status = response.status.value;
+    let Some(declared) = declared_response(spec, operation, status) else {
+        violations.push(Violation {
+            source_info: response.status.source_info,
+            message: format!(
+                "status {} isn't declared for {} {}",
+                status,
+                operation.method.to_uppercase(),
+                operation.path
+            ),
+        });
+        return;
+    };
+
+    let header = |name: &str| {
+        response
+            .headers
+            .iter()
+            .find(|header| template_value(&header.key).eq_ignore_ascii_case(name))
+            .map(|header| template_value(&header.value))
+    };
+    if let Some(headers) = declared["headers"].as_mapping() {
+        for (name, value) in headers {
+            let (Some(name), Some(value)) = (name.as_str(), spec.resolve(value)) else {
+                continue;
+            };
+            if value["required"] == Value::Bool(true) && header(name).is_none() {
+                violations.push(Violation {
+                    source_info: response.source_info,
+                    message: format!("missing required header {}", name),
+                });
+            }
+        }
+    }
+
+    let Some(body) = &response.body else {
+        return;
+    };
+    let AstBytes::Json(json) = &body.value else {
+        return;
+    };
+    let (Some(instance), Some(schema)) = (
+        json::value(json),
+        media_type_schema(declared, header("Content-Type").as_deref()),
+    ) else {
+        return;
+    };
+    let schema = serde_json::to_value(schema).unwrap_or(serde_json::Value::Bool(true));
+    for error in schema::validate(&schema, root, &instance) {
+        violations.push(Violation {
+            source_info: body.space0.source_info,
+            message: format!("body doesn't match the schema: {}", error),
+        });
+    }
+}
+
+/// Find the operation an entry's method and path are for. When several paths
+/// match, e.g. `/users/me` and `/users/{id}`, the one with the fewest
+/// parameters wins, since it's the most specific.
+fn find_operation<'a>(
+    operations: &'a [Operation<'a>],
+    method: &str,
+    path: &str,
+) -> Option<&'a Operation<'a>> {
+    let find = |method: &str| {
+        operations
+            .iter()
+            .filter(|operation| operation.method == method && path_matches(operation.path, path))
+            .min_by_key(|operation| operation.path.matches('{').count())
+    };
+    find(method).or_else(|| if method == "head" { find("get") } else { None })
+}
+
+/// Check if the path of an entry matches a path of the document, where each
+/// `{parameter}` matches a non-empty part of a segment.
+fn path_matches(spec_path: &str, path: &str) -> bool {
+    let mut pattern = String::from("^");
+    let mut rest = spec_path;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        pattern.push_str(&regex::escape(&rest[..start]));
+        pattern.push_str("[^/]+");
+        rest = &rest[start + end + 1..];
+    }
+    pattern.push_str(&regex::escape(rest));
+    pattern.push('$');
+    regex::Regex::new(&pattern).is_ok_and(|regex| regex.is_match(path))
+}
+
+/// Find the response an operation declares for a status: the one for the
+/// status itself, or else for its range, e.g. `4XX`, or else the default one.
+fn declared_response<'a>(
+    spec: &'a Spec,
+    operation: &Operation<'a>,
+    status: u16,
+) -> Option<&'a Value> {
+    let responses = operation.value["responses"].as_mapping()?;
+    let find = |key: &str| {
+        responses.iter().find_map(|(name, value)| {
+            let name = match name {
+                Value::Number(number) => number.to_string(),
+                Value::String(name) => name.to_uppercase(),
+                _ => return None,
+            };
+            (name == key).then_some(value)
+        })
+    };
+    let response = find(&status.to_string())
+        .or_else(|| find(&format!("{}XX", status / 100)))
+        .or_else(|| find("DEFAULT"))?;
+    spec.resolve(response)
+}
+
+/// Find the schema of a response for a media type, which is the one of its
+/// `Content-Type` header if it has one, or else the first JSON one.
+fn media_type_schema<'a>(response: &'a Value, content_type: Option<&str>) -> Option<&'a Value> {
+    let content = response["content"].as_mapping()?;
+    let media_type = content_type.map(|content_type| {
+        content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+    });
+    let (_, value) = content.iter().find(|(name, _)| {
+        let name = name.as_str().unwrap_or_default().to_lowercase();
+        match &media_type {
+            Some(media_type) => name == *media_type,
+            None => name.contains("json"),
+        }
+    })?;
+    value.get("schema")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SPEC: &str = r##"
+openapi: 3.0.3
+paths:
+  /users/me:
+    get:
+      responses:
+        "200":
+          description: OK
+  /users/{id}:
+    get:
+      responses:
+        200:
+          description: OK
+          headers:
+            X-Rate-Limit:
+              required: true
+              schema:
+                type: integer
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/User"
+        4XX:
+          $ref: "#/components/responses/Error"
+components:
+  responses:
+    Error:
+      description: Error
+      content:
+        application/json:
+          schema:
+            type: object
+            required: [error]
+  schemas:
+    User:
+      type: object
+      required: [id, name]
+      properties:
+        id:
+          type: integer
+        name:
+          type: string
+"##;
+
+    fn messages(contents: &str) -> Vec<String> {
+        check_openapi(contents, SPEC)
+            .unwrap()
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_path_matches() {
+        assert!(path_matches("/users/{id}", "/users/:id"));
+        assert!(path_matches("/users/{id}", "/users/1"));
+        assert!(path_matches("/files/{name}.json", "/files/a.json"));
+        assert!(!path_matches("/users/{id}", "/users/1/orders"));
+        assert!(!path_matches("/users/{id}", "/users/"));
+        assert!(!path_matches("/a.b", "/a_b"));
+    }
+
+    #[test]
+    fn test_check_openapi() {
+        let contents = r#"GET /users/:id
+HTTP 200
+X-Rate-Limit: 100
+{"id": 1, "name": "Ada"}
+
+HEAD /users/me
+HTTP 200
+
+GET /users/1
+HTTP 404
+{"error": "not found"}
+
+ANY *
+HTTP 404
+"#;
+        assert!(messages(contents).is_empty());
+    }
+
+    #[test]
+    fn test_check_openapi_violations() {
+        let contents = r#"POST /users
+HTTP 201
+
+GET /users/:id
+HTTP 200
+{"id": "1"}
+
+GET /users/:id
+HTTP 500
+
+GET /users/2
+HTTP 400
+{"message": "bad request"}
+
+GET /users/:id
+HTTP 200
+X-Rate-Limit: 100
+{"id": {{id}}, "name": "Ada"}
+
+PATCH /users/:id
+HTTP 200
+"#;
+        assert_eq!(
+            messages(contents),
+            vec![
+                "1:1: POST /users isn't an operation of the document",
+                "5:1: missing required header X-Rate-Limit",
+                "6:1: body doesn't match the schema: $: missing required property name",
+                "6:1: body doesn't match the schema: $.id: expected integer, got \"1\"",
+                "9:6: status 500 isn't declared for GET /users/{id}",
+                "13:1: body doesn't match the schema: $: missing required property error",
+                "20:1: PATCH /users/:id isn't an operation of the document",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_openapi_errors() {
+        assert!(check_openapi("GET /", "swagger: '2.0'").is_err());
+        assert!(check_openapi("GET", SPEC).is_err());
+    }
+}
@@ -0,0 +1,596 @@
+use axum::http::StatusCode;
+use impostor_core::ast::{
+    Assert as AstAssert, Bytes as AstBytes, Entry as AstEntry, ImpostorFile, JsonValue,
+    PredicateFuncValue, PredicateValue, QueryValue, Response as AstResponse,
+};
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+    dispatch::{ANY_METHOD, ANY_PATH},
+    error,
+    openapi::METHODS,
+    parse, template_value,
+};
+
+/// The header the entries of an imported document are picked with.
+const PREFER: &str = "prefer";
+
+/// Describe the entries of an Impostor file as an OpenAPI 3 document, in YAML.
+///
+/// Entries that share a path and method are merged into one operation, whose
+/// responses are the responses of all of them. The parameters of an operation
+/// come from the path parameters of its path, and from the `queryparam`,
+/// `header` and `cookie` asserts and the headers of its entries. A parameter
+/// is required if every entry of the operation has an assert on it, and its
+/// example is the value the first `==` assert on it compares it to. Response
+/// headers and bodies become examples.
+///
+/// Entries with any method (`ANY`), methods OpenAPI doesn't have, or on any
+/// path (`*`) are left out, and so are entries with a proxy, since there's no
+/// response to describe.
+pub fn export_openapi(contents: &str, title: &str) -> error::Result<String> {
+    let document = document(&parse(contents)?, title);
+    serde_yaml::to_string(&document).map_err(|e| error::Error::InvalidOpenApi(e.to_string()))
+}
+
+/// A path of the document, with its operations by method.
+struct Path {
+    path: String,
+    operations: Vec<(String, Operation)>,
+}
+
+#[derive(Default)]
+struct Operation {
+    /// How many entries were merged into the operation.
+    entries: usize,
+    parameters: Vec<Parameter>,
+    responses: Vec<Response>,
+}
+
+struct Parameter {
+    location: &'static str,
+    name: String,
+    /// How many of the operation's entries have an assert on the parameter.
+    entries: usize,
+    example: Option<String>,
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(String, Option<String>)>,
+    /// The examples of the response, by media type.
+    content: Vec<(String, Vec<Example>)>,
+}
+
+/// An example value, along with its name if it has one.
+type Example = (Option<String>, Value);
+
+fn document(file: &ImpostorFile, title: &str) -> Value {
+    let mut paths: Vec<Path> = vec![];
+    for entry in &file.entries {
+        let path = entry.request.path.to_string();
+        if path == ANY_PATH || entry.responses.is_empty() {
+            continue;
+        }
+        let path = openapi_path(&path);
+
+        for method in entry.request.method.0.split('|') {
+            let method = method.to_lowercase();
+            if method == ANY_METHOD.to_lowercase() || !METHODS.contains(&method.as_str()) {
+                continue;
+            }
+
+            let index = match paths.iter().position(|p| p.path == path) {
+                Some(index) => index,
+                None => {
+                    paths.push(Path {
+                        path: path.clone(),
+                        operations: vec![],
+                    });
+                    paths.len() - 1
+                }
+            };
+            let operations = &mut paths[index].operations;
+            let index = match operations.iter().position(|(m, _)| *m == method) {
+                Some(index) => index,
+                None => {
+                    operations.push((method.clone(), Operation::default()));
+                    operations.len() - 1
+                }
+            };
+            operations[index].1.add(entry);
+        }
+    }
+
+    let mut document = Mapping::new();
+    document.insert("openapi".into(), "3.0.3".into());
+    let mut info = Mapping::new();
+    info.insert("title".into(), title.into());
+    info.insert("version".into(), "1.0.0".into());
+    document.insert("info".into(), info.into());
+
+    let mut paths_value = Mapping::new();
+    for path in paths {
+        let mut item = Mapping::new();
+        for (method, operation) in path.operations {
+            item.insert(method.into(), operation.to_value(&path.path));
+        }
+        paths_value.insert(path.path.into(), item.into());
+    }
+    document.insert("paths".into(), paths_value.into());
+    document.into()
+}
+
+impl Operation {
+    /// Merge an entry into the operation.
+    fn add(&mut self, entry: &AstEntry) {
+        self.entries += 1;
+
+        let asserts = entry.request.asserts();
+        let mut seen: Vec<(&'static str, String)> = vec![];
+        for (location, name, example) in parameters(entry, &asserts) {
+            // An entry with several asserts on a parameter still counts once.
+            let key = (location, name.to_lowercase());
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+
+            let existing = self.parameters.iter_mut().find(|parameter| {
+                parameter.location == location && parameter.name.eq_ignore_ascii_case(&name)
+            });
+            match existing {
+                Some(parameter) => {
+                    parameter.entries += 1;
+                    parameter.example = parameter.example.take().or(example);
+                }
+                None => self.parameters.push(Parameter {
+                    location,
+                    name,
+                    entries: 1,
+                    example,
+                }),
+            }
+        }
+
+        let name = example_name(&asserts);
+        for response in &entry.responses {
+            self.add_response(response, name.clone());
+        }
+    }
+
+    fn add_response(&mut self, response: &AstResponse, name: Option<String>) {
+        let status = response.status.value;
+        let index = match self.responses.iter().position(|r| r.status == status) {
+            Some(index) => index,
+            None => {
+                self.responses.push(Response {
+                    status,
+                    headers: vec![],
+                    content: vec![],
+                });
+                self.responses.len() - 1
+            }
+        };
+        let existing = &mut self.responses[index];
+
+        let mut content_type = None;
+        for header in &response.headers {
+            let header_name = header.key.to_string();
+            let value = (!has_expressions(&header.value)).then(|| template_value(&header.value));
+            if header_name.eq_ignore_ascii_case("content-type") {
+                content_type = value;
+                continue;
+            }
+            if !existing
+                .headers
+                .iter()
+                .any(|(name, _)| name.eq_ignore_ascii_case(&header_name))
+            {
+                existing.headers.push((header_name, value));
+            }
+        }
+
+        let Some(body) = &response.body else {
+            return;
+        };
+        let (default_type, example) = match &body.value {
+            AstBytes::Json(value) => ("application/json", Some(json_value(value))),
+            AstBytes::Xml(value) => ("application/xml", Some(value.as_str().into())),
+            AstBytes::OnelineString(value) => ("text/plain", Some(template_value(value).into())),
+            AstBytes::MultilineString(value) => {
+                ("text/plain", Some(value.value().to_string().into()))
+            }
+            AstBytes::Base64(_) | AstBytes::Hex(_) | AstBytes::File(_) => {
+                ("application/octet-stream", None)
+            }
+        };
+        let media_type = content_type
+            .and_then(|value| value.split(';').next().map(|t| t.trim().to_string()))
+            .filter(|media_type| !media_type.is_empty())
+            .unwrap_or_else(|| default_type.to_string());
+
+        let index = match existing.content.iter().position(|(t, _)| *t == media_type) {
+            Some(index) => index,
+            None => {
+                existing.content.push((media_type, vec![]));
+                existing.content.len() - 1
+            }
+        };
+        let examples = &mut existing.content[index].1;
+        if let Some(example) = example {
+            if !examples.iter().any(|(_, value)| *value == example) {
+                examples.push((name, example));
+            }
+        }
+    }
+
+    fn to_value(&self, path: &str) -> Value {
+        let mut parameters: Vec<Value> = path_parameters(path)
+            .into_iter()
+            .map(|name| parameter_value(&name, "path", true, None))
+            .collect();
+        parameters.extend(self.parameters.iter().map(|parameter| {
+            parameter_value(
+                &parameter.name,
+                parameter.location,
+                parameter.entries == self.entries,
+                parameter.example.as_deref(),
+            )
+        }));
+
+        let mut operation = Mapping::new();
+        if !parameters.is_empty() {
+            operation.insert("parameters".into(), parameters.into());
+        }
+        let mut responses = Mapping::new();
+        for response in &self.responses {
+            responses.insert(response.status.to_string().into(), response.to_value());
+        }
+        operation.insert("responses".into(), responses.into());
+        operation.into()
+    }
+}
+
+impl Response {
+    fn to_value(&self) -> Value {
+        let description = StatusCode::from_u16(self.status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or("Response");
+
+        let mut response = Mapping::new();
+        response.insert("description".into(), description.into());
+        if !self.headers.is_empty() {
+            let mut headers = Mapping::new();
+            for (name, example) in &self.headers {
+                headers.insert(
+                    name.as_str().into(),
+                    schema_with_example(example.as_deref()),
+                );
+            }
+            response.insert("headers".into(), headers.into());
+        }
+        if !self.content.is_empty() {
+            let mut content = Mapping::new();
+            for (media_type, examples) in &self.content {
+                let mut media = Mapping::new();
+                match examples.as_slice() {
+                    [] => {}
+                    [(_, example)] => {
+                        media.insert("example".into(), example.clone());
+                    }
+                    examples => {
+                        let mut named = Mapping::new();
+                        for (index, (name, example)) in examples.iter().enumerate() {
+                            let name = name.clone().unwrap_or(format!("example{}", index + 1));
+                            let mut value = Mapping::new();
+                            value.insert("value".into(), example.clone());
+                            named.insert(name.into(), value.into());
+                        }
+                        media.insert("examples".into(), named.into());
+                    }
+                }
+                content.insert(media_type.as_str().into(), media.into());
+            }
+            response.insert("content".into(), content.into());
+        }
+        response.into()
+    }
+}
+
+/// Find the parameters an entry has asserts on, along with the value the
+/// first `==` assert on each compares it to. The headers of the request are
+/// parameters too.
+fn parameters(
+    entry: &AstEntry,
+    asserts: &[AstAssert],
+) -> Vec<(&'static str, String, Option<String>)> {
+    let mut parameters = vec![];
+    for assert in asserts {
+        if assert.predicate.not || prefer_selector(assert).is_some() {
+            continue;
+        }
+        let (location, name) = match &assert.query.value {
+            QueryValue::QueryParam { name, .. } => ("query", template_value(name)),
+            QueryValue::Header { name, .. } => ("header", template_value(name)),
+            QueryValue::Cookie { expr, .. } => ("cookie", template_value(&expr.name)),
+            _ => continue,
+        };
+        let example = match &assert.predicate.predicate_func.value {
+            PredicateFuncValue::Equal {
+                value: PredicateValue::String(value),
+                ..
+            } if !has_expressions(value) => Some(template_value(value)),
+            _ => None,
+        };
+        parameters.push((location, name, example));
+    }
+    for header in &entry.request.headers {
+        let example = (!has_expressions(&header.value)).then(|| template_value(&header.value));
+        parameters.push(("header", header.key.to_string(), example));
+    }
+
+    // OpenAPI describes these headers elsewhere, and ignores them as
+    // parameters.
+    parameters.retain(|(location, name, _)| {
+        *location != "header"
+            || !["accept", "content-type", "authorization"].contains(&name.to_lowercase().as_str())
+    });
+    parameters
+}
+
+/// Find what an assert picks, if it picks one of the examples of an imported
+/// document, e.g. `example=admin` for `header "Prefer" contains "example=admin"`.
+fn prefer_selector(assert: &AstAssert) -> Option<String> {
+    let QueryValue::Header { name, .. } = &assert.query.value else {
+        return None;
+    };
+    if !template_value(name).eq_ignore_ascii_case(PREFER) {
+        return None;
+    }
+    let PredicateFuncValue::Contain {
+        value: PredicateValue::String(value),
+        ..
+    } = &assert.predicate.predicate_func.value
+    else {
+        return None;
+    };
+    let value = template_value(value);
+    (value.starts_with("example=") || value.starts_with("code=")).then_some(value)
+}
+
+/// The name of the example an entry's asserts pick with a `Prefer` header, if
+/// any.
+fn example_name(asserts: &[AstAssert]) -> Option<String> {
+    asserts
+        .iter()
+        .filter_map(prefer_selector)
+        .find_map(|selector| selector.strip_prefix("example=").map(str::to_string))
+}
+
+fn has_expressions(template: &impostor_core::ast::Template) -> bool {
+    template
+        .elements
+        .iter()
+        .any(|element| matches!(element, impostor_core::ast::TemplateElement::Expression(_)))
+}
+
+fn parameter_value(name: &str, location: &str, required: bool, example: Option<&str>) -> Value {
+    let mut parameter = Mapping::new();
+    parameter.insert("name".into(), name.into());
+    parameter.insert("in".into(), location.into());
+    if required {
+        parameter.insert("required".into(), true.into());
+    }
+    let Value::Mapping(schema) = schema_with_example(example) else {
+        unreachable!("schemas are mappings");
+    };
+    parameter.extend(schema);
+    parameter.into()
+}
+
+/// Build the `schema` and `example` of a parameter or header. Values in
+/// Impostor files are strings.
+fn schema_with_example(example: Option<&str>) -> Value {
+    let mut schema = Mapping::new();
+    schema.insert("type".into(), "string".into());
+    let mut value = Mapping::new();
+    value.insert("schema".into(), schema.into());
+    if let Some(example) = example {
+        value.insert("example".into(), example.into());
+    }
+    value.into()
+}
+
+/// Turn a JSON body into a value for the document, keeping the order of its
+/// keys. Templates are written as they are in the file.
+fn json_value(value: &JsonValue) -> Value {
+    match value {
+        JsonValue::Expression(expr) => Value::String(format!("{{{{{}}}}}", expr)),
+        JsonValue::Number(number) => serde_yaml::from_str(number).unwrap_or(Value::Null),
+        JsonValue::String(template) => Value::String(template.to_string()),
+        JsonValue::Boolean(value) => Value::Bool(*value),
+        JsonValue::List { elements, .. } => Value::Sequence(
+            elements
+                .iter()
+                .map(|element| json_value(&element.value))
+                .collect(),
+        ),
+        JsonValue::Object { elements, .. } => Value::Mapping(
+            elements
+                .iter()
+                .map(|element| (element.name.to_string().into(), json_value(&element.value)))
+                .collect(),
+        ),
+        JsonValue::Null => Value::Null,
+    }
+}
+
+/// Turn an Impostor path into an OpenAPI path, e.g. `/users/:id` into
+/// `/users/{id}`.
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix([':', '*']) {
+            Some(name) if !name.is_empty() => format!("{{{}}}", name),
+            _ => segment.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// The names of the parameters of an OpenAPI path.
+fn path_parameters(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::openapi::import_openapi;
+
+    #[test]
+    fn test_openapi_path() {
+        assert_eq!(openapi_path("/users/:id/*rest"), "/users/{id}/{rest}");
+        assert_eq!(path_parameters("/users/{id}/{rest}"), vec!["id", "rest"]);
+    }
+
+    #[test]
+    fn test_export_openapi() {
+        let contents = r#"
+GET /users/:id
+X-Tenant: acme
+[Asserts]
+queryparam "fields" == "name"
+cookie "session" exists
+HTTP 200
+Content-Type: application/json
+Cache-Control: max-age=60
+{"id": 1, "name": "Ada"}
+
+GET|HEAD /users/:id
+X-Tenant: acme
+HTTP 404
+`no such user`
+
+ANY /health
+HTTP 200
+
+DELETE /users/:id
+HTTP 204
+"#;
+        assert_eq!(
+            export_openapi(contents, "Users").unwrap(),
+            r#"openapi: 3.0.3
+info:
+  title: Users
+  version: 1.0.0
+paths:
+  /users/{id}:
+    get:
+      parameters:
+      - name: id
+        in: path
+        required: true
+        schema:
+          type: string
+      - name: fields
+        in: query
+        schema:
+          type: string
+        example: name
+      - name: session
+        in: cookie
+        schema:
+          type: string
+      - name: X-Tenant
+        in: header
+        required: true
+        schema:
+          type: string
+        example: acme
+      responses:
+        '200':
+          description: OK
+          headers:
+            Cache-Control:
+              schema:
+                type: string
+              example: max-age=60
+          content:
+            application/json:
+              example:
+                id: 1
+                name: Ada
+        '404':
+          description: Not Found
+          content:
+            text/plain:
+              example: no such user
+    head:
+      parameters:
+      - name: id
+        in: path
+        required: true
+        schema:
+          type: string
+      - name: X-Tenant
+        in: header
+        required: true
+        schema:
+          type: string
+        example: acme
+      responses:
+        '404':
+          description: Not Found
+          content:
+            text/plain:
+              example: no such user
+    delete:
+      parameters:
+      - name: id
+        in: path
+        required: true
+        schema:
+          type: string
+      responses:
+        '204':
+          description: No Content
+"#
+        );
+    }
+
+    #[test]
+    fn test_export_imported_openapi() {
+        let spec = r#"
+openapi: 3.0.3
+paths:
+  /users/{id}:
+    get:
+      responses:
+        "200":
+          description: A user
+          content:
+            application/json:
+              examples:
+                ada:
+                  value: {id: 1, name: Ada}
+                admin:
+                  value: {id: 0, name: root}
+"#;
+        let imported = impostor_core::printer::print_impostor_file(&import_openapi(spec).unwrap());
+        let exported: Value =
+            serde_yaml::from_str(&export_openapi(&imported, "Users").unwrap()).unwrap();
+
+        let operation = &exported["paths"]["/users/{id}"]["get"];
+        assert_eq!(operation["parameters"].as_sequence().unwrap().len(), 1);
+        let examples = &operation["responses"]["200"]["content"]["application/json"]["examples"];
+        // The default example is picked without a `Prefer` header, so its name
+        // is lost.
+        assert_eq!(examples["admin"]["value"]["name"], "root");
+        assert_eq!(examples["example2"]["value"]["name"], "Ada");
+    }
+}
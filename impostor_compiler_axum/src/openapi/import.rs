@@ -0,0 +1,582 @@
+use impostor_core::ast::{
+    Assert as AstAssert, Body as AstBody, Bytes as AstBytes, Entry as AstEntry,
+    Header as AstHeader, ImpostorFile, PredicateFuncValue,
+};
+use serde_yaml::Value;
+
+use crate::{
+    build, error,
+    openapi::{impostor_path, Operation, Spec},
+};
+
+/// Generate an Impostor file from an OpenAPI 3 document, in YAML or JSON.
+///
+/// Each operation gets one entry per example of each of its responses, in the
+/// order they're written. Path templates become path parameters, and required
+/// query parameters become `exists` asserts. `example` and `examples` become
+/// the response bodies, as JSON if their media type is JSON.
+///
+/// An operation's default response is the first example of its first success
+/// response. It's the last of the operation's entries, so that it handles the
+/// requests the others don't. The others are picked with a `Prefer` header the
+/// way other OpenAPI mock servers do, e.g. `Prefer: code=404` for the first
+/// example of a 404 response, or `Prefer: example=admin` for a named example.
+/// An operation whose only response is `default` gets it as a 200 response,
+/// and one without any response is skipped with a warning.
+pub fn import_openapi(contents: &str) -> error::Result<ImpostorFile> {
+    let spec = Spec::parse(contents)?;
+
+    let mut entries = vec![];
+    for operation in spec.operations() {
+        let path = impostor_path(operation.path);
+        let required_params = required_query_params(&operation);
+        let label = operation.value["operationId"]
+            .as_str()
+            .or(operation.value["summary"].as_str());
+
+        let examples = examples(&spec, &operation);
+        if examples.is_empty() {
+            log::warn!(
+                "Not importing {} {}: no response with a status",
+                operation.method.to_uppercase(),
+                path
+            );
+            continue;
+        }
+        let default = examples
+            .iter()
+            .position(|example| (200..300).contains(&example.status))
+            .unwrap_or(0);
+
+        let mut alternatives = vec![];
+        let mut default_entry = None;
+        for (index, example) in examples.iter().enumerate() {
+            let mut asserts: Vec<AstAssert> = required_params
+                .iter()
+                .map(|name| build::assert(build::query_param(name), PredicateFuncValue::Exist))
+                .collect();
+            let preferences = if index == default {
+                vec![]
+            } else {
+                example.preferences(examples[default].status)
+            };
+            for preference in &preferences {
+                asserts.push(build::assert(
+                    build::header_query("Prefer"),
+                    build::contains(preference),
+                ));
+            }
+
+            let mut comment = match label {
+                Some(label) => format!("{}: {}", label, example.status),
+                None => example.status.to_string(),
+            };
+            if let Some(name) = &example.name {
+                comment.push_str(&format!(", example {}", name));
+            }
+
+            // Whether this is the file's first entry is only known once
+            // they're all in order.
+            let request = build::request(
+                &operation.method.to_uppercase(),
+                &path,
+                asserts,
+                false,
+                &[comment],
+            );
+            let entry = AstEntry {
+                request,
+                responses: vec![build::response(
+                    example.status,
+                    example.headers.clone(),
+                    example.body.clone(),
+                )],
+                proxy: None,
+            };
+            if index == default {
+                default_entry = Some(entry);
+            } else {
+                alternatives.push((preferences.len(), entry));
+            }
+        }
+
+        // Entries that need more of the `Prefer` header go first, so that the
+        // ones that need less don't take their requests.
+        alternatives.sort_by_key(|(preferences, _)| std::cmp::Reverse(*preferences));
+        entries.extend(alternatives.into_iter().map(|(_, entry)| entry));
+        entries.extend(default_entry);
+    }
+
+    if let Some(first) = entries.first_mut() {
+        first.request.line_terminators.remove(0);
+    }
+    Ok(ImpostorFile {
+        entries,
+        line_terminators: vec![],
+    })
+}
+
+/// A response an operation can send.
+struct Example {
+    status: u16,
+    /// The name of the example in the `examples` of its media type.
+    name: Option<String>,
+    /// Whether this is the first example of its response.
+    first: bool,
+    headers: Vec<AstHeader>,
+    body: Option<AstBody>,
+}
+
+impl Example {
+    /// What a `Prefer` header has to contain to pick this example over the
+    /// default response, whose status is `default_status`.
+    fn preferences(&self, default_status: u16) -> Vec<String> {
+        let mut preferences = vec![];
+        if self.status != default_status {
+            preferences.push(format!("code={}", self.status));
+        }
+        match &self.name {
+            Some(name) if !self.first || self.status == default_status => {
+                preferences.push(format!("example={}", name))
+            }
+            _ => {}
+        }
+        preferences
+    }
+}
+
+/// The names of the query parameters an operation requires.
+fn required_query_params(operation: &Operation) -> Vec<String> {
+    operation
+        .parameters
+        .iter()
+        .filter(|parameter| parameter["in"] == "query" && parameter["required"] == true)
+        .filter_map(|parameter| parameter["name"].as_str())
+        .map(str::to_string)
+        .collect()
+}
+
+/// List the examples of each response of an operation. Responses without
+/// examples get one without a body. Responses whose status isn't a number are
+/// skipped, but ranges like `2XX` use their first status, and the `default`
+/// response is used as a 200 one when it's the only response.
+fn examples(spec: &Spec, operation: &Operation) -> Vec<Example> {
+    let Some(responses) = operation.value["responses"].as_mapping() else {
+        return vec![];
+    };
+
+    let mut examples = vec![];
+    let mut default = None;
+    for (status, response) in responses {
+        let status = match status {
+            Value::Number(status) => status.as_u64().map(|status| status.to_string()),
+            Value::String(status) if status == "default" => {
+                default = Some(response);
+                continue;
+            }
+            Value::String(status) => Some(status.replace(['X', 'x'], "0")),
+            _ => None,
+        };
+        let Some(status) = status.and_then(|status| status.parse::<u16>().ok()) else {
+            continue;
+        };
+        examples.extend(response_examples(spec, status, response));
+    }
+    if examples.is_empty() {
+        if let Some(response) = default {
+            examples.extend(response_examples(spec, 200, response));
+        }
+    }
+    examples
+}
+
+/// List the examples of a response, or one without a body if it has none.
+fn response_examples(spec: &Spec, status: u16, response: &Value) -> Vec<Example> {
+    let Some(response) = spec.resolve(response) else {
+        return vec![];
+    };
+
+    let mut headers = response_headers(spec, response);
+    let (media_type, values) = match media_type(response) {
+        Some((media_type, media)) => {
+            headers.extend(build::header("Content-Type", media_type));
+            (media_type, media_values(spec, media))
+        }
+        None => ("", vec![]),
+    };
+    if values.is_empty() {
+        return vec![Example {
+            status,
+            name: None,
+            first: true,
+            headers,
+            body: None,
+        }];
+    }
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, value))| Example {
+            status,
+            name,
+            first: index == 0,
+            headers: headers.clone(),
+            body: body(media_type, value),
+        })
+        .collect()
+}
+
+/// Pick the media type of a response to use for its examples: the first JSON
+/// one, or the first one if none is JSON.
+fn media_type(response: &Value) -> Option<(&str, &Value)> {
+    let content = response["content"].as_mapping()?;
+    let media_types: Vec<(&str, &Value)> = content
+        .iter()
+        .filter_map(|(name, media)| Some((name.as_str()?, media)))
+        .collect();
+    media_types
+        .iter()
+        .find(|(name, _)| name.contains("json"))
+        .or(media_types.first())
+        .copied()
+}
+
+/// The examples of a media type, along with their names: the ones in its
+/// `examples`, or else its `example`, or else the `example` of its schema.
+/// Examples with only an `externalValue` are skipped.
+fn media_values<'a>(spec: &'a Spec, media: &'a Value) -> Vec<(Option<String>, &'a Value)> {
+    if let Some(examples) = media["examples"].as_mapping() {
+        return examples
+            .iter()
+            .filter_map(|(name, example)| {
+                let example = spec.resolve(example)?;
+                let value = example.get("value")?;
+                Some((name.as_str().map(str::to_string), value))
+            })
+            .collect();
+    }
+    if let Some(example) = media.get("example") {
+        return vec![(None, example)];
+    }
+    let schema = media.get("schema").and_then(|schema| spec.resolve(schema));
+    match schema.and_then(|schema| schema.get("example")) {
+        Some(example) => vec![(None, example)],
+        None => vec![],
+    }
+}
+
+/// Build the headers of a response from the examples of its `headers`.
+/// Headers without an example are left out, and so is `Content-Type`, which
+/// comes from the media type.
+fn response_headers(spec: &Spec, response: &Value) -> Vec<AstHeader> {
+    let Some(headers) = response["headers"].as_mapping() else {
+        return vec![];
+    };
+    headers
+        .iter()
+        .filter_map(|(name, header)| {
+            let name = name.as_str()?;
+            if name.eq_ignore_ascii_case("content-type") {
+                return None;
+            }
+            let header = spec.resolve(header)?;
+            let example = header.get("example").or_else(|| {
+                let schema = spec.resolve(header.get("schema")?)?;
+                schema.get("example")
+            })?;
+            let value = match example {
+                Value::String(value) => value.clone(),
+                Value::Number(value) => value.to_string(),
+                Value::Bool(value) => value.to_string(),
+                _ => return None,
+            };
+            build::header(name, &value)
+        })
+        .collect()
+}
+
+/// Build the body of an example: JSON if its media type is JSON or it isn't a
+/// string, text if it is a string, and base64 if neither can be written.
+fn body(media_type: &str, value: &Value) -> Option<AstBody> {
+    let text = match value {
+        Value::String(text) if !media_type.contains("json") => text.clone(),
+        value => {
+            let mut text = String::new();
+            json_text(value, 0, &mut text)?;
+            text
+        }
+    };
+    if text.is_empty() {
+        return None;
+    }
+
+    let value = build::json(&text)
+        .map(AstBytes::Json)
+        .or_else(|| build::text(&text))
+        .unwrap_or_else(|| build::base64_bytes(text.as_bytes()));
+    Some(build::body(value))
+}
+
+/// Write a YAML value as pretty JSON, keeping the order of its keys. Returns
+/// `None` if it can't be written as JSON, e.g. if it has a non-finite number.
+fn json_text(value: &Value, indent: usize, out: &mut String) -> Option<()> {
+    let newline = |out: &mut String, indent: usize| {
+        out.push('\n');
+        out.push_str(&"  ".repeat(indent));
+    };
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(value) => out.push_str(&value.to_string()),
+        Value::Number(value) => {
+            if value.as_f64().is_some_and(|value| !value.is_finite()) {
+                return None;
+            }
+            out.push_str(&value.to_string());
+        }
+        Value::String(value) => out.push_str(&serde_json::to_string(value).ok()?),
+        Value::Sequence(values) if values.is_empty() => out.push_str("[]"),
+        Value::Sequence(values) => {
+            out.push('[');
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                newline(out, indent + 1);
+                json_text(value, indent + 1, out)?;
+            }
+            newline(out, indent);
+            out.push(']');
+        }
+        Value::Mapping(mapping) if mapping.is_empty() => out.push_str("{}"),
+        Value::Mapping(mapping) => {
+            out.push('{');
+            for (index, (key, value)) in mapping.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                newline(out, indent + 1);
+                let key = match key {
+                    Value::String(key) => key.clone(),
+                    Value::Number(key) => key.to_string(),
+                    Value::Bool(key) => key.to_string(),
+                    _ => return None,
+                };
+                out.push_str(&serde_json::to_string(&key).ok()?);
+                out.push_str(": ");
+                json_text(value, indent + 1, out)?;
+            }
+            newline(out, indent);
+            out.push('}');
+        }
+        Value::Tagged(tagged) => json_text(&tagged.value, indent, out)?,
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use axum::body::Body;
+    use impostor_core::printer::print_impostor_file;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::compile_ast;
+
+    const SPEC: &str = r##"
+openapi: 3.0.3
+info:
+  title: Users
+  version: "1.0"
+paths:
+  /users:
+    get:
+      operationId: listUsers
+      parameters:
+        - name: page
+          in: query
+          required: true
+        - name: sort
+          in: query
+      responses:
+        "200":
+          description: The users
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  $ref: "#/components/schemas/User"
+              example:
+                - id: 1
+                  name: Ada
+  /users/{id}:
+    get:
+      summary: Get a user
+      responses:
+        "200":
+          description: A user
+          headers:
+            Cache-Control:
+              schema:
+                type: string
+                example: max-age=60
+          content:
+            application/json:
+              examples:
+                ada:
+                  value: {id: 1, name: Ada}
+                admin:
+                  $ref: "#/components/examples/Admin"
+        "404":
+          description: No such user
+          content:
+            text/plain:
+              example: no such user
+    delete:
+      responses:
+        "204":
+          description: Deleted
+        default:
+          description: Unexpected error
+components:
+  schemas:
+    User:
+      type: object
+      example: {id: 2, name: Grace}
+  examples:
+    Admin:
+      value: {id: 0, name: root, admin: true}
+"##;
+
+    #[test]
+    fn test_import_openapi() {
+        let file = import_openapi(SPEC).unwrap();
+        assert_eq!(
+            print_impostor_file(&file),
+            r#"# listUsers: 200
+GET /users
+[Asserts]
+queryparam "page" exists
+HTTP 200
+Content-Type: application/json
+[
+  {
+    "id": 1,
+    "name": "Ada"
+  }
+]
+
+# Get a user: 200, example admin
+GET /users/:id
+[Asserts]
+header "Prefer" contains "example=admin"
+HTTP 200
+Cache-Control: max-age=60
+Content-Type: application/json
+{
+  "id": 0,
+  "name": "root",
+  "admin": true
+}
+
+# Get a user: 404
+GET /users/:id
+[Asserts]
+header "Prefer" contains "code=404"
+HTTP 404
+Content-Type: text/plain
+`no such user`
+
+# Get a user: 200, example ada
+GET /users/:id
+HTTP 200
+Cache-Control: max-age=60
+Content-Type: application/json
+{
+  "id": 1,
+  "name": "Ada"
+}
+
+# 204
+DELETE /users/:id
+HTTP 204
+"#
+        );
+    }
+
+    #[test]
+    fn test_import_openapi_default_response() {
+        let spec = r#"
+openapi: 3.0.0
+paths:
+  /health:
+    get:
+      responses:
+        default:
+          description: Health
+          content:
+            text/plain:
+              example: ok
+  /ping:
+    get:
+      responses: {}
+"#;
+        let file = import_openapi(spec).unwrap();
+        assert_eq!(
+            print_impostor_file(&file),
+            "# 200\nGET /health\nHTTP 200\nContent-Type: text/plain\n`ok`\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_openapi_serves() {
+        let router = compile_ast(import_openapi(SPEC).unwrap()).unwrap();
+
+        let call = |uri: &str, prefer: Option<&str>| {
+            let mut request = axum::http::Request::builder().uri(uri);
+            if let Some(prefer) = prefer {
+                request = request.header("prefer", prefer);
+            }
+            let request = request.body(Body::empty()).unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status().as_u16();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let body = serde_json::Value::from_str(&String::from_utf8_lossy(&body));
+                (status, body.ok())
+            }
+        };
+
+        assert_eq!(call("/users", None).await.0, 404);
+        assert_eq!(call("/users?page=1", None).await.0, 200);
+        assert_eq!(
+            call("/users/1", None).await,
+            (200, Some(serde_json::json!({"id": 1, "name": "Ada"})))
+        );
+        assert_eq!(
+            call("/users/0", Some("example=admin")).await.1.unwrap()["admin"],
+            true
+        );
+        assert_eq!(call("/users/3", Some("code=404")).await.0, 404);
+    }
+
+    #[test]
+    fn test_json_text() {
+        let value: Value =
+            serde_yaml::from_str("{b: [1, 2.5], a: {}, c: [], d: \"x\\\"\"}").unwrap();
+        let mut text = String::new();
+        json_text(&value, 0, &mut text).unwrap();
+        assert_eq!(
+            text,
+            "{\n  \"b\": [\n    1,\n    2.5\n  ],\n  \"a\": {},\n  \"c\": [],\n  \"d\": \"x\\\"\"\n}"
+        );
+
+        let value: Value = serde_yaml::from_str(".inf").unwrap();
+        assert!(json_text(&value, 0, &mut String::new()).is_none());
+    }
+}
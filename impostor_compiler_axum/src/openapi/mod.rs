@@ -0,0 +1,198 @@
+//! Conversions between Impostor files and OpenAPI 3 documents, and checks of
+//! the former against the latter.
+
+mod check;
+mod export;
+mod import;
+
+use serde_yaml::Value;
+
+use crate::error;
+
+pub use check::{check_openapi, Violation};
+pub use export::export_openapi;
+pub use import::import_openapi;
+
+/// The methods an OpenAPI path item can have operations for.
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// How many `$ref`s in a row are followed before giving up, in case they
+/// loop.
+const MAX_REF_DEPTH: usize = 32;
+
+/// An OpenAPI 3 document, read from YAML or JSON.
+pub(crate) struct Spec {
+    root: Value,
+}
+
+/// An operation of an OpenAPI document, e.g. `get` on `/users/{id}`.
+pub(crate) struct Operation<'a> {
+    /// The path of the operation, as written in the document.
+    pub path: &'a str,
+    /// The method of the operation, in lowercase.
+    pub method: &'a str,
+    pub value: &'a Value,
+    /// The parameters of the operation, with `$ref`s followed, including the
+    /// ones it inherits from its path.
+    pub parameters: Vec<&'a Value>,
+}
+
+impl Spec {
+    pub fn parse(contents: &str) -> error::Result<Spec> {
+        let root: Value = serde_yaml::from_str(contents)
+            .map_err(|e| error::Error::InvalidOpenApi(e.to_string()))?;
+        let version = match &root["openapi"] {
+            Value::String(version) => version.as_str(),
+            _ => "",
+        };
+        if !version.starts_with("3.") {
+            return Err(error::Error::InvalidOpenApi(
+                "expected an OpenAPI 3 document".to_string(),
+            ));
+        }
+        Ok(Spec { root })
+    }
+
+    /// The document as JSON, e.g. to validate against its schemas, which
+    /// `$ref`s resolve within.
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::to_value(&self.root).unwrap_or(serde_json::Value::Null)
+    }
+
+    /// Follow the `$ref` of a value, if it has one, to the value it points
+    /// to. Only references within the document are supported, and they
+    /// resolve to `None` otherwise.
+    pub fn resolve<'a>(&'a self, mut value: &'a Value) -> Option<&'a Value> {
+        for _ in 0..MAX_REF_DEPTH {
+            let Some(reference) = value.get("$ref") else {
+                return Some(value);
+            };
+            let pointer = reference.as_str()?.strip_prefix("#/")?;
+            value = &self.root;
+            for token in pointer.split('/') {
+                let token = token.replace("~1", "/").replace("~0", "~");
+                value = value.get(token.as_str())?;
+            }
+        }
+        None
+    }
+
+    /// List the operations of the document, in the order they're written.
+    pub fn operations(&self) -> Vec<Operation<'_>> {
+        let Some(paths) = self.root["paths"].as_mapping() else {
+            return vec![];
+        };
+
+        let mut operations = vec![];
+        for (path, item) in paths {
+            let (Some(path), Some(item)) = (path.as_str(), self.resolve(item)) else {
+                continue;
+            };
+            for method in METHODS {
+                let Some(value) = item.get(method) else {
+                    continue;
+                };
+                let mut parameters = self.parameters(item);
+                for parameter in self.parameters(value) {
+                    // Parameters of an operation override those of its path.
+                    parameters.retain(|p| !same_parameter(p, parameter));
+                    parameters.push(parameter);
+                }
+                operations.push(Operation {
+                    path,
+                    method,
+                    value,
+                    parameters,
+                });
+            }
+        }
+        operations
+    }
+
+    fn parameters<'a>(&'a self, value: &'a Value) -> Vec<&'a Value> {
+        value["parameters"]
+            .as_sequence()
+            .into_iter()
+            .flatten()
+            .filter_map(|parameter| self.resolve(parameter))
+            .collect()
+    }
+}
+
+/// Check if two parameters are the same, which they are if they have the same
+/// name and location.
+fn same_parameter(a: &Value, b: &Value) -> bool {
+    a["name"] == b["name"] && a["in"] == b["in"]
+}
+
+/// Turn an OpenAPI path into an Impostor path, e.g. `/users/{id}` into
+/// `/users/:id`.
+pub(crate) fn impostor_path(path: &str) -> String {
+    let mut converted = String::new();
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        converted.push_str(&rest[..start]);
+        converted.push(':');
+        converted.push_str(&rest[start + 1..start + end]);
+        rest = &rest[start + end + 1..];
+    }
+    converted.push_str(rest);
+    converted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_impostor_path() {
+        assert_eq!(impostor_path("/users"), "/users");
+        assert_eq!(
+            impostor_path("/users/{id}/orders/{orderId}"),
+            "/users/:id/orders/:orderId"
+        );
+    }
+
+    #[test]
+    fn test_spec() {
+        let spec = Spec::parse(
+            r##"
+            openapi: 3.0.3
+            paths:
+              /users/{id}:
+                parameters:
+                  - $ref: "#/components/parameters/Id"
+                  - name: verbose
+                    in: query
+                get:
+                  parameters:
+                    - name: verbose
+                      in: query
+                      required: true
+                delete: {}
+            components:
+              parameters:
+                Id:
+                  name: id
+                  in: path
+            "##,
+        )
+        .unwrap();
+
+        let operations = spec.operations();
+        assert_eq!(operations.len(), 2);
+        assert_eq!(operations[0].method, "get");
+        assert_eq!(operations[0].parameters.len(), 2);
+        assert_eq!(operations[0].parameters[0]["name"], "id");
+        assert_eq!(operations[0].parameters[1]["required"], true);
+        assert_eq!(operations[1].method, "delete");
+
+        assert!(Spec::parse("swagger: '2.0'").is_err());
+        assert!(Spec::parse("openapi: [").is_err());
+    }
+}
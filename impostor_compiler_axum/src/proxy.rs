@@ -0,0 +1,192 @@
+use axum::{
+    body::{self, Body, Bytes},
+    extract::Request as AxumRequest,
+    http::{
+        header::{
+            CONNECTION, HOST, PROXY_AUTHENTICATE, PROXY_AUTHORIZATION, TE, TRAILER,
+            TRANSFER_ENCODING, UPGRADE,
+        },
+        request::Parts,
+        uri::Scheme,
+        HeaderMap, HeaderName, Request, Response, StatusCode, Uri,
+    },
+    response::{IntoResponse, Response as AxumResponse},
+};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::TokioExecutor,
+};
+
+use crate::{error, read_body, MAX_BODY_SIZE};
+
+/// A server that requests can be forwarded to, e.g. the real API a file
+/// mocks.
+#[derive(Clone, Debug)]
+pub struct Upstream {
+    /// The scheme and authority of the server, and the path requests are
+    /// relative to, without a trailing slash.
+    base: String,
+    client: Client<HttpsConnector<HttpConnector>, Body>,
+}
+
+impl Upstream {
+    /// Create an upstream from its URL, e.g. `http://localhost:8080/api` or
+    /// `https://api.example.com`. Certificates of `https` servers are checked
+    /// against the Mozilla root certificates.
+    pub fn new(url: &str) -> error::Result<Upstream> {
+        let uri: Uri = url
+            .parse()
+            .map_err(|e| error::Error::InvalidUpstream(format!("{}: {}", url, e)))?;
+        let (Some(scheme), Some(authority)) = (uri.scheme(), uri.authority()) else {
+            return Err(error::Error::InvalidUpstream(format!(
+                "{}: expected an absolute URL",
+                url
+            )));
+        };
+        if *scheme != Scheme::HTTP && *scheme != Scheme::HTTPS {
+            return Err(error::Error::InvalidUpstream(format!(
+                "{}: only http and https URLs are supported",
+                url
+            )));
+        }
+        let connector = HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_or_http()
+            .enable_http1()
+            .build();
+
+        Ok(Upstream {
+            base: format!(
+                "{}://{}{}",
+                scheme,
+                authority,
+                uri.path().trim_end_matches('/')
+            ),
+            client: Client::builder(TokioExecutor::new()).build(connector),
+        })
+    }
+
+    /// Send a request to the upstream server and buffer its response, which
+    /// is an error if it's larger than [`MAX_BODY_SIZE`].
+    ///
+    /// Hop-by-hop headers are dropped both ways, and the `Host` header is the
+    /// upstream's rather than the one the request was sent with.
+    pub(crate) async fn forward(
+        &self,
+        parts: &Parts,
+        body: Bytes,
+    ) -> Result<Response<Bytes>, Box<dyn std::error::Error + Send + Sync>> {
+        let path_and_query = parts.uri.path_and_query().map_or("/", |p| p.as_str());
+        let uri: Uri = format!("{}{}", self.base, path_and_query).parse()?;
+
+        let mut headers = parts.headers.clone();
+        remove_hop_by_hop(&mut headers);
+        headers.remove(HOST);
+
+        let mut request = Request::builder()
+            .method(parts.method.clone())
+            .uri(uri)
+            .body(Body::from(body))?;
+        *request.headers_mut() = headers;
+
+        let (mut parts, incoming) = self.client.request(request).await?.into_parts();
+        let body = body::to_bytes(Body::new(incoming), MAX_BODY_SIZE).await?;
+        remove_hop_by_hop(&mut parts.headers);
+        Ok(Response::from_parts(parts, body))
+    }
+
+    /// Send a request received by a router to the upstream server. If it
+    /// can't be forwarded, the error is the response to send instead.
+    pub(crate) async fn forward_request(
+        &self,
+        request: AxumRequest,
+    ) -> Result<Response<Bytes>, AxumResponse> {
+        let (parts, body) = request.into_parts();
+        let body = read_body(body).await?;
+        self.forward(&parts, body)
+            .await
+            .map_err(|e| bad_gateway(&parts, e))
+    }
+
+    /// Send a request received by a router to the upstream server and respond
+    /// with its response.
+    pub(crate) async fn respond(&self, request: AxumRequest) -> AxumResponse {
+        match self.forward_request(request).await {
+            Ok(response) => response.map(Body::from),
+            Err(response) => response,
+        }
+    }
+}
+
+/// Respond to a request that couldn't be forwarded.
+pub(crate) fn bad_gateway(
+    parts: &Parts,
+    e: Box<dyn std::error::Error + Send + Sync>,
+) -> AxumResponse {
+    log::warn!("Failed to forward {} {}: {}", parts.method, parts.uri, e);
+    (StatusCode::BAD_GATEWAY, format!("upstream error: {}", e)).into_response()
+}
+
+/// Remove the headers that only apply to a single connection, which a proxy
+/// doesn't pass on: the standard ones, and the ones the `Connection` header
+/// names.
+pub(crate) fn remove_hop_by_hop(headers: &mut HeaderMap) {
+    let named: Vec<HeaderName> = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::try_from(name.trim()).ok())
+        .collect();
+    for name in named {
+        headers.remove(name);
+    }
+
+    for name in [
+        CONNECTION,
+        HeaderName::from_static("keep-alive"),
+        PROXY_AUTHENTICATE,
+        PROXY_AUTHORIZATION,
+        TE,
+        TRAILER,
+        TRANSFER_ENCODING,
+        UPGRADE,
+    ] {
+        headers.remove(name);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_upstream_new() {
+        assert!(Upstream::new("http://localhost:8080").is_ok());
+        assert_eq!(
+            Upstream::new("http://localhost:8080/api/").unwrap().base,
+            "http://localhost:8080/api"
+        );
+        assert!(Upstream::new("https://api.example.com").is_ok());
+        assert!(Upstream::new("ftp://localhost:8080").is_err());
+        assert!(Upstream::new("localhost:8080").is_err());
+        assert!(Upstream::new("/api").is_err());
+    }
+
+    #[test]
+    fn test_remove_hop_by_hop() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONNECTION, HeaderValue::from_static("keep-alive, x-hop"));
+        headers.insert("keep-alive", HeaderValue::from_static("timeout=5"));
+        headers.insert("x-hop", HeaderValue::from_static("1"));
+        headers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        headers.insert("x-end-to-end", HeaderValue::from_static("1"));
+
+        remove_hop_by_hop(&mut headers);
+        assert_eq!(headers.len(), 1);
+        assert!(headers.contains_key("x-end-to-end"));
+    }
+}
@@ -0,0 +1,518 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State as StateExtractor},
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+        HeaderMap, Method, StatusCode,
+    },
+    response::Response,
+    Router,
+};
+use impostor_core::{
+    ast::{
+        Assert as AstAssert, Body as AstBody, Bytes as AstBytes, Entry as AstEntry,
+        Header as AstHeader, ImpostorFile, QueryValue,
+    },
+    printer::print_impostor_file,
+};
+
+use crate::{
+    build,
+    proxy::{self, Upstream},
+    read_body,
+};
+
+/// The requests sent through a recording proxy, and the responses the
+/// upstream server sent back, which can be written out as an Impostor file.
+pub struct Recording {
+    upstream: Upstream,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+/// A request and the response the upstream server sent for it.
+pub(crate) struct Exchange {
+    pub method: Method,
+    pub path: String,
+    pub query: Option<String>,
+    pub request_body: Bytes,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl Exchange {
+    /// Check if two exchanges are for the same request, with the same method,
+    /// path, query params and body.
+    pub fn same_request(&self, other: &Exchange) -> bool {
+        self.method == other.method
+            && self.path == other.path
+            && self.params() == other.params()
+            && self.request_body == other.request_body
+    }
+
+    /// Parse the query params of the request by name, the way the
+    /// `queryparam` query finds them, e.g. `a[b]` is a name of its own. The
+    /// values of a name are sorted, since the asserts built from them don't
+    /// depend on their order.
+    fn params(&self) -> BTreeMap<String, Vec<String>> {
+        let mut params: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let query = self.query.as_deref().unwrap_or_default();
+        for (name, value) in form_urlencoded::parse(query.as_bytes()) {
+            params
+                .entry(name.into_owned())
+                .or_default()
+                .push(value.into_owned());
+        }
+        for values in params.values_mut() {
+            values.sort();
+        }
+        params
+    }
+}
+
+impl Recording {
+    pub fn new(upstream: Upstream) -> Recording {
+        Recording {
+            upstream,
+            exchanges: Mutex::new(vec![]),
+        }
+    }
+
+    /// How many distinct requests were recorded.
+    pub fn len(&self) -> usize {
+        self.exchanges.lock().expect("lock is never poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Record an exchange, unless a request like it was already recorded.
+    fn add(&self, exchange: Exchange) {
+        let mut exchanges = self.exchanges.lock().expect("lock is never poisoned");
+        if !exchanges.iter().any(|e| e.same_request(&exchange)) {
+            exchanges.push(exchange);
+        }
+    }
+
+    /// Build an Impostor file with one entry per distinct request, in the
+    /// order they were first received, except that more specific requests
+    /// come first on the same method and path. Each entry responds with what
+    /// the upstream server sent back the first time.
+    pub fn to_ast(&self) -> ImpostorFile {
+        impostor_file(&self.exchanges.lock().expect("lock is never poisoned"))
+    }
+
+    /// Print the recording as an Impostor file.
+    pub fn to_impostor(&self) -> String {
+        print_impostor_file(&self.to_ast())
+    }
+}
+
+/// Build a router that forwards every request to the recording's upstream
+/// server, and records it along with the response.
+pub fn record_router(recording: Arc<Recording>) -> Router {
+    Router::new().fallback(record).with_state(recording)
+}
+
+async fn record(
+    StateExtractor(recording): StateExtractor<Arc<Recording>>,
+    request: Request,
+) -> Response {
+    let (parts, body) = request.into_parts();
+    let body = match read_body(body).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    let response = match recording.upstream.forward(&parts, body.clone()).await {
+        Ok(response) => response,
+        Err(e) => return proxy::bad_gateway(&parts, e),
+    };
+
+    // The method has to be written the way the parser reads it.
+    let method = parts.method.as_str();
+    if method.chars().all(|c| c.is_ascii_uppercase()) {
+        recording.add(Exchange {
+            method: parts.method.clone(),
+            path: parts.uri.path().to_string(),
+            query: parts.uri.query().map(str::to_string),
+            request_body: body,
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: response.body().clone(),
+        });
+    } else {
+        log::warn!("Not recording {} {}: unsupported method", method, parts.uri);
+    }
+
+    let (parts, body) = response.into_parts();
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Build an Impostor file with an entry per exchange, in order, except that
+/// on the same method and path, entries with more asserts come first. An
+/// entry whose asserts are some of another's would otherwise match its
+/// requests, e.g. one for a request without a query.
+pub(crate) fn impostor_file(exchanges: &[Exchange]) -> ImpostorFile {
+    let mut entries: Vec<(&Exchange, Vec<AstAssert>)> = exchanges
+        .iter()
+        .map(|exchange| (exchange, asserts(exchange)))
+        .collect();
+
+    // Reorder each method and path's entries among the places they take.
+    let mut done = vec![false; entries.len()];
+    for start in 0..entries.len() {
+        if done[start] {
+            continue;
+        }
+        let (method, path) = (&exchanges[start].method, &exchanges[start].path);
+        let places: Vec<usize> = (start..entries.len())
+            .filter(|&index| exchanges[index].method == *method && exchanges[index].path == *path)
+            .collect();
+        let mut group: Vec<(&Exchange, Vec<AstAssert>)> = places
+            .iter()
+            .map(|&index| (entries[index].0, std::mem::take(&mut entries[index].1)))
+            .collect();
+        group.sort_by_key(|(_, asserts)| std::cmp::Reverse(asserts.len()));
+        for (index, entry) in places.into_iter().zip(group) {
+            entries[index] = entry;
+            done[index] = true;
+        }
+    }
+
+    ImpostorFile {
+        entries: entries
+            .into_iter()
+            .enumerate()
+            .map(|(index, (exchange, asserts))| entry(exchange, asserts, index == 0))
+            .collect(),
+        line_terminators: vec![],
+    }
+}
+
+fn entry(exchange: &Exchange, asserts: Vec<AstAssert>, first: bool) -> AstEntry {
+    let request = build::request(
+        exchange.method.as_str(),
+        &exchange.path,
+        asserts,
+        first,
+        &[],
+    );
+    let response = build::response(
+        exchange.status.as_u16(),
+        headers(&exchange.headers),
+        body(&exchange.headers, &exchange.body),
+    );
+
+    AstEntry {
+        request,
+        responses: vec![response],
+        proxy: None,
+    }
+}
+
+/// Build the asserts that tell a request apart from the other requests on its
+/// path: one per query param, and one for the body if it isn't empty. A
+/// repeated param gets one `includes` assert per value, and one on how many
+/// values it has.
+fn asserts(exchange: &Exchange) -> Vec<AstAssert> {
+    let mut asserts = vec![];
+
+    for (name, mut values) in exchange.params() {
+        if let [value] = values.as_slice() {
+            asserts.push(build::assert(
+                build::query_param(&name),
+                build::equal(value),
+            ));
+            continue;
+        }
+        let count = values.len();
+        values.dedup();
+        for value in values {
+            asserts.push(build::assert(
+                build::query_param(&name),
+                build::include(&value),
+            ));
+        }
+        asserts.push(build::count_assert(build::query_param(&name), count));
+    }
+
+    if !exchange.request_body.is_empty() {
+        // The `body` query replaces invalid UTF-8 the same way.
+        let body = String::from_utf8_lossy(&exchange.request_body);
+        asserts.push(build::assert(QueryValue::Body, build::equal(&body)));
+    }
+
+    asserts
+}
+
+/// Build the headers of a recorded response. `Content-Length` is left out,
+/// since Impostor sets it from the body it sends, and so are headers whose
+/// name or value can't be written in an Impostor file.
+fn headers(headers: &HeaderMap) -> Vec<AstHeader> {
+    headers
+        .iter()
+        .filter(|(name, _)| *name != CONTENT_LENGTH)
+        .filter_map(|(name, value)| build::header(name.as_str(), value.to_str().ok()?))
+        .collect()
+}
+
+/// Build the body of a recorded response: JSON as it was sent if the response
+/// says it's JSON, text if it's valid UTF-8 that can be written as is, and
+/// base64 otherwise.
+fn body(headers: &HeaderMap, bytes: &Bytes) -> Option<AstBody> {
+    if bytes.is_empty() {
+        return None;
+    }
+
+    let is_json = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("json"));
+    let text = std::str::from_utf8(bytes).ok();
+    let value = text
+        .filter(|_| is_json)
+        .and_then(build::json)
+        .map(AstBytes::Json)
+        .or_else(|| text.and_then(build::text))
+        .unwrap_or_else(|| build::base64_bytes(bytes));
+
+    Some(build::body(value))
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{body, http::HeaderValue, routing::get};
+    use impostor_core::parser::parse_impostor_file;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_body() {
+        let json =
+            HeaderMap::from_iter([(CONTENT_TYPE, HeaderValue::from_static("application/json"))]);
+        let print = |headers: &HeaderMap, bytes: &'static [u8]| {
+            let mut file = parse_impostor_file("GET /\nHTTP 200\n").unwrap();
+            file.entries[0].responses[0].body = body(headers, &Bytes::from_static(bytes));
+            print_impostor_file(&file)
+        };
+
+        assert_eq!(
+            print(&json, b"{\"a\": [1]}\n"),
+            "GET /\nHTTP 200\n{\"a\": [1]}\n"
+        );
+        assert_eq!(
+            print(&HeaderMap::new(), b"{\"a\": 1}"),
+            "GET /\nHTTP 200\n`{\"a\": 1}`\n"
+        );
+        assert_eq!(
+            print(&HeaderMap::new(), b"a\nb\n"),
+            "GET /\nHTTP 200\n```\na\nb\n```\n"
+        );
+        assert_eq!(
+            print(&HeaderMap::new(), b"a\n{{b}}"),
+            "GET /\nHTTP 200\nbase64,YQp7e2J9fQ==;\n"
+        );
+        assert_eq!(
+            print(&HeaderMap::new(), &[0, 159]),
+            "GET /\nHTTP 200\nbase64,AJ8=;\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_impostor_file_with_repeated_and_nested_params() {
+        let exchange = |query: Option<&str>, body: &'static str| Exchange {
+            method: Method::GET,
+            path: "/items".to_string(),
+            query: query.map(str::to_string),
+            request_body: Bytes::new(),
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+        };
+        let mut exchanges: Vec<Exchange> = vec![];
+        for exchange in [
+            exchange(None, "all"),
+            exchange(Some("tag=a&tag=b"), "a and b"),
+            exchange(Some("tag=b&tag=a"), "b and a"),
+            exchange(Some("filter%5Bcolor%5D=red"), "red"),
+            exchange(Some("tag=a"), "a"),
+        ] {
+            if !exchanges.iter().any(|e| e.same_request(&exchange)) {
+                exchanges.push(exchange);
+            }
+        }
+        assert_eq!(exchanges.len(), 4);
+
+        let printed = print_impostor_file(&impostor_file(&exchanges));
+        assert_eq!(
+            printed,
+            "GET /items\n\
+             [Asserts]\n\
+             queryparam \"tag\" includes \"a\"\n\
+             queryparam \"tag\" includes \"b\"\n\
+             queryparam \"tag\" count == 2\n\
+             HTTP 200\n\
+             `a and b`\n\
+             \n\
+             GET /items\n\
+             [Asserts]\n\
+             queryparam \"filter[color]\" == \"red\"\n\
+             HTTP 200\n\
+             `red`\n\
+             \n\
+             GET /items\n\
+             [Asserts]\n\
+             queryparam \"tag\" == \"a\"\n\
+             HTTP 200\n\
+             `a`\n\
+             \n\
+             GET /items\n\
+             HTTP 200\n\
+             `all`\n"
+        );
+
+        // Every entry can be reached.
+        let replay = crate::compile(&printed).unwrap();
+        for (uri, expected) in [
+            ("/items", "all"),
+            ("/items?tag=b&tag=a", "a and b"),
+            ("/items?filter%5Bcolor%5D=red", "red"),
+            ("/items?tag=a", "a"),
+        ] {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let response = replay.clone().oneshot(request).await.unwrap();
+            let body = body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body, expected, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let upstream = Router::new()
+            .route(
+                "/orders/:id",
+                get(|| async {
+                    (
+                        [
+                            ("content-type", "application/json"),
+                            ("x-cache", "miss # 1"),
+                        ],
+                        "{\n  \"id\": 1,\n  \"note\": \"a \\\"b\\\"\"\n}",
+                    )
+                }),
+            )
+            .route(
+                "/orders",
+                get(|| async { "page" }).post(|body: String| async move {
+                    (StatusCode::CREATED, format!("created {body}"))
+                }),
+            )
+            .route("/logo", get(|| async { vec![0u8, 159, 146, 150] }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(crate::serve(listener, upstream));
+
+        let upstream = Upstream::new(&format!("http://{}", address)).unwrap();
+        let recording = Arc::new(Recording::new(upstream));
+        let router = record_router(recording.clone());
+
+        let requests = [
+            ("GET", "/orders/1", ""),
+            ("GET", "/orders/1", ""),
+            ("GET", "/orders?page=2&sort=id", ""),
+            ("POST", "/orders", "{\"item\": \"tea\"}"),
+            ("POST", "/orders", "{\"item\": \"coffee\"}"),
+            ("GET", "/logo", ""),
+        ];
+        let mut responses = vec![];
+        for (method, uri, body) in requests {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::from(body))
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            responses.push((status, body));
+        }
+        assert_eq!(responses[0].0, StatusCode::OK);
+        assert_eq!(
+            responses[3],
+            (
+                StatusCode::CREATED,
+                Bytes::from("created {\"item\": \"tea\"}")
+            )
+        );
+        assert_eq!(recording.len(), 5);
+
+        let recorded = recording.to_impostor();
+        let without_dates: Vec<&str> = recorded
+            .lines()
+            .filter(|line| !line.starts_with("Date:"))
+            .collect();
+        assert_eq!(
+            without_dates.join("\n"),
+            "GET /orders/1\n\
+             HTTP 200\n\
+             Content-Type: application/json\n\
+             X-Cache: miss \\# 1\n\
+             {\n  \"id\": 1,\n  \"note\": \"a \\\"b\\\"\"\n}\n\
+             \n\
+             GET /orders\n\
+             [Asserts]\n\
+             queryparam \"page\" == \"2\"\n\
+             queryparam \"sort\" == \"id\"\n\
+             HTTP 200\n\
+             Content-Type: text/plain; charset=utf-8\n\
+             `page`\n\
+             \n\
+             POST /orders\n\
+             [Asserts]\n\
+             body == \"{\\\"item\\\": \\\"tea\\\"}\"\n\
+             HTTP 201\n\
+             Content-Type: text/plain; charset=utf-8\n\
+             `created {\"item\": \"tea\"}`\n\
+             \n\
+             POST /orders\n\
+             [Asserts]\n\
+             body == \"{\\\"item\\\": \\\"coffee\\\"}\"\n\
+             HTTP 201\n\
+             Content-Type: text/plain; charset=utf-8\n\
+             `created {\"item\": \"coffee\"}`\n\
+             \n\
+             GET /logo\n\
+             HTTP 200\n\
+             Content-Type: application/octet-stream\n\
+             base64,AJ+Slg==;"
+        );
+
+        // The recording replays the responses it recorded.
+        let replay = crate::compile(&recorded).unwrap();
+        for ((method, uri, body), expected) in requests.iter().zip(&responses) {
+            let request = Request::builder()
+                .method(*method)
+                .uri(*uri)
+                .body(Body::from(*body))
+                .unwrap();
+            let response = replay.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(&(status, body), expected, "{} {}", method, uri);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::state::State;
+
+/// The state every scenario starts in, and goes back to when state is reset.
+pub(crate) const INITIAL_STATE: &str = "started";
+
+/// The scenario options of an entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Scenario {
+    pub name: String,
+    /// The state the scenario has to be in for the entry to match, if any.
+    pub state: Option<String>,
+    /// The state the scenario moves to when the entry responds, if any.
+    pub next_state: Option<String>,
+}
+
+impl Scenario {
+    /// Check if the scenario is in the state the entry requires.
+    pub fn matches(&self, state: &State) -> bool {
+        match &self.state {
+            Some(required) => state.scenario_state(&self.name) == *required,
+            None => true,
+        }
+    }
+
+    /// Move the scenario to its next state, if the entry sets one, provided
+    /// it's still in the state the entry requires, and return whether it was.
+    /// Both happen as one step, so that of two requests that match the entry
+    /// at the same time, only one takes the transition.
+    pub fn transition(&self, state: &State) -> bool {
+        state.transition_scenario(
+            &self.name,
+            self.state.as_deref(),
+            self.next_state.as_deref(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scenario_matches_and_transitions() {
+        let state = State::default();
+        let scenario = Scenario {
+            name: "checkout".to_string(),
+            state: Some(INITIAL_STATE.to_string()),
+            next_state: Some("paid".to_string()),
+        };
+
+        assert!(scenario.matches(&state));
+        assert!(scenario.transition(&state));
+        assert!(!scenario.matches(&state));
+        assert!(!scenario.transition(&state));
+        assert_eq!(state.scenario_state("checkout"), "paid");
+
+        state.reset();
+        assert!(scenario.matches(&state));
+    }
+
+    #[test]
+    fn test_scenario_transitions_once_at_a_time() {
+        let state = State::default();
+        let scenario = Scenario {
+            name: "checkout".to_string(),
+            state: Some(INITIAL_STATE.to_string()),
+            next_state: Some("paid".to_string()),
+        };
+        let barrier = std::sync::Barrier::new(8);
+
+        let transitions = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        scenario.matches(&state) && scenario.transition(&state)
+                    })
+                })
+                .collect();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .filter(|transitioned| *transitioned)
+                .count()
+        });
+        assert_eq!(transitions, 1);
+        assert_eq!(state.scenario_state("checkout"), "paid");
+    }
+}
@@ -0,0 +1,821 @@
+//! A validator for JSON Schema (draft 2020-12, and the dialect of OpenAPI 3.0),
+//! as far as OpenAPI documents and request and response bodies need it: types,
+//! `enum` and `const`, the keywords for objects, arrays, strings and numbers,
+//! common `format`s, the `allOf`/`anyOf`/`oneOf`/`not` combinators,
+//! `if`/`then`/`else`, and `$ref`s within the document the schema comes from,
+//! e.g. to its `$defs`.
+//!
+//! Schemas of `matchesSchema` asserts are compiled with [`CompiledSchema`],
+//! which rejects the keywords of draft 2020-12 that aren't supported, like
+//! `unevaluatedProperties` or `$dynamicRef`, rather than accept instances they
+//! would reject. OpenAPI documents are validated leniently, ignoring them.
+
+use std::{borrow::Cow, collections::HashMap, sync::OnceLock};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::json;
+
+/// How many `$ref`s deep validation goes before giving up, in case a schema
+/// refers to itself without going any deeper into the instance.
+const MAX_DEPTH: usize = 64;
+
+/// A way in which an instance doesn't match a schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SchemaError {
+    /// Where in the instance the error is, e.g. `$.users[0].name`.
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Validate an instance against a schema. `root` is the document the schema
+/// is part of, which its `$ref`s are resolved against.
+pub(crate) fn validate(schema: &Value, root: &Value, instance: &Value) -> Vec<SchemaError> {
+    let mut errors = vec![];
+    let validator = Validator {
+        root,
+        regexes: &HashMap::new(),
+    };
+    validator.validate(schema, instance, "$", 0, &mut errors);
+    errors
+}
+
+/// The keywords of draft 2020-12 that validation doesn't support.
+const UNSUPPORTED_KEYWORDS: [&str; 7] = [
+    "dependentSchemas",
+    "unevaluatedProperties",
+    "unevaluatedItems",
+    "$anchor",
+    "$dynamicAnchor",
+    "$dynamicRef",
+    "$recursiveRef",
+];
+
+/// The `format`s that validation checks.
+const FORMATS: [&str; 8] = [
+    "date-time",
+    "date",
+    "time",
+    "email",
+    "uuid",
+    "uri",
+    "ipv4",
+    "ipv6",
+];
+
+/// A schema that's been checked for keywords that validation doesn't support,
+/// with its regexes compiled, to validate many instances against it.
+#[derive(Clone, Debug)]
+pub(crate) struct CompiledSchema {
+    schema: Value,
+    regexes: HashMap<String, Regex>,
+}
+
+impl CompiledSchema {
+    pub fn compile(schema: Value) -> Result<CompiledSchema, String> {
+        let mut regexes = HashMap::new();
+        check(&schema, &schema, "#", &mut regexes)?;
+        Ok(CompiledSchema { schema, regexes })
+    }
+
+    pub fn validate(&self, instance: &Value) -> Vec<SchemaError> {
+        let mut errors = vec![];
+        let validator = Validator {
+            root: &self.schema,
+            regexes: &self.regexes,
+        };
+        validator.validate(&self.schema, instance, "$", 0, &mut errors);
+        errors
+    }
+}
+
+/// Check that a schema, at `location` in `root`, has nothing validation
+/// doesn't support, and compile its regexes.
+fn check(
+    schema: &Value,
+    root: &Value,
+    location: &str,
+    regexes: &mut HashMap<String, Regex>,
+) -> Result<(), String> {
+    let Value::Object(schema) = schema else {
+        return Ok(());
+    };
+    if let Some(keyword) = UNSUPPORTED_KEYWORDS
+        .iter()
+        .find(|keyword| schema.contains_key(**keyword))
+    {
+        return Err(format!("{} at {} isn't supported", keyword, location));
+    }
+    // `$ref`s are resolved against the root, whatever `$id`s say.
+    if location != "#" && schema.contains_key("$id") {
+        return Err(format!("$id at {} isn't supported", location));
+    }
+    if let Some(dialect) = schema.get("$schema").and_then(Value::as_str) {
+        if !dialect.contains("2020-12") {
+            return Err(format!(
+                "$schema {} isn't supported, only draft 2020-12 is",
+                dialect
+            ));
+        }
+    }
+    if let Some(reference) = schema.get("$ref") {
+        let target = reference
+            .as_str()
+            .and_then(|reference| reference.strip_prefix('#'))
+            .and_then(|pointer| root.pointer(pointer));
+        if target.is_none() {
+            return Err(format!(
+                "$ref {} at {} isn't to a part of the schema",
+                reference, location
+            ));
+        }
+    }
+    if let Some(format) = schema.get("format").and_then(Value::as_str) {
+        if !FORMATS.contains(&format) {
+            return Err(format!("format {} at {} isn't supported", format, location));
+        }
+    }
+
+    let patterns = schema
+        .get("pattern")
+        .and_then(Value::as_str)
+        .into_iter()
+        .chain(
+            schema
+                .get("patternProperties")
+                .and_then(Value::as_object)
+                .into_iter()
+                .flat_map(|properties| properties.keys().map(String::as_str)),
+        );
+    for pattern in patterns {
+        let regex = Regex::new(pattern)
+            .map_err(|_| format!("invalid pattern {} at {}", pattern, location))?;
+        regexes.insert(pattern.to_string(), regex);
+    }
+
+    for keyword in [
+        "items",
+        "additionalProperties",
+        "contains",
+        "propertyNames",
+        "not",
+        "if",
+        "then",
+        "else",
+    ] {
+        if let Some(subschema) = schema.get(keyword) {
+            check(
+                subschema,
+                root,
+                &format!("{}/{}", location, keyword),
+                regexes,
+            )?;
+        }
+    }
+    for keyword in ["prefixItems", "allOf", "anyOf", "oneOf"] {
+        let subschemas = schema.get(keyword).and_then(Value::as_array);
+        for (index, subschema) in subschemas.into_iter().flatten().enumerate() {
+            let location = format!("{}/{}/{}", location, keyword, index);
+            check(subschema, root, &location, regexes)?;
+        }
+    }
+    for keyword in ["properties", "patternProperties", "$defs", "definitions"] {
+        let subschemas = schema.get(keyword).and_then(Value::as_object);
+        for (name, subschema) in subschemas.into_iter().flatten() {
+            let location = format!("{}/{}/{}", location, keyword, name);
+            check(subschema, root, &location, regexes)?;
+        }
+    }
+    Ok(())
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    /// Regexes compiled ahead of time, by their pattern.
+    regexes: &'a HashMap<String, Regex>,
+}
+
+impl Validator<'_> {
+    fn validate(
+        &self,
+        schema: &Value,
+        instance: &Value,
+        path: &str,
+        depth: usize,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        let error = |errors: &mut Vec<SchemaError>, message: String| {
+            errors.push(SchemaError {
+                path: path.to_string(),
+                message,
+            })
+        };
+        if depth > MAX_DEPTH {
+            return error(errors, "schema is too deeply nested".to_string());
+        }
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return error(errors, "no value is allowed".to_string()),
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match self.resolve(reference) {
+                Some(target) => self.validate(target, instance, path, depth + 1, errors),
+                None => error(errors, format!("can't resolve $ref {}", reference)),
+            }
+        }
+
+        // OpenAPI 3.0 has `nullable` instead of a `null` type.
+        let nullable = schema.get("nullable") == Some(&Value::Bool(true));
+        if instance.is_null() && nullable {
+            return;
+        }
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::String(name) => vec![name],
+                Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+                _ => vec![],
+            };
+            if !types.is_empty() && !types.iter().any(|name| has_type(instance, name)) {
+                return error(
+                    errors,
+                    format!("expected {}, got {}", types.join(" or "), instance),
+                );
+            }
+        }
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            if !values.iter().any(|value| json::equal(value, instance)) {
+                error(
+                    errors,
+                    format!("{} isn't one of {}", instance, Value::from(values.clone())),
+                );
+            }
+        }
+        if let Some(value) = schema.get("const") {
+            if !json::equal(value, instance) {
+                error(errors, format!("expected {}, got {}", value, instance));
+            }
+        }
+
+        match instance {
+            Value::Object(object) => {
+                let properties = schema.get("properties").and_then(Value::as_object);
+                let pattern_properties = schema.get("patternProperties").and_then(Value::as_object);
+                for name in schema
+                    .get("required")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                {
+                    if !object.contains_key(name) {
+                        error(errors, format!("missing required property {}", name));
+                    }
+                }
+                let dependencies = schema.get("dependentRequired").and_then(Value::as_object);
+                for (name, required) in dependencies.into_iter().flatten() {
+                    if !object.contains_key(name) {
+                        continue;
+                    }
+                    for required in required.as_array().into_iter().flatten() {
+                        let Some(required) = required.as_str() else {
+                            continue;
+                        };
+                        if !object.contains_key(required) {
+                            error(
+                                errors,
+                                format!("missing property {}, which {} requires", required, name),
+                            );
+                        }
+                    }
+                }
+                for (name, value) in object {
+                    let path = format!("{}.{}", path, name);
+                    if let Some(names) = schema.get("propertyNames") {
+                        if !self.is_valid(names, &Value::from(name.as_str()), depth) {
+                            errors.push(SchemaError {
+                                path: path.clone(),
+                                message: "property name isn't allowed".to_string(),
+                            });
+                        }
+                    }
+
+                    let mut property_schemas: Vec<&Value> = properties
+                        .and_then(|properties| properties.get(name))
+                        .into_iter()
+                        .collect();
+                    for (pattern, property) in pattern_properties.into_iter().flatten() {
+                        if self
+                            .regex(pattern)
+                            .is_some_and(|regex| regex.is_match(name))
+                        {
+                            property_schemas.push(property);
+                        }
+                    }
+                    if !property_schemas.is_empty() {
+                        for property in property_schemas {
+                            self.validate(property, value, &path, depth + 1, errors);
+                        }
+                        continue;
+                    }
+                    match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => errors.push(SchemaError {
+                            path,
+                            message: "property isn't allowed".to_string(),
+                        }),
+                        Some(additional) => {
+                            self.validate(additional, value, &path, depth + 1, errors)
+                        }
+                        None => {}
+                    }
+                }
+                check_count(
+                    schema,
+                    "minProperties",
+                    "maxProperties",
+                    object.len(),
+                    "properties",
+                )
+                .into_iter()
+                .for_each(|message| error(errors, message));
+            }
+            Value::Array(items) => {
+                // The first items can each have a schema of their own, and
+                // `items` is for the ones after them.
+                let prefix_items = schema.get("prefixItems").and_then(Value::as_array);
+                for (index, item) in items.iter().enumerate() {
+                    let item_schema = match prefix_items.and_then(|prefix| prefix.get(index)) {
+                        Some(item_schema) => item_schema,
+                        None => match schema.get("items") {
+                            Some(item_schema) => item_schema,
+                            None => continue,
+                        },
+                    };
+                    let path = format!("{}[{}]", path, index);
+                    self.validate(item_schema, item, &path, depth + 1, errors);
+                }
+                if let Some(contains) = schema.get("contains") {
+                    let count = items
+                        .iter()
+                        .filter(|item| self.is_valid(contains, item, depth))
+                        .count() as u64;
+                    let min = schema
+                        .get("minContains")
+                        .and_then(Value::as_u64)
+                        .unwrap_or(1);
+                    let max = schema.get("maxContains").and_then(Value::as_u64);
+                    if count < min {
+                        error(
+                            errors,
+                            format!(
+                                "expected at least {} items matching contains, got {}",
+                                min, count
+                            ),
+                        );
+                    } else if let Some(max) = max.filter(|max| count > *max) {
+                        error(
+                            errors,
+                            format!(
+                                "expected at most {} items matching contains, got {}",
+                                max, count
+                            ),
+                        );
+                    }
+                }
+                check_count(schema, "minItems", "maxItems", items.len(), "items")
+                    .into_iter()
+                    .for_each(|message| error(errors, message));
+                let unique = schema.get("uniqueItems") == Some(&Value::Bool(true));
+                if unique
+                    && items.iter().enumerate().any(|(index, item)| {
+                        items[..index].iter().any(|other| json::equal(item, other))
+                    })
+                {
+                    error(errors, "items aren't unique".to_string());
+                }
+            }
+            Value::String(string) => {
+                let length = string.chars().count();
+                check_count(schema, "minLength", "maxLength", length, "characters")
+                    .into_iter()
+                    .for_each(|message| error(errors, message));
+                if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                    match self.regex(pattern) {
+                        Some(regex) if !regex.is_match(string) => {
+                            error(errors, format!("doesn't match pattern {}", pattern))
+                        }
+                        Some(_) => {}
+                        None => error(errors, format!("invalid pattern {}", pattern)),
+                    }
+                }
+                if let Some(format) = schema.get("format").and_then(Value::as_str) {
+                    if has_format(string, format) == Some(false) {
+                        error(errors, format!("isn't a valid {}", format));
+                    }
+                }
+            }
+            Value::Number(number) => {
+                let number = number.as_f64().unwrap_or(f64::NAN);
+                check_number(schema, number)
+                    .into_iter()
+                    .for_each(|message| error(errors, message));
+            }
+            _ => {}
+        }
+
+        if let Some(schemas) = schema.get("allOf").and_then(Value::as_array) {
+            for schema in schemas {
+                self.validate(schema, instance, path, depth + 1, errors);
+            }
+        }
+        if let Some(schemas) = schema.get("anyOf").and_then(Value::as_array) {
+            if !schemas
+                .iter()
+                .any(|schema| self.is_valid(schema, instance, depth))
+            {
+                error(errors, "doesn't match any schema of anyOf".to_string());
+            }
+        }
+        if let Some(schemas) = schema.get("oneOf").and_then(Value::as_array) {
+            let matches = schemas
+                .iter()
+                .filter(|schema| self.is_valid(schema, instance, depth))
+                .count();
+            if matches != 1 {
+                error(
+                    errors,
+                    format!("matches {} schemas of oneOf instead of 1", matches),
+                );
+            }
+        }
+        if let Some(schema) = schema.get("not") {
+            if self.is_valid(schema, instance, depth) {
+                error(errors, "matches the schema of not".to_string());
+            }
+        }
+        if let Some(condition) = schema.get("if") {
+            let branch = if self.is_valid(condition, instance, depth) {
+                schema.get("then")
+            } else {
+                schema.get("else")
+            };
+            if let Some(branch) = branch {
+                self.validate(branch, instance, path, depth + 1, errors);
+            }
+        }
+    }
+
+    fn is_valid(&self, schema: &Value, instance: &Value, depth: usize) -> bool {
+        let mut errors = vec![];
+        self.validate(schema, instance, "$", depth + 1, &mut errors);
+        errors.is_empty()
+    }
+
+    /// Get the regex of a pattern, compiled ahead of time if it was, or
+    /// `None` if it's invalid.
+    fn regex(&self, pattern: &str) -> Option<Cow<'_, Regex>> {
+        match self.regexes.get(pattern) {
+            Some(regex) => Some(Cow::Borrowed(regex)),
+            None => Regex::new(pattern).ok().map(Cow::Owned),
+        }
+    }
+
+    /// Find what a `$ref` within the document points to, e.g.
+    /// `#/components/schemas/User`.
+    fn resolve(&self, reference: &str) -> Option<&Value> {
+        let pointer = reference.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+}
+
+/// Check if an instance has a JSON Schema type. Integers are numbers without
+/// a fractional part, e.g. `1.0`.
+fn has_type(instance: &Value, name: &str) -> bool {
+    match name {
+        "null" => instance.is_null(),
+        "boolean" => instance.is_boolean(),
+        "object" => instance.is_object(),
+        "array" => instance.is_array(),
+        "string" => instance.is_string(),
+        "number" => instance.is_number(),
+        "integer" => instance
+            .as_f64()
+            .is_some_and(|number| number.fract() == 0.0),
+        _ => true,
+    }
+}
+
+/// Check if a string has a format, or get `None` if the format isn't one of
+/// [`FORMATS`], e.g. one of OpenAPI's, like `int64`.
+fn has_format(string: &str, format: &str) -> Option<bool> {
+    static REGEXES: OnceLock<HashMap<&str, Regex>> = OnceLock::new();
+    let regexes = REGEXES.get_or_init(|| {
+        const DATE: &str = r"\d{4}-(0[1-9]|1[0-2])-(0[1-9]|[12]\d|3[01])";
+        const TIME: &str =
+            r"([01]\d|2[0-3]):[0-5]\d:([0-5]\d|60)(\.\d+)?([Zz]|[+-]([01]\d|2[0-3]):[0-5]\d)";
+        [
+            ("date-time", format!("^{}[Tt]{}$", DATE, TIME)),
+            ("date", format!("^{}$", DATE)),
+            ("time", format!("^{}$", TIME)),
+            ("email", r"^[^@\s]+@[^@\s]+\.[^@\s]+$".to_string()),
+            (
+                "uuid",
+                r"^[0-9a-fA-F]{8}-([0-9a-fA-F]{4}-){3}[0-9a-fA-F]{12}$".to_string(),
+            ),
+            ("uri", r"^[A-Za-z][A-Za-z0-9+.-]*:\S*$".to_string()),
+        ]
+        .into_iter()
+        .map(|(format, pattern)| (format, Regex::new(&pattern).expect("the regex is valid")))
+        .collect()
+    });
+    match format {
+        "ipv4" => Some(string.parse::<std::net::Ipv4Addr>().is_ok()),
+        "ipv6" => Some(string.parse::<std::net::Ipv6Addr>().is_ok()),
+        format => regexes.get(format).map(|regex| regex.is_match(string)),
+    }
+}
+
+/// Check a count, e.g. the length of a string, against its minimum and
+/// maximum keywords.
+fn check_count(
+    schema: &serde_json::Map<String, Value>,
+    min: &str,
+    max: &str,
+    count: usize,
+    unit: &str,
+) -> Option<String> {
+    let bound = |keyword| schema.get(keyword).and_then(Value::as_u64);
+    if let Some(min) = bound(min).filter(|min| (count as u64) < *min) {
+        return Some(format!("expected at least {} {}, got {}", min, unit, count));
+    }
+    if let Some(max) = bound(max).filter(|max| (count as u64) > *max) {
+        return Some(format!("expected at most {} {}, got {}", max, unit, count));
+    }
+    None
+}
+
+/// Check a number against the numeric keywords. `exclusiveMinimum` and
+/// `exclusiveMaximum` can be booleans, as in OpenAPI 3.0, or bounds of their
+/// own, as in later versions of JSON Schema.
+fn check_number(schema: &serde_json::Map<String, Value>, number: f64) -> Option<String> {
+    let bound = |keyword| schema.get(keyword).and_then(Value::as_f64);
+    let exclusive = |keyword| schema.get(keyword) == Some(&Value::Bool(true));
+
+    if let Some(min) = bound("minimum") {
+        if number < min || (exclusive("exclusiveMinimum") && number == min) {
+            return Some(format!("expected at least {}, got {}", min, number));
+        }
+    }
+    if let Some(max) = bound("maximum") {
+        if number > max || (exclusive("exclusiveMaximum") && number == max) {
+            return Some(format!("expected at most {}, got {}", max, number));
+        }
+    }
+    if let Some(min) = bound("exclusiveMinimum").filter(|min| number <= *min) {
+        return Some(format!("expected more than {}, got {}", min, number));
+    }
+    if let Some(max) = bound("exclusiveMaximum").filter(|max| number >= *max) {
+        return Some(format!("expected less than {}, got {}", max, number));
+    }
+    if let Some(divisor) = bound("multipleOf").filter(|divisor| *divisor > 0.0) {
+        if (number / divisor).fract() != 0.0 {
+            return Some(format!(
+                "expected a multiple of {}, got {}",
+                divisor, number
+            ));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn errors(schema: Value, instance: Value) -> Vec<String> {
+        validate(&schema, &schema, &instance)
+            .into_iter()
+            .map(|error| error.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_types() {
+        assert!(errors(json!({"type": "integer"}), json!(1.0)).is_empty());
+        assert_eq!(
+            errors(json!({"type": "integer"}), json!(1.5)),
+            vec!["$: expected integer, got 1.5"]
+        );
+        assert!(errors(json!({"type": ["string", "null"]}), json!(null)).is_empty());
+        assert!(errors(json!({"type": "string", "nullable": true}), json!(null)).is_empty());
+        assert_eq!(
+            errors(json!({"enum": ["a", "b"]}), json!("c")),
+            vec!["$: \"c\" isn't one of [\"a\",\"b\"]"]
+        );
+        assert!(errors(json!(true), json!({"a": 1})).is_empty());
+    }
+
+    #[test]
+    fn test_validate_objects_and_arrays() {
+        let schema = json!({
+            "type": "object",
+            "required": ["id", "name"],
+            "additionalProperties": false,
+            "properties": {
+                "id": {"type": "integer", "minimum": 1},
+                "name": {"type": "string", "minLength": 1, "pattern": "^[A-Z]"},
+                "tags": {"type": "array", "items": {"type": "string"}, "maxItems": 2}
+            }
+        });
+        assert!(errors(
+            schema.clone(),
+            json!({"id": 1, "name": "Ada", "tags": ["a"]})
+        )
+        .is_empty());
+        assert_eq!(
+            errors(
+                schema,
+                json!({"id": 0, "name": "ada", "tags": ["a", 1, "c"], "admin": true})
+            ),
+            vec![
+                "$.admin: property isn't allowed",
+                "$.id: expected at least 1, got 0",
+                "$.name: doesn't match pattern ^[A-Z]",
+                "$.tags[1]: expected string, got 1",
+                "$.tags: expected at most 2 items, got 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_refs_and_combinators() {
+        let root = json!({
+            "components": {"schemas": {
+                "Node": {
+                    "type": "object",
+                    "properties": {"children": {"type": "array", "items": {"$ref": "#/components/schemas/Node"}}}
+                }
+            }}
+        });
+        let schema = json!({"$ref": "#/components/schemas/Node"});
+        assert!(validate(&schema, &root, &json!({"children": [{"children": []}]})).is_empty());
+        assert_eq!(
+            validate(&schema, &root, &json!({"children": [{"children": 1}]}))[0].path,
+            "$.children[0].children"
+        );
+
+        let schema = json!({"oneOf": [{"type": "integer"}, {"type": "number"}]});
+        assert_eq!(
+            errors(schema.clone(), json!(1)),
+            vec!["$: matches 2 schemas of oneOf instead of 1"]
+        );
+        assert!(errors(schema, json!(1.5)).is_empty());
+        assert!(errors(
+            json!({"anyOf": [{"type": "string"}, {"minimum": 3}]}),
+            json!(4)
+        )
+        .is_empty());
+        assert!(!errors(json!({"not": {"type": "string"}}), json!("a")).is_empty());
+
+        let schema = json!({
+            "if": {"properties": {"kind": {"const": "card"}}},
+            "then": {"required": ["number"]},
+            "else": {"required": ["iban"]}
+        });
+        assert!(errors(schema.clone(), json!({"kind": "card", "number": "4242"})).is_empty());
+        assert_eq!(
+            errors(schema, json!({"kind": "transfer"})),
+            vec!["$: missing required property iban"]
+        );
+    }
+
+    #[test]
+    fn test_validate_2020_12_keywords() {
+        let schema = json!({
+            "$defs": {"sku": {"type": "string", "pattern": "^[A-Z]+-[0-9]+$"}},
+            "type": "object",
+            "properties": {
+                "line": {
+                    "type": "array",
+                    "prefixItems": [{"$ref": "#/$defs/sku"}, {"type": "integer"}],
+                    "items": false
+                },
+                "tags": {"type": "array", "contains": {"const": "gift"}, "maxContains": 1}
+            },
+            "patternProperties": {"^x-": {"type": "string"}},
+            "additionalProperties": false,
+            "propertyNames": {"maxLength": 8}
+        });
+        assert!(errors(
+            schema.clone(),
+            json!({"line": ["AB-1", 2], "tags": ["gift", "red"], "x-note": "hi"})
+        )
+        .is_empty());
+        assert_eq!(
+            errors(
+                schema,
+                json!({"x-priority": 1, "tags": ["gift", "gift"], "comment": "", "line": ["ab", 2, 3]})
+            ),
+            vec![
+                "$.comment: property isn't allowed",
+                "$.line[0]: doesn't match pattern ^[A-Z]+-[0-9]+$",
+                "$.line[2]: no value is allowed",
+                "$.tags: expected at most 1 items matching contains, got 2",
+                "$.x-priority: property name isn't allowed",
+                "$.x-priority: expected string, got 1",
+            ]
+        );
+        assert_eq!(
+            errors(
+                json!({"dependentRequired": {"tags": ["line"]}}),
+                json!({"tags": []})
+            ),
+            vec!["$: missing property line, which tags requires"]
+        );
+    }
+
+    #[test]
+    fn test_validate_formats() {
+        let schema = json!({
+            "properties": {
+                "at": {"format": "date-time"},
+                "id": {"format": "uuid"},
+                "ip": {"format": "ipv4"},
+                "size": {"format": "int64"}
+            }
+        });
+        assert!(errors(
+            schema.clone(),
+            json!({"at": "2024-05-01T10:00:00Z", "id": "123e4567-e89b-12d3-a456-426614174000", "ip": "10.0.0.1", "size": "big"})
+        )
+        .is_empty());
+        assert_eq!(
+            errors(
+                schema,
+                json!({"at": "2024-05-01", "id": "123", "ip": "10.0.0.256"})
+            ),
+            vec![
+                "$.at: isn't a valid date-time",
+                "$.id: isn't a valid uuid",
+                "$.ip: isn't a valid ipv4",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile() {
+        let schema = CompiledSchema::compile(json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$defs": {"code": {"type": "string", "pattern": "^[A-Z]+$"}},
+            "patternProperties": {"^x-": {"$ref": "#/$defs/code"}}
+        }))
+        .unwrap();
+        assert_eq!(schema.regexes.len(), 2);
+        assert!(schema.validate(&json!({"x-code": "AB"})).is_empty());
+        assert_eq!(
+            schema
+                .validate(&json!({"x-code": "ab"}))
+                .into_iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<String>>(),
+            vec!["$.x-code: doesn't match pattern ^[A-Z]+$"]
+        );
+
+        for (schema, error) in [
+            (
+                json!({"properties": {"tags": {"unevaluatedItems": false}}}),
+                "unevaluatedItems at #/properties/tags isn't supported",
+            ),
+            (
+                json!({"$schema": "http://json-schema.org/draft-07/schema#"}),
+                "$schema http://json-schema.org/draft-07/schema# isn't supported, only draft 2020-12 is",
+            ),
+            (
+                json!({"items": {"$id": "item"}}),
+                "$id at #/items isn't supported",
+            ),
+            (
+                json!({"allOf": [{"$ref": "order.json"}]}),
+                "$ref \"order.json\" at #/allOf/0 isn't to a part of the schema",
+            ),
+            (
+                json!({"format": "hostname"}),
+                "format hostname at # isn't supported",
+            ),
+            (json!({"pattern": "("}), "invalid pattern ( at #"),
+        ] {
+            assert_eq!(CompiledSchema::compile(schema).unwrap_err(), error);
+        }
+    }
+}
@@ -0,0 +1,400 @@
+//! Runs Hurl-style test files against a compiled router, in-process, so that
+//! mocks can be tested without a server or any other tool.
+
+use axum::{
+    body::{self, Body},
+    http::{self, StatusCode},
+    Router,
+};
+use impostor_core::ast::{
+    Bytes as AstBytes, Entry as AstEntry, Request as AstRequest, Response as AstResponse,
+};
+use tower::ServiceExt;
+
+use crate::{
+    error,
+    hurl::{encode_component, url_path},
+    json, parse, template_value,
+};
+
+/// The outcome of one request of a test file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestResult {
+    /// The line of the test file the request is on.
+    pub line: usize,
+    pub method: String,
+    pub url: String,
+    /// How the response differs from the expected one. The test passed if
+    /// there are none.
+    pub failures: Vec<Failure>,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// A way in which a response differs from the expected one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// The request couldn't be sent, e.g. because a header isn't valid.
+    Request(String),
+    Status {
+        expected: u16,
+        actual: u16,
+    },
+    Header {
+        name: String,
+        expected: String,
+        actual: Option<String>,
+    },
+    /// The bodies differ. JSON bodies are compared as JSON, and shown pretty
+    /// printed.
+    Body {
+        expected: String,
+        actual: String,
+    },
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Failure::Request(message) => write!(f, "can't send request: {}", message),
+            Failure::Status { expected, actual } => {
+                write!(f, "status: expected {}, got {}", expected, actual)
+            }
+            Failure::Header {
+                name,
+                expected,
+                actual: Some(actual),
+            } => write!(
+                f,
+                "header {}: expected {:?}, got {:?}",
+                name, expected, actual
+            ),
+            Failure::Header {
+                name,
+                expected,
+                actual: None,
+            } => write!(f, "header {}: expected {:?}, got none", name, expected),
+            Failure::Body { expected, actual } => {
+                write!(f, "body (- expected, + actual):")?;
+                for line in diff(expected, actual) {
+                    write!(f, "\n{}", line)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Run the requests of a test file against a router, in order, and check
+/// that each one gets the response the file expects: its status, each of its
+/// headers, and its body, if it has one.
+///
+/// Test files are written the way Hurl files are, e.g. `samples/tour_test.hurl`.
+/// URLs can be full ones, whose scheme and host are ignored, or just paths.
+pub async fn run_tests(router: Router, tests: &str) -> error::Result<Vec<TestResult>> {
+    let file = parse(tests)?;
+
+    let mut results = vec![];
+    for entry in &file.entries {
+        results.push(run_test(&router, entry).await);
+    }
+    Ok(results)
+}
+
+async fn run_test(router: &Router, entry: &AstEntry) -> TestResult {
+    let url = url(&entry.request);
+    let mut result = TestResult {
+        line: entry.request.space0.source_info.end.line,
+        method: entry.request.method.0.clone(),
+        url: url.clone(),
+        failures: vec![],
+    };
+
+    let request = match request(&entry.request, &url) {
+        Ok(request) => request,
+        Err(message) => {
+            result.failures.push(Failure::Request(message));
+            return result;
+        }
+    };
+    let response = router
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("routers don't fail");
+    if let Some(expected) = entry.responses.first() {
+        let (parts, body) = response.into_parts();
+        let body = match body::to_bytes(body, usize::MAX).await {
+            Ok(body) => body,
+            Err(e) => {
+                result.failures.push(Failure::Request(e.to_string()));
+                return result;
+            }
+        };
+        result.failures = compare(expected, parts.status, &parts.headers, &body);
+    }
+    result
+}
+
+/// Find the path and query of a request, with the params of its
+/// `[QueryStringParams]` section added to the query.
+fn url(request: &AstRequest) -> String {
+    let mut url = url_path(&template_value(&request.path));
+    for param in request.query_params() {
+        url.push(if url.contains('?') { '&' } else { '?' });
+        url.push_str(&encode_component(&template_value(&param.key)));
+        url.push('=');
+        url.push_str(&encode_component(&template_value(&param.value)));
+    }
+    url
+}
+
+fn request(request: &AstRequest, url: &str) -> Result<http::Request<Body>, String> {
+    let mut builder = http::Request::builder()
+        .method(request.method.0.as_str())
+        .uri(url);
+    for header in &request.headers {
+        builder = builder.header(template_value(&header.key), template_value(&header.value));
+    }
+    // As a server would, tell the mocks which host the URL is for, unless the
+    // test says otherwise.
+    let has_host = request
+        .headers
+        .iter()
+        .any(|header| template_value(&header.key).eq_ignore_ascii_case("host"));
+    if let Some(host) = host(&template_value(&request.path)).filter(|_| !has_host) {
+        builder = builder.header(http::header::HOST, host);
+    }
+    let cookies: Vec<String> = request
+        .cookies()
+        .iter()
+        .map(|cookie| {
+            format!(
+                "{}={}",
+                template_value(&cookie.key),
+                template_value(&cookie.value)
+            )
+        })
+        .collect();
+    if !cookies.is_empty() {
+        builder = builder.header(http::header::COOKIE, cookies.join("; "));
+    }
+    let body = match &request.body {
+        Some(body) => bytes(&body.value).ok_or("file bodies aren't supported")?,
+        None => vec![],
+    };
+    builder.body(Body::from(body)).map_err(|e| e.to_string())
+}
+
+/// Get the host and port of a URL, if it isn't just a path.
+fn host(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    Some(rest.split(['/', '?']).next().unwrap_or(rest))
+}
+
+/// Get the bytes of a body, or `None` for a file, which isn't supported.
+fn bytes(bytes: &AstBytes) -> Option<Vec<u8>> {
+    let bytes = match bytes {
+        AstBytes::Json(value) => value.encoded().into_bytes(),
+        AstBytes::Xml(value) => value.clone().into_bytes(),
+        AstBytes::MultilineString(value) => template_value(&value.value()).into_bytes(),
+        AstBytes::OnelineString(value) => template_value(value).into_bytes(),
+        AstBytes::Base64(value) => value.value.clone(),
+        AstBytes::Hex(value) => value.value.clone(),
+        AstBytes::File(_) => return None,
+    };
+    Some(bytes)
+}
+
+fn compare(
+    expected: &AstResponse,
+    status: StatusCode,
+    headers: &http::HeaderMap,
+    body: &[u8],
+) -> Vec<Failure> {
+    let mut failures = vec![];
+    if status.as_u16() != expected.status.value {
+        failures.push(Failure::Status {
+            expected: expected.status.value,
+            actual: status.as_u16(),
+        });
+    }
+
+    for header in &expected.headers {
+        let name = template_value(&header.key);
+        let value = template_value(&header.value);
+        let values: Vec<&str> = headers
+            .get_all(name.as_str())
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        if !values.contains(&value.as_str()) {
+            failures.push(Failure::Header {
+                name,
+                expected: value,
+                actual: values.first().map(|value| value.to_string()),
+            });
+        }
+    }
+
+    let Some(expected) = &expected.body else {
+        return failures;
+    };
+    let Some(expected_bytes) = bytes(&expected.value) else {
+        failures.push(Failure::Request("file bodies aren't supported".to_string()));
+        return failures;
+    };
+    if let AstBytes::Json(_) = expected.value {
+        let json = |bytes: &[u8]| serde_json::from_slice::<serde_json::Value>(bytes).ok();
+        if let (Some(expected), actual) = (json(&expected_bytes), json(body)) {
+            if !actual
+                .as_ref()
+                .is_some_and(|actual| json::equal(actual, &expected))
+            {
+                failures.push(Failure::Body {
+                    expected: pretty(&expected),
+                    actual: actual
+                        .map(|actual| pretty(&actual))
+                        .unwrap_or_else(|| String::from_utf8_lossy(body).into_owned()),
+                });
+            }
+            return failures;
+        }
+    }
+    if expected_bytes != body {
+        failures.push(Failure::Body {
+            expected: String::from_utf8_lossy(&expected_bytes).into_owned(),
+            actual: String::from_utf8_lossy(body).into_owned(),
+        });
+    }
+    failures
+}
+
+fn pretty(value: &serde_json::Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+/// Compare two texts line by line, with lines only in the first one prefixed
+/// with `-`, lines only in the second one with `+`, and lines in both with a
+/// space.
+fn diff(expected: &str, actual: &str) -> Vec<String> {
+    let a: Vec<&str> = expected.lines().collect();
+    let b: Vec<&str> = actual.lines().collect();
+
+    // The lengths of the longest common subsequences of the ends of the texts.
+    let mut lengths = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push(format!("  {}", a[i]));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            lines.push(format!("- {}", a[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", b[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a\nb\nc", "a\nc\nd"), vec!["  a", "- b", "  c", "+ d"]);
+        assert_eq!(diff("", "a"), vec!["+ a"]);
+    }
+
+    #[tokio::test]
+    async fn test_run_tests_tour() {
+        let router = crate::compile(include_str!("../../samples/tour.impostor")).unwrap();
+        let results = run_tests(router, include_str!("../../samples/tour_test.hurl"))
+            .await
+            .unwrap();
+        assert!(results.len() > 25);
+        for result in results {
+            assert!(result.passed(), "{:?}", result);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_tests_failures() {
+        let router = crate::compile(
+            r#"GET /users
+HTTP 200
+Content-Type: application/json
+{"id": 1, "name": "Ada"}
+
+POST /users
+HTTP 201
+`created`
+"#,
+        )
+        .unwrap();
+        let tests = r#"GET http://localhost:3939/users
+HTTP 200
+Content-Type: application/json
+{"name": "Ada", "id": 1}
+
+GET /users
+HTTP 201
+Content-Type: text/plain
+X-Id: 1
+{"id": 2, "name": "Ada"}
+
+POST /users
+[QueryStringParams]
+q: a b
+HTTP 201
+`created!`
+"#;
+        let results = run_tests(router, tests).await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].passed());
+        assert_eq!(results[0].url, "/users");
+        assert_eq!(
+            results[1]
+                .failures
+                .iter()
+                .map(|failure| failure.to_string())
+                .collect::<Vec<String>>(),
+            vec![
+                "status: expected 201, got 200",
+                "header Content-Type: expected \"text/plain\", got \"application/json\"",
+                "header X-Id: expected \"1\", got none",
+                "body (- expected, + actual):\n  {\n-   \"id\": 2,\n+   \"id\": 1,\n    \"name\": \"Ada\"\n  }",
+            ]
+        );
+        assert_eq!(results[2].url, "/users?q=a%20b");
+        assert_eq!(
+            results[2].failures,
+            vec![Failure::Body {
+                expected: "created!".to_string(),
+                actual: "created".to_string()
+            }]
+        );
+        assert_eq!(results[2].line, 12);
+
+        assert!(run_tests(crate::compile("").unwrap(), "GET").await.is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use axum::{extract::Request, Router};
+use hyper::body::Incoming;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpListener, TcpStream},
+    task::JoinSet,
+};
+use tower_service::Service;
+
+/// Serve a compiled router on a listener.
+///
+/// This works like `axum::serve`, serving HTTP/1 and HTTP/2, except that
+/// entries get access to the underlying connection. Some faults, like
+/// `malformed-response`, can't be expressed as an HTTP response and need to
+/// write to the connection directly, which only makes sense over HTTP/1.
+///
+/// Connections are served as tasks that belong to the returned future, so
+/// dropping it, e.g. by aborting the task it runs in, also closes every
+/// connection it accepted, including idle keep-alive ones.
+pub async fn serve(listener: TcpListener, router: Router) {
+    let mut connections = JoinSet::new();
+    loop {
+        // Forget about the connections that are done.
+        while connections.try_join_next().is_some() {}
+
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            // Errors about a single connection, e.g. the client hung up
+            // before we accepted it, shouldn't take the server down.
+            Err(e) if is_connection_error(&e) => continue,
+            // Others, like running out of file descriptors, are likely to
+            // happen again right away, so wait a bit before trying again.
+            Err(e) => {
+                log::error!("accept error: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        let router = router.clone();
+
+        connections.spawn(async move {
+            let raw_response = RawResponse::default();
+            let connection = Connection {
+                stream,
+                raw_response: raw_response.clone(),
+                pending: None,
+            };
+            let service = hyper::service::service_fn(move |mut request: Request<Incoming>| {
+                request.extensions_mut().insert(raw_response.clone());
+                router.clone().call(request)
+            });
+
+            // An error here means the connection broke, either because of the
+            // client or because of a fault we injected. Either way, there's
+            // nothing left to do with it.
+            let _ = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(connection), service)
+                .await;
+        });
+    }
+}
+
+/// Check if an error accepting a connection is about that connection only.
+fn is_connection_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    )
+}
+
+/// A handle to the connection a request came in on, which lets a handler send
+/// raw bytes instead of the response hyper would have written.
+///
+/// Only requests served by [`serve`] have this handle in their extensions.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RawResponse(Arc<Mutex<Option<Vec<u8>>>>);
+
+impl RawResponse {
+    /// Replace the next response on this connection with `bytes`, and close
+    /// the connection after they're sent.
+    pub fn send(&self, bytes: Vec<u8>) {
+        *self.0.lock().expect("lock is never poisoned") = Some(bytes);
+    }
+
+    fn take(&self) -> Option<Vec<u8>> {
+        self.0.lock().expect("lock is never poisoned").take()
+    }
+}
+
+struct Connection {
+    stream: TcpStream,
+    raw_response: RawResponse,
+    // The raw response being written, and how much of it has been written.
+    pending: Option<(Vec<u8>, usize)>,
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        if let Some(bytes) = this.raw_response.take() {
+            this.pending = Some((bytes, 0));
+        }
+
+        let Some((bytes, written)) = &mut this.pending else {
+            return Pin::new(&mut this.stream).poll_write(cx, buf);
+        };
+
+        // Whatever hyper wanted to write is dropped in favour of the raw
+        // response, and the connection is closed once it's sent.
+        while *written < bytes.len() {
+            *written += ready!(Pin::new(&mut this.stream).poll_write(cx, &bytes[*written..]))?;
+        }
+        ready!(Pin::new(&mut this.stream).poll_shutdown(cx))?;
+        Poll::Ready(Err(io::ErrorKind::ConnectionAborted.into()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
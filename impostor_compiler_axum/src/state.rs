@@ -0,0 +1,426 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    compile_entries,
+    dispatch::Routes,
+    entry::Entry,
+    error,
+    journal::{Journal, JournalEntry},
+    near_miss::path_is_similar,
+    parse,
+    proxy::Upstream,
+    scenario::INITIAL_STATE,
+    verify::Verification,
+};
+
+/// State shared by all the routes of a compiled router.
+///
+/// Anything that changes while the router is serving lives here: the entries
+/// themselves, since entries can be added and removed at runtime, how many
+/// times each entry was hit, which state each scenario is in, and the
+/// requests received so far.
+#[derive(Default)]
+pub struct State {
+    registry: RwLock<Registry>,
+    // How many requests each entry has handled, by entry id.
+    hits: Mutex<HashMap<usize, usize>>,
+    // The current state of each scenario that has left its initial state.
+    scenarios: Mutex<HashMap<String, String>>,
+    journal: Mutex<Journal>,
+    // Whether requests that no entry matched get a body explaining why.
+    verbose_not_found: AtomicBool,
+    // Where requests that no entry matched are forwarded, if anywhere.
+    proxy_unmatched: RwLock<Option<Upstream>>,
+}
+
+/// The entries of a router, grouped by where they came from.
+#[derive(Default)]
+struct Registry {
+    // Entries added at runtime to be tried before the file's, newest first.
+    first: Vec<Arc<Entry>>,
+    file: Vec<Arc<Entry>>,
+    // Entries added at runtime to be tried after the file's, oldest first.
+    last: Vec<Arc<Entry>>,
+    routes: Routes,
+    next_id: usize,
+}
+
+impl Registry {
+    /// Give entries the next free ids.
+    fn assign_ids(&mut self, entries: Vec<Entry>) -> Vec<Arc<Entry>> {
+        entries
+            .into_iter()
+            .map(|mut entry| {
+                entry.id = self.next_id;
+                self.next_id += 1;
+                Arc::new(entry)
+            })
+            .collect()
+    }
+
+    /// All the entries, in the order they're tried.
+    fn entries(&self) -> impl Iterator<Item = (&Arc<Entry>, Origin)> {
+        self.first
+            .iter()
+            .map(|entry| (entry, Origin::Runtime))
+            .chain(self.file.iter().map(|entry| (entry, Origin::File)))
+            .chain(self.last.iter().map(|entry| (entry, Origin::Runtime)))
+    }
+
+    /// Apply a change to the entries, and index them again. If they can't be
+    /// indexed, e.g. because of conflicting paths, the change is undone.
+    fn update(&mut self, change: impl FnOnce(&mut Registry)) -> error::Result<()> {
+        let (first, file, last) = (self.first.clone(), self.file.clone(), self.last.clone());
+        change(self);
+        let entries = self.entries().map(|(entry, _)| entry.clone()).collect();
+        match Routes::new(entries) {
+            Ok(routes) => {
+                self.routes = routes;
+                Ok(())
+            }
+            Err(e) => {
+                (self.first, self.file, self.last) = (first, file, last);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Where an entry came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    /// The file the router was compiled from.
+    File,
+    /// Added while the router was serving.
+    Runtime,
+}
+
+/// Where entries added at runtime go relative to the file's entries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Position {
+    /// Before every other entry, so that they take precedence.
+    #[default]
+    First,
+    /// After every other entry, so that they only match what nothing else
+    /// does.
+    Last,
+}
+
+/// A description of a compiled entry.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EntryInfo {
+    pub id: usize,
+    pub origin: Origin,
+    pub method: String,
+    pub path: String,
+    /// Where the entry's request starts in its file, or in the snippet it
+    /// was added with.
+    pub line: usize,
+    pub column: usize,
+    /// How many responses the entry has.
+    pub responses: usize,
+    pub scenario: Option<String>,
+    /// How many requests the entry has handled.
+    pub hits: usize,
+}
+
+impl State {
+    /// Create the state for a router with the entries of a file.
+    pub(crate) fn new(entries: Vec<Entry>) -> error::Result<State> {
+        let state = State::default();
+        state.replace_file_entries(entries)?;
+        Ok(state)
+    }
+
+    /// Replace the entries that came from a file, e.g. when it's reloaded.
+    pub(crate) fn replace_file_entries(&self, entries: Vec<Entry>) -> error::Result<()> {
+        let mut registry = self.registry.write().expect("lock is never poisoned");
+        let entries = registry.assign_ids(entries);
+        registry.update(|registry| registry.file = entries)
+    }
+
+    /// Find the entries whose path matches `path`, in the order they should
+    /// be tried.
+    pub(crate) fn candidates(&self, path: &str) -> Vec<Arc<Entry>> {
+        self.registry
+            .read()
+            .expect("lock is never poisoned")
+            .routes
+            .candidates(path)
+    }
+
+    /// Find the entries whose path is close to `path`, without matching it,
+    /// in the order they're tried.
+    pub(crate) fn similar(&self, path: &str) -> Vec<Arc<Entry>> {
+        self.registry
+            .read()
+            .expect("lock is never poisoned")
+            .entries()
+            .filter(|(entry, _)| path_is_similar(&entry.path, path))
+            .map(|(entry, _)| entry.clone())
+            .collect()
+    }
+
+    /// Compile the entries of an Impostor file and add them to the router,
+    /// at the given position relative to the file's entries.
+    ///
+    /// Returns a description of the added entries, whose ids can be used to
+    /// remove them.
+    pub fn register(&self, contents: &str, position: Position) -> error::Result<Vec<EntryInfo>> {
+        let entries = compile_entries(parse(contents)?)?;
+
+        let mut registry = self.registry.write().expect("lock is never poisoned");
+        let mut entries = registry.assign_ids(entries);
+        let infos = entries
+            .iter()
+            .map(|entry| entry.info(Origin::Runtime))
+            .collect();
+        registry.update(|registry| match position {
+            Position::First => {
+                entries.append(&mut registry.first);
+                registry.first = entries;
+            }
+            Position::Last => registry.last.append(&mut entries),
+        })?;
+        Ok(infos)
+    }
+
+    /// Remove an entry that was added at runtime. Returns whether there was
+    /// such an entry.
+    pub fn remove(&self, id: usize) -> bool {
+        let mut registry = self.registry.write().expect("lock is never poisoned");
+        let is_runtime_entry = registry
+            .entries()
+            .any(|(entry, origin)| entry.id == id && origin == Origin::Runtime);
+        if !is_runtime_entry {
+            return false;
+        }
+
+        registry
+            .update(|registry| {
+                registry.first.retain(|entry| entry.id != id);
+                registry.last.retain(|entry| entry.id != id);
+            })
+            .expect("removing entries can't make their paths conflict");
+        true
+    }
+
+    /// Record a hit on an entry, and return how many hits it had before this
+    /// one.
+    pub(crate) fn hit(&self, entry_id: usize) -> usize {
+        let mut hits = self.hits.lock().expect("lock is never poisoned");
+        let entry_hits = hits.entry(entry_id).or_default();
+        *entry_hits += 1;
+        *entry_hits - 1
+    }
+
+    /// Describe the entries of the router in the order they're tried, along
+    /// with their hit counts.
+    pub fn entries(&self) -> Vec<EntryInfo> {
+        let registry = self.registry.read().expect("lock is never poisoned");
+        let hits = self.hits.lock().expect("lock is never poisoned");
+        registry
+            .entries()
+            .map(|(entry, origin)| EntryInfo {
+                hits: hits.get(&entry.id).copied().unwrap_or(0),
+                ..entry.info(origin)
+            })
+            .collect()
+    }
+
+    /// Get the current state of every scenario that has left its initial
+    /// state.
+    pub fn scenarios(&self) -> HashMap<String, String> {
+        self.scenarios
+            .lock()
+            .expect("lock is never poisoned")
+            .clone()
+    }
+
+    /// Get the most recent requests received by the router, oldest first.
+    pub fn journal(&self) -> Vec<JournalEntry> {
+        self.journal
+            .lock()
+            .expect("lock is never poisoned")
+            .entries()
+    }
+
+    /// Find the requests in the journal that match a request definition in
+    /// Impostor syntax, i.e. a request without a response.
+    ///
+    /// This is useful to check that a service called its dependencies the way
+    /// it should have, e.g. exactly once with a given body.
+    pub fn verify(&self, definition: &str) -> error::Result<Vec<JournalEntry>> {
+        let verification = Verification::compile(definition)?;
+        Ok(self
+            .journal()
+            .into_iter()
+            .filter(|entry| verification.matches(entry))
+            .collect())
+    }
+
+    pub(crate) fn record(&self, entry: JournalEntry) {
+        self.journal
+            .lock()
+            .expect("lock is never poisoned")
+            .record(entry);
+    }
+
+    /// Choose whether requests that no entry matched get a body explaining
+    /// why, instead of an empty one. The explanation is plain text, or JSON if
+    /// the request accepts it.
+    ///
+    /// The explanation is always logged at debug level.
+    pub fn set_verbose_not_found(&self, verbose: bool) {
+        self.verbose_not_found.store(verbose, Ordering::Relaxed);
+    }
+
+    pub(crate) fn verbose_not_found(&self) -> bool {
+        self.verbose_not_found.load(Ordering::Relaxed)
+    }
+
+    /// Choose a server to forward requests that no entry matched to, instead
+    /// of responding with a 404 or a 405.
+    pub fn set_proxy_unmatched(&self, upstream: Option<Upstream>) {
+        *self
+            .proxy_unmatched
+            .write()
+            .expect("lock is never poisoned") = upstream;
+    }
+
+    pub(crate) fn proxy_unmatched(&self) -> Option<Upstream> {
+        self.proxy_unmatched
+            .read()
+            .expect("lock is never poisoned")
+            .clone()
+    }
+
+    /// Get the current state of a scenario.
+    pub(crate) fn scenario_state(&self, scenario: &str) -> String {
+        self.scenarios
+            .lock()
+            .expect("lock is never poisoned")
+            .get(scenario)
+            .cloned()
+            .unwrap_or_else(|| INITIAL_STATE.to_string())
+    }
+
+    /// Move a scenario to a new state if it's in the state it's required to
+    /// be in, checking and moving it under the same lock, and return whether
+    /// it was. No required state means any state, and no new state means the
+    /// scenario stays where it is.
+    pub(crate) fn transition_scenario(
+        &self,
+        scenario: &str,
+        required: Option<&str>,
+        new_state: Option<&str>,
+    ) -> bool {
+        let mut scenarios = self.scenarios.lock().expect("lock is never poisoned");
+        let current = scenarios
+            .get(scenario)
+            .map_or(INITIAL_STATE, String::as_str);
+        if required.is_some_and(|required| required != current) {
+            return false;
+        }
+        if let Some(new_state) = new_state {
+            scenarios.insert(scenario.to_string(), new_state.to_string());
+        }
+        true
+    }
+
+    /// Go back to the state the router was compiled with: every scenario is
+    /// back in its initial state, every entry starts over from its first
+    /// response, and the journal is empty. Entries added at runtime are kept.
+    pub fn reset(&self) {
+        self.hits.lock().expect("lock is never poisoned").clear();
+        self.scenarios
+            .lock()
+            .expect("lock is never poisoned")
+            .clear();
+        self.journal.lock().expect("lock is never poisoned").clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn state(contents: &str) -> State {
+        State::new(compile_entries(parse(contents).unwrap()).unwrap()).unwrap()
+    }
+
+    fn paths(state: &State) -> Vec<(usize, String)> {
+        state
+            .entries()
+            .into_iter()
+            .map(|entry| (entry.id, entry.path))
+            .collect()
+    }
+
+    #[test]
+    fn test_hits_are_counted_per_entry() {
+        let state = State::default();
+        assert_eq!(state.hit(0), 0);
+        assert_eq!(state.hit(0), 1);
+        assert_eq!(state.hit(1), 0);
+        assert_eq!(state.hit(0), 2);
+    }
+
+    #[test]
+    fn test_reset() {
+        let state = State::default();
+        state.hit(0);
+        state.transition_scenario("checkout", None, Some("paid"));
+
+        state.reset();
+        assert_eq!(state.hit(0), 0);
+        assert_eq!(state.scenario_state("checkout"), INITIAL_STATE);
+    }
+
+    #[test]
+    fn test_register_and_remove() {
+        let state = state("GET /file\nHTTP 200\n");
+        state
+            .register("GET /last\nHTTP 200\n", Position::Last)
+            .unwrap();
+        let added = state
+            .register("GET /first\nHTTP 200\n", Position::First)
+            .unwrap();
+        assert_eq!(added[0].id, 2);
+        assert_eq!(added[0].origin, Origin::Runtime);
+        assert_eq!(
+            paths(&state),
+            vec![
+                (2, "/first".to_string()),
+                (0, "/file".to_string()),
+                (1, "/last".to_string()),
+            ]
+        );
+
+        assert!(!state.remove(0));
+        assert!(state.remove(2));
+        assert!(!state.remove(2));
+        assert_eq!(
+            paths(&state),
+            vec![(0, "/file".to_string()), (1, "/last".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_register_conflicting_path() {
+        let state = state("GET /orders/:id\nHTTP 200\n");
+        let result = state.register("GET /orders/:order_id\nHTTP 200\n", Position::First);
+        assert!(matches!(result, Err(error::Error::InvalidPath(_))));
+        assert_eq!(paths(&state), vec![(0, "/orders/:id".to_string())]);
+    }
+}
@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 
+use axum::extract::Request;
 use impostor_core::ast::Template;
 
-use crate::possibly_trim_surrounding_quotes;
+use crate::{journal::BufferedBody, template_value};
+
+/// The variables templates can use, built from the request being handled.
+pub(crate) type Context = HashMap<String, String>;
 
 pub enum TemplateExecutionError {
     UnknownVariable(String),
@@ -30,13 +34,17 @@ impl StringOrTemplate {
     /// Executes the template, substituting variables with values from the
     /// context. If the template is just a static string, returns a copy of that
     /// string. This means that this method will always allocate.
-    pub fn execute(&self) -> Result<String, TemplateExecutionError> {
+    pub fn execute(&self, context: &Context) -> Result<String, TemplateExecutionError> {
         match self {
             StringOrTemplate::String(s) => Ok(s.clone()),
-            StringOrTemplate::Template(t) => execute(t, HashMap::new()),
+            StringOrTemplate::Template(t) => execute(t, context),
         }
     }
 
+    pub fn is_template(&self) -> bool {
+        matches!(self, StringOrTemplate::Template(_))
+    }
+
     /// Converts an AST template into a `StringOrTemplate`.
     pub(crate) fn from_ast_template(template: Template) -> StringOrTemplate {
         if template
@@ -46,7 +54,7 @@ impl StringOrTemplate {
         {
             StringOrTemplate::Template(template)
         } else {
-            StringOrTemplate::String(possibly_trim_surrounding_quotes(template.encoded()))
+            StringOrTemplate::String(template_value(&template))
         }
     }
 }
@@ -57,10 +65,30 @@ impl From<Template> for StringOrTemplate {
     }
 }
 
-fn execute(
-    template: &Template,
-    context: HashMap<String, String>,
-) -> Result<String, TemplateExecutionError> {
+/// Build the context for templates from a request.
+///
+/// `request` describes the request the way it would look on the wire, e.g. so
+/// that a fallback entry can tell what's missing a mock.
+pub(crate) fn context(request: &Request) -> Context {
+    let mut description = format!("{} {}\n", request.method(), request.uri());
+    for (name, value) in request.headers() {
+        description.push_str(&format!(
+            "{}: {}\n",
+            name,
+            String::from_utf8_lossy(value.as_bytes())
+        ));
+    }
+    if let Some(body) = request.extensions().get::<BufferedBody>() {
+        if !body.0.is_empty() {
+            description.push('\n');
+            description.push_str(&String::from_utf8_lossy(&body.0));
+        }
+    }
+
+    HashMap::from([("request".to_string(), description)])
+}
+
+fn execute(template: &Template, context: &Context) -> Result<String, TemplateExecutionError> {
     let mut result = String::new();
 
     for element in template.elements.iter() {
@@ -0,0 +1,139 @@
+use axum::http::Uri;
+
+use crate::{
+    asserts::Assert,
+    dispatch::{method_matches, ANY_PATH},
+    entry::{compile_asserts, EntryCompilationError},
+    error,
+    journal::JournalEntry,
+};
+
+/// A request definition compiled to check which recorded requests match it.
+///
+/// A recorded request matches if it has the same method, its path matches the
+/// definition's path the same way the router would route it, and it passes
+/// every assert of the definition. Like in entries, `ANY` matches every method
+/// and `*` matches every path.
+pub(crate) struct Verification {
+    method: String,
+    // No router means any path.
+    path: Option<matchit::Router<()>>,
+    asserts: Vec<Assert>,
+}
+
+impl Verification {
+    /// Compile a request definition in Impostor syntax, without a response,
+    /// e.g.:
+    ///
+    /// ```text
+    /// POST /orders
+    /// [Asserts]
+    /// body contains "express"
+    /// ```
+    pub fn compile(definition: &str) -> error::Result<Verification> {
+        let request =
+            impostor_core::parser::parse_request(definition).map_err(error::Error::ParseError)?;
+
+        let path = request.path.to_string();
+        let path = if path == ANY_PATH {
+            None
+        } else {
+            let mut router = matchit::Router::new();
+            router
+                .insert(path, ())
+                .map_err(|e| error::Error::InvalidPath(Box::new(e)))?;
+            Some(router)
+        };
+        let asserts = compile_asserts(&request).map_err(|e| {
+            error::Error::EntryCompilationError(EntryCompilationError::AssertCompilationError(e))
+        })?;
+
+        Ok(Verification {
+            method: request.method.0,
+            path,
+            asserts,
+        })
+    }
+
+    /// Check if a recorded request matches the definition.
+    pub fn matches(&self, entry: &JournalEntry) -> bool {
+        if !method_matches(&self.method, &entry.method) {
+            return false;
+        }
+        let Ok(uri) = entry.uri.parse::<Uri>() else {
+            return false;
+        };
+        if let Some(path) = &self.path {
+            if path.at(uri.path()).is_err() {
+                return false;
+            }
+        }
+
+        let request = entry.to_request();
+        self.asserts
+            .iter()
+            .all(|a| a.apply(&request).is_ok_and(|r| r))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn journal_entry(method: &str, uri: &str, body: &str) -> JournalEntry {
+        JournalEntry {
+            method: method.to_string(),
+            uri: uri.to_string(),
+            headers: vec![],
+            body: body.to_string().into(),
+            entry: None,
+            status: 200,
+            received_at: 0,
+            duration: 0,
+        }
+    }
+
+    #[test]
+    fn test_verification_matches() {
+        let verification = Verification::compile(
+            "POST /orders/:id\n[Asserts]\nbody contains \"express\"\nqueryparam \"notify\" == \"true\"\n",
+        )
+        .unwrap();
+
+        assert!(verification.matches(&journal_entry(
+            "POST",
+            "/orders/1?notify=true",
+            "shipping: express"
+        )));
+        assert!(!verification.matches(&journal_entry(
+            "GET",
+            "/orders/1?notify=true",
+            "shipping: express"
+        )));
+        assert!(!verification.matches(&journal_entry(
+            "POST",
+            "/orders?notify=true",
+            "shipping: express"
+        )));
+        assert!(!verification.matches(&journal_entry(
+            "POST",
+            "/orders/1?notify=true",
+            "shipping: standard"
+        )));
+    }
+
+    #[test]
+    fn test_verification_any_method_and_path() {
+        let verification = Verification::compile("ANY *").unwrap();
+        assert!(verification.matches(&journal_entry("GET", "/", "")));
+        assert!(verification.matches(&journal_entry("DELETE", "/orders/1", "")));
+    }
+
+    #[test]
+    fn test_verification_compile_error() {
+        assert!(matches!(
+            Verification::compile("POST /orders\nHTTP 200\n"),
+            Err(error::Error::ParseError(_))
+        ));
+    }
+}
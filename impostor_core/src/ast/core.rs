@@ -27,10 +27,15 @@ pub struct ImpostorFile {
 }
 
 /// AST for a single mock entry.
+///
+/// An entry either has a proxy, or at least one response. When it has more,
+/// they are sent in order on successive calls, and the `after-last` option
+/// decides what happens once they run out.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Entry {
     pub request: Request,
-    pub response: Response,
+    pub responses: Vec<Response>,
+    pub proxy: Option<Proxy>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -67,6 +72,36 @@ impl Request {
         }
         vec![]
     }
+
+    /// Returns the query params of the `[QueryStringParams]` section.
+    pub fn query_params(&self) -> Vec<KeyValue> {
+        for section in self.sections.iter() {
+            if let RequestSectionValue::QueryParams(params) = &section.value {
+                return params.clone();
+            }
+        }
+        vec![]
+    }
+
+    /// Returns the cookies of the `[Cookies]` section.
+    pub fn cookies(&self) -> Vec<KeyValue> {
+        for section in self.sections.iter() {
+            if let RequestSectionValue::Cookies(cookies) = &section.value {
+                return cookies.clone();
+            }
+        }
+        vec![]
+    }
+
+    /// Returns the options list of this spec request.
+    pub fn options(&self) -> Vec<EntryOption> {
+        for section in self.sections.iter() {
+            if let RequestSectionValue::Options(options) = &section.value {
+                return options.clone();
+            }
+        }
+        vec![]
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -82,6 +117,20 @@ pub struct Response {
     pub source_info: SourceInfo,
 }
 
+/// AST for the proxy of an entry, e.g. `PROXY http://localhost:8080`, which
+/// forwards requests to another server instead of responding to them. Its
+/// headers are set on the forwarded requests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Proxy {
+    pub line_terminators: Vec<LineTerminator>,
+    pub space0: Whitespace,
+    pub space1: Whitespace,
+    pub url: Template,
+    pub line_terminator0: LineTerminator,
+    pub headers: Vec<Header>,
+    pub source_info: SourceInfo,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Method(pub String);
 
@@ -135,6 +184,9 @@ impl RequestSection {
         match self.value {
             RequestSectionValue::Asserts(_) => "Asserts",
             RequestSectionValue::Captures(_) => "Captures",
+            RequestSectionValue::Options(_) => "Options",
+            RequestSectionValue::QueryParams(_) => "QueryStringParams",
+            RequestSectionValue::Cookies(_) => "Cookies",
         }
     }
 }
@@ -144,6 +196,11 @@ impl RequestSection {
 pub enum RequestSectionValue {
     Captures(Vec<Capture>),
     Asserts(Vec<Assert>),
+    Options(Vec<EntryOption>),
+    /// Query params the request has, as in Hurl.
+    QueryParams(Vec<KeyValue>),
+    /// Cookies the request has, as in Hurl.
+    Cookies(Vec<KeyValue>),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -157,6 +214,118 @@ pub struct KeyValue {
     pub line_terminator0: LineTerminator,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntryOption {
+    pub line_terminators: Vec<LineTerminator>,
+    pub space0: Whitespace,
+    pub space1: Whitespace,
+    pub space2: Whitespace,
+    pub kind: OptionKind,
+    pub line_terminator0: LineTerminator,
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OptionKind {
+    Delay(Delay),
+    Fault(Fault),
+    AfterLast(AfterLast),
+    /// The scenario this entry belongs to.
+    Scenario(String),
+    /// The state the entry's scenario has to be in for the entry to match.
+    State(String),
+    /// The state the entry's scenario moves to when the entry responds.
+    NextState(String),
+    /// Whether the entry is only tried after the other entries for its path.
+    Fallback(bool),
+}
+
+impl OptionKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OptionKind::Delay(_) => "delay",
+            OptionKind::Fault(_) => "fault",
+            OptionKind::AfterLast(_) => "after-last",
+            OptionKind::Scenario(_) => "scenario",
+            OptionKind::State(_) => "state",
+            OptionKind::NextState(_) => "next-state",
+            OptionKind::Fallback(_) => "fallback",
+        }
+    }
+}
+
+/// How long an entry waits before responding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+pub enum Delay {
+    /// Always wait for the same duration, e.g. `250ms`.
+    Fixed(Duration),
+    /// Wait for a duration picked uniformly from the range, e.g. `100ms..500ms`.
+    Range { min: Duration, max: Duration },
+    /// Wait for a normally distributed duration, e.g. `normal(250ms, 50ms)`.
+    Normal {
+        space0: Whitespace,
+        mean: Duration,
+        space1: Whitespace,
+        space2: Whitespace,
+        std_dev: Duration,
+        space3: Whitespace,
+    },
+}
+
+/// A way for an entry to misbehave instead of sending a well-formed response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Close the connection without sending anything.
+    EmptyResponse,
+    /// Send the status, headers and part of the body, then close the
+    /// connection.
+    ConnectionReset,
+    /// Send part of the body with a `Content-Length` for all of it.
+    TruncatedBody,
+    /// Never respond.
+    Stall,
+    /// Send bytes that aren't a valid HTTP response.
+    MalformedResponse,
+}
+
+/// What an entry with several responses sends once it has sent all of them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AfterLast {
+    /// Keep sending the last response.
+    #[default]
+    RepeatLast,
+    /// Start over from the first response.
+    Cycle,
+    /// Respond with a 404, as if the entry didn't exist.
+    NotFound,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Duration {
+    pub value: u64,
+    pub unit: Option<DurationUnit>,
+}
+
+impl Duration {
+    /// Returns this duration in milliseconds. Durations without a unit are in
+    /// milliseconds.
+    pub fn as_millis(&self) -> u64 {
+        match self.unit {
+            None | Some(DurationUnit::Millisecond) => self.value,
+            Some(DurationUnit::Second) => self.value.saturating_mul(1000),
+            Some(DurationUnit::Minute) => self.value.saturating_mul(60_000),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DurationUnit {
+    Millisecond,
+    Second,
+    Minute,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Capture {
     pub line_terminators: Vec<LineTerminator>,
@@ -189,7 +358,11 @@ pub struct Query {
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[allow(clippy::large_enum_variant)]
 pub enum QueryValue {
-    Path,
+    /// The full URL of the request.
+    Url,
+    Host,
+    Scheme,
+    Port,
     Header {
         space0: Whitespace,
         name: Template,
@@ -211,6 +384,18 @@ pub enum QueryValue {
         space0: Whitespace,
         name: Template,
     },
+    /// A field of an `application/x-www-form-urlencoded` body.
+    Form {
+        space0: Whitespace,
+        name: Template,
+    },
+    /// A part of a `multipart/form-data` body: its content, or with an
+    /// attribute, its file name or content type.
+    Multipart {
+        space0: Whitespace,
+        name: Template,
+        attribute: Option<MultipartAttribute>,
+    },
     Regex {
         space0: Whitespace,
         value: RegexValue,
@@ -224,6 +409,27 @@ pub enum QueryValue {
     Md5,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultipartAttribute {
+    pub space0: Whitespace,
+    pub name: MultipartAttributeName,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MultipartAttributeName {
+    Filename,
+    ContentType,
+}
+
+impl MultipartAttributeName {
+    pub fn value(&self) -> &'static str {
+        match self {
+            MultipartAttributeName::Filename => "filename",
+            MultipartAttributeName::ContentType => "contentType",
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum RegexValue {
     Template(Template),
@@ -297,6 +503,8 @@ pub enum PredicateValue {
     Expression(Expr),
     File(File),
     Hex(Hex),
+    /// A JSON object or array, which is compared as JSON.
+    Json(json::Value),
     MultilineString(MultilineString),
     Null,
     Number(Number),
@@ -353,10 +561,22 @@ pub enum PredicateFuncValue {
         space0: Whitespace,
         value: PredicateValue,
     },
+    /// Checks that JSON has at least the members of a JSON value, with arrays
+    /// in any order if `unordered` (the whitespace after it) is set.
+    IncludeJson {
+        space0: Whitespace,
+        unordered: Option<Whitespace>,
+        value: PredicateValue,
+    },
     Match {
         space0: Whitespace,
         value: PredicateValue,
     },
+    /// Checks that JSON is valid against a JSON Schema, in a file or inline.
+    MatchSchema {
+        space0: Whitespace,
+        value: PredicateValue,
+    },
     IsInteger,
     IsFloat,
     IsBoolean,
@@ -633,10 +853,15 @@ pub enum FilterValue {
     },
     HtmlEscape,
     HtmlUnescape,
+    /// Lowercases strings, and the strings the predicate compares them to.
+    IgnoreCase,
     JsonPath {
         space0: Whitespace,
         expr: Template,
     },
+    /// Normalizes media types, like in a `Content-Type` or `Accept` header,
+    /// and the ones the predicate compares them to.
+    MediaType,
     Nth {
         space0: Whitespace,
         n: u64,
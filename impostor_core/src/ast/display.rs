@@ -187,6 +187,71 @@ impl fmt::Display for RetryOption {
     }
 }
 
+impl fmt::Display for Delay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Delay::Fixed(duration) => write!(f, "{duration}"),
+            Delay::Range { min, max } => write!(f, "{min}..{max}"),
+            Delay::Normal {
+                space0,
+                mean,
+                space1,
+                space2,
+                std_dev,
+                space3,
+            } => write!(
+                f,
+                "normal({}{mean}{},{}{std_dev}{})",
+                space0.value, space1.value, space2.value, space3.value
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Fault::EmptyResponse => "empty-response",
+            Fault::ConnectionReset => "connection-reset",
+            Fault::TruncatedBody => "truncated-body",
+            Fault::Stall => "stall",
+            Fault::MalformedResponse => "malformed-response",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl fmt::Display for AfterLast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            AfterLast::RepeatLast => "repeat-last",
+            AfterLast::Cycle => "cycle",
+            AfterLast::NotFound => "not-found",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.unit {
+            Some(unit) => write!(f, "{}{}", self.value, unit),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+impl fmt::Display for DurationUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            DurationUnit::Millisecond => "ms",
+            DurationUnit::Second => "s",
+            DurationUnit::Minute => "m",
+        };
+        write!(f, "{s}")
+    }
+}
+
 impl fmt::Display for VariableDefinition {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}={}", self.name, self.value)
@@ -254,7 +319,9 @@ impl PredicateFuncValue {
             PredicateFuncValue::EndWith { .. } => "endsWith".to_string(),
             PredicateFuncValue::Contain { .. } => "contains".to_string(),
             PredicateFuncValue::Include { .. } => "includes".to_string(),
+            PredicateFuncValue::IncludeJson { .. } => "includesJson".to_string(),
             PredicateFuncValue::Match { .. } => "matches".to_string(),
+            PredicateFuncValue::MatchSchema { .. } => "matchesSchema".to_string(),
             PredicateFuncValue::IsInteger => "isInteger".to_string(),
             PredicateFuncValue::IsFloat => "isFloat".to_string(),
             PredicateFuncValue::IsBoolean => "isBoolean".to_string(),
@@ -31,9 +31,11 @@ pub struct Error {
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ParseError {
+    AfterLast { name: String },
     DuplicateSection,
     EscapeChar,
     Expecting { value: String },
+    Fault { name: String },
     FileContentType,
     Filename,
     GraphQlVariables,
@@ -101,9 +103,11 @@ impl crate::error::Error for Error {
 
     fn description(&self) -> String {
         match self.inner {
+            ParseError::AfterLast { .. } => "Parsing after-last".to_string(),
             ParseError::DuplicateSection => "Parsing section".to_string(),
             ParseError::EscapeChar => "Parsing escape character".to_string(),
             ParseError::Expecting { .. } => "Parsing literal".to_string(),
+            ParseError::Fault { .. } => "Parsing fault".to_string(),
             ParseError::FileContentType => "Parsing file content type".to_string(),
             ParseError::Filename => "Parsing filename".to_string(),
             ParseError::GraphQlVariables => "Parsing GraphQL variables".to_string(),
@@ -136,9 +140,27 @@ impl crate::error::Error for Error {
 
     fn fixme(&self) -> String {
         match &self.inner {
+            ParseError::AfterLast { name } => {
+                let valid_values = ["repeat-last", "cycle", "not-found"];
+                let default = format!("Valid values are {}", valid_values.join(", "));
+                let did_you_mean = did_you_mean(&valid_values, name.as_str(), &default);
+                format!("the after-last value <{name}> is not valid. {did_you_mean}")
+            }
             ParseError::DuplicateSection => "the section is already defined".to_string(),
             ParseError::EscapeChar => "the escaping sequence is not valid".to_string(),
             ParseError::Expecting { value } => format!("expecting '{value}'"),
+            ParseError::Fault { name } => {
+                let valid_values = [
+                    "empty-response",
+                    "connection-reset",
+                    "truncated-body",
+                    "stall",
+                    "malformed-response",
+                ];
+                let default = format!("Valid values are {}", valid_values.join(", "));
+                let did_you_mean = did_you_mean(&valid_values, name.as_str(), &default);
+                format!("the fault <{name}> is not valid. {did_you_mean}")
+            }
             ParseError::FileContentType => "expecting a content type".to_string(),
             ParseError::Filename => "expecting a filename".to_string(),
             ParseError::GraphQlVariables => {
@@ -148,32 +170,13 @@ impl crate::error::Error for Error {
             ParseError::InvalidCookieAttribute => "the cookie attribute is not valid".to_string(),
             ParseError::InvalidOption(name) => {
                 let valid_values = [
-                    "aws-sigv4",
-                    "cacert",
-                    "cert",
-                    "compressed",
-                    "connect-to",
+                    "after-last",
                     "delay",
-                    "insecure",
-                    "http1.0",
-                    "http1.1",
-                    "http2",
-                    "http3",
-                    "ipv4",
-                    "ipv6",
-                    "key",
-                    "location",
-                    "max-redirs",
-                    "output",
-                    "path-as-is",
-                    "proxy",
-                    "resolve",
-                    "retry",
-                    "retry-interval",
-                    "skip",
-                    "variable",
-                    "verbose",
-                    "very-verbose",
+                    "fallback",
+                    "fault",
+                    "next-state",
+                    "scenario",
+                    "state",
                 ];
                 let default = format!("Valid values are {}", valid_values.join(", "));
                 let did_you_mean = did_you_mean(&valid_values, name.as_str(), &default);
@@ -219,8 +222,8 @@ impl crate::error::Error for Error {
             }
             ParseError::ResponseSection => "this is not a valid section for a response".to_string(),
             ParseError::ResponseSectionName { name } => {
-                let valid_values = ["Captures", "Asserts"];
-                let default = "Valid values are Captures or Asserts";
+                let valid_values = ["Captures", "Asserts", "Options"];
+                let default = "Valid values are Captures, Asserts or Options";
                 let did_your_mean = did_you_mean(&valid_values, name.as_str(), default);
                 format!("the section is not valid. {did_your_mean}")
             }
@@ -59,7 +59,9 @@ pub fn filter(reader: &mut Reader) -> ParseResult<Filter> {
             format_filter,
             html_decode_filter,
             html_encode_filter,
+            ignore_case_filter,
             jsonpath_filter,
+            media_type_filter,
             nth_filter,
             regex_filter,
             replace_filter,
@@ -126,6 +128,11 @@ fn html_decode_filter(reader: &mut Reader) -> ParseResult<FilterValue> {
     Ok(FilterValue::HtmlUnescape)
 }
 
+fn ignore_case_filter(reader: &mut Reader) -> ParseResult<FilterValue> {
+    try_literal("ignoreCase", reader)?;
+    Ok(FilterValue::IgnoreCase)
+}
+
 fn jsonpath_filter(reader: &mut Reader) -> ParseResult<FilterValue> {
     try_literal("jsonpath", reader)?;
     let space0 = one_or_more_spaces(reader)?;
@@ -133,6 +140,11 @@ fn jsonpath_filter(reader: &mut Reader) -> ParseResult<FilterValue> {
     Ok(FilterValue::JsonPath { space0, expr })
 }
 
+fn media_type_filter(reader: &mut Reader) -> ParseResult<FilterValue> {
+    try_literal("mediaType", reader)?;
+    Ok(FilterValue::MediaType)
+}
+
 fn nth_filter(reader: &mut Reader) -> ParseResult<FilterValue> {
     try_literal("nth", reader)?;
     let space0 = one_or_more_spaces(reader)?;
@@ -215,6 +227,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_filters() {
+        let mut reader = Reader::new(" mediaType ignoreCase ==");
+        let filters = filters(&mut reader).unwrap();
+        assert_eq!(
+            filters
+                .into_iter()
+                .map(|(_, filter)| filter.value)
+                .collect::<Vec<FilterValue>>(),
+            vec![FilterValue::MediaType, FilterValue::IgnoreCase]
+        );
+        assert_eq!(reader.state.cursor, 21);
+    }
+
     #[test]
     fn test_error() {
         let mut reader = Reader::new("xcount");
@@ -270,7 +270,7 @@ fn expression_value(reader: &mut Reader) -> ParseResult<JsonValue> {
     Ok(JsonValue::Expression(exp))
 }
 
-fn list_value(reader: &mut Reader) -> ParseResult<JsonValue> {
+pub fn list_value(reader: &mut Reader) -> ParseResult<JsonValue> {
     try_literal("[", reader)?;
     let space0 = whitespace(reader);
     let mut elements = vec![];
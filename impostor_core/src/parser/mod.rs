@@ -23,6 +23,12 @@ pub fn parse_impostor_file(s: &str) -> ParseResult<ImpostorFile> {
     parsers::impostor_file(&mut reader)
 }
 
+/// Parse a single request definition, without a response.
+pub fn parse_request(s: &str) -> ParseResult<Request> {
+    let mut reader = Reader::new(s);
+    parsers::request_only(&mut reader)
+}
+
 pub use self::error::{Error, JsonErrorVariant, ParseError};
 pub use self::json::{
     boolean_value as parse_json_boolean, null_value as parse_json_null,
@@ -30,7 +36,7 @@ pub use self::json::{
 };
 pub use self::reader::Reader;
 pub use self::template::templatize;
-use crate::ast::ImpostorFile;
+use crate::ast::{ImpostorFile, Request};
 
 mod base64;
 mod bytes;
@@ -44,6 +50,7 @@ mod json;
 mod key_string;
 mod multiline;
 mod number;
+mod options;
 mod parsers;
 mod path;
 mod predicate;
@@ -0,0 +1,325 @@
+/*
+ * Copyright (C) 2023 The Impostor Contributors
+ * Copyright (C) 2023 Orange
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *          http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ */
+use crate::ast::*;
+use crate::parser::error::*;
+use crate::parser::number::natural;
+use crate::parser::primitives::*;
+use crate::parser::reader::Reader;
+use crate::parser::ParseResult;
+
+/// Parse an option in an `[Options]` section.
+pub fn parse(reader: &mut Reader) -> ParseResult<EntryOption> {
+    let line_terminators = optional_line_terminators(reader)?;
+    let space0 = zero_or_more_spaces(reader)?;
+    let start = reader.state;
+    // We accept '_' even though no option name uses it, so that a misspelled
+    // name still gets a "did you mean" suggestion instead of a generic error.
+    let option = reader.read_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_');
+    let space1 = zero_or_more_spaces(reader)?;
+    try_literal(":", reader)?;
+    let space2 = zero_or_more_spaces(reader)?;
+    let kind = match option.as_str() {
+        "delay" => option_delay(reader)?,
+        "fault" => option_fault(reader)?,
+        "after-last" => option_after_last(reader)?,
+        "scenario" => OptionKind::Scenario(name(reader)?),
+        "state" => OptionKind::State(name(reader)?),
+        "next-state" => OptionKind::NextState(name(reader)?),
+        "fallback" => option_fallback(reader)?,
+        _ => {
+            return Err(Error::new(
+                start.pos,
+                false,
+                ParseError::InvalidOption(option),
+            ))
+        }
+    };
+
+    let line_terminator0 = line_terminator(reader)?;
+    Ok(EntryOption {
+        line_terminators,
+        space0,
+        space1,
+        space2,
+        kind,
+        line_terminator0,
+    })
+}
+
+fn option_delay(reader: &mut Reader) -> ParseResult<OptionKind> {
+    let value = delay(reader)?;
+    Ok(OptionKind::Delay(value))
+}
+
+fn option_fault(reader: &mut Reader) -> ParseResult<OptionKind> {
+    let start = reader.state;
+    let name = reader.read_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_');
+    let value = match name.as_str() {
+        "empty-response" => Fault::EmptyResponse,
+        "connection-reset" => Fault::ConnectionReset,
+        "truncated-body" => Fault::TruncatedBody,
+        "stall" => Fault::Stall,
+        "malformed-response" => Fault::MalformedResponse,
+        _ => return Err(Error::new(start.pos, false, ParseError::Fault { name })),
+    };
+    Ok(OptionKind::Fault(value))
+}
+
+fn option_after_last(reader: &mut Reader) -> ParseResult<OptionKind> {
+    let start = reader.state;
+    let name = reader.read_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_');
+    let value = match name.as_str() {
+        "repeat-last" => AfterLast::RepeatLast,
+        "cycle" => AfterLast::Cycle,
+        "not-found" => AfterLast::NotFound,
+        _ => return Err(Error::new(start.pos, false, ParseError::AfterLast { name })),
+    };
+    Ok(OptionKind::AfterLast(value))
+}
+
+fn option_fallback(reader: &mut Reader) -> ParseResult<OptionKind> {
+    match boolean(reader) {
+        Ok(value) => Ok(OptionKind::Fallback(value)),
+        // The option name is known at this point, so there's nothing else
+        // this could be.
+        Err(e) => Err(Error::new(e.pos, false, e.inner)),
+    }
+}
+
+/// Parse the name of a scenario or of a scenario state.
+fn name(reader: &mut Reader) -> ParseResult<String> {
+    let start = reader.state;
+    let name = reader.read_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_');
+    if name.is_empty() {
+        let inner = ParseError::Expecting {
+            value: "name".to_string(),
+        };
+        return Err(Error::new(start.pos, false, inner));
+    }
+    Ok(name)
+}
+
+fn delay(reader: &mut Reader) -> ParseResult<Delay> {
+    if try_literal("normal(", reader).is_ok() {
+        let space0 = zero_or_more_spaces(reader)?;
+        let mean = duration(reader)?;
+        let space1 = zero_or_more_spaces(reader)?;
+        literal(",", reader)?;
+        let space2 = zero_or_more_spaces(reader)?;
+        let std_dev = duration(reader)?;
+        let space3 = zero_or_more_spaces(reader)?;
+        literal(")", reader)?;
+        return Ok(Delay::Normal {
+            space0,
+            mean,
+            space1,
+            space2,
+            std_dev,
+            space3,
+        });
+    }
+
+    let start = reader.state;
+    let min = duration(reader)?;
+    if try_literal("..", reader).is_err() {
+        return Ok(Delay::Fixed(min));
+    }
+    let max = duration(reader)?;
+    if max.as_millis() < min.as_millis() {
+        let inner = ParseError::Expecting {
+            value: "a delay range whose end is not less than its start".to_string(),
+        };
+        return Err(Error::new(start.pos, false, inner));
+    }
+    Ok(Delay::Range { min, max })
+}
+
+fn duration(reader: &mut Reader) -> ParseResult<Duration> {
+    let start = reader.state;
+    let value = natural(reader).map_err(|_| {
+        let inner = ParseError::Expecting {
+            value: "duration".to_string(),
+        };
+        Error::new(start.pos, false, inner)
+    })?;
+    let unit = duration_unit(reader);
+    Ok(Duration { value, unit })
+}
+
+fn duration_unit(reader: &mut Reader) -> Option<DurationUnit> {
+    if try_literal("ms", reader).is_ok() {
+        Some(DurationUnit::Millisecond)
+    } else if try_literal("s", reader).is_ok() {
+        Some(DurationUnit::Second)
+    } else if try_literal("m", reader).is_ok() {
+        Some(DurationUnit::Minute)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_option_delay() {
+        let mut reader = Reader::new("delay: 250ms");
+        let option = parse(&mut reader).unwrap();
+        assert_eq!(
+            option.kind,
+            OptionKind::Delay(Delay::Fixed(Duration {
+                value: 250,
+                unit: Some(DurationUnit::Millisecond),
+            }))
+        );
+        assert_eq!(reader.state.cursor, 12);
+    }
+
+    #[test]
+    fn test_option_fault() {
+        let mut reader = Reader::new("fault: connection-reset\n");
+        let option = parse(&mut reader).unwrap();
+        assert_eq!(option.kind, OptionKind::Fault(Fault::ConnectionReset));
+
+        let mut reader = Reader::new("fault: crash");
+        let error = parse(&mut reader).err().unwrap();
+        assert_eq!(error.pos, Pos { line: 1, column: 8 });
+        assert_eq!(
+            error.inner,
+            ParseError::Fault {
+                name: "crash".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_option_after_last() {
+        let mut reader = Reader::new("after-last: cycle\n");
+        let option = parse(&mut reader).unwrap();
+        assert_eq!(option.kind, OptionKind::AfterLast(AfterLast::Cycle));
+
+        let mut reader = Reader::new("after-last: repeat");
+        let error = parse(&mut reader).err().unwrap();
+        assert_eq!(
+            error.pos,
+            Pos {
+                line: 1,
+                column: 13
+            }
+        );
+        assert_eq!(
+            error.inner,
+            ParseError::AfterLast {
+                name: "repeat".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_option_scenario() {
+        let mut reader = Reader::new("scenario: checkout\n");
+        let option = parse(&mut reader).unwrap();
+        assert_eq!(option.kind, OptionKind::Scenario("checkout".to_string()));
+
+        let mut reader = Reader::new("next-state: order_paid\n");
+        let option = parse(&mut reader).unwrap();
+        assert_eq!(option.kind, OptionKind::NextState("order_paid".to_string()));
+
+        let mut reader = Reader::new("state: \n");
+        let error = parse(&mut reader).err().unwrap();
+        assert_eq!(error.pos, Pos { line: 1, column: 8 });
+        assert!(!error.recoverable);
+    }
+
+    #[test]
+    fn test_option_fallback() {
+        let mut reader = Reader::new("fallback: true\n");
+        let option = parse(&mut reader).unwrap();
+        assert_eq!(option.kind, OptionKind::Fallback(true));
+
+        let mut reader = Reader::new("fallback: yes\n");
+        let error = parse(&mut reader).err().unwrap();
+        assert_eq!(
+            error.pos,
+            Pos {
+                line: 1,
+                column: 11
+            }
+        );
+        assert!(!error.recoverable);
+    }
+
+    #[test]
+    fn test_delay() {
+        let mut reader = Reader::new("250");
+        assert_eq!(
+            delay(&mut reader).unwrap(),
+            Delay::Fixed(Duration {
+                value: 250,
+                unit: None,
+            })
+        );
+
+        let mut reader = Reader::new("100ms..2s");
+        assert_eq!(
+            delay(&mut reader).unwrap(),
+            Delay::Range {
+                min: Duration {
+                    value: 100,
+                    unit: Some(DurationUnit::Millisecond),
+                },
+                max: Duration {
+                    value: 2,
+                    unit: Some(DurationUnit::Second),
+                },
+            }
+        );
+
+        let mut reader = Reader::new("normal(250ms, 1m)");
+        let value = delay(&mut reader).unwrap();
+        assert_eq!(value.to_string(), "normal(250ms, 1m)");
+        assert_eq!(reader.state.cursor, 17);
+    }
+
+    #[test]
+    fn test_delay_error() {
+        let mut reader = Reader::new("fast");
+        let error = delay(&mut reader).err().unwrap();
+        assert_eq!(error.pos, Pos { line: 1, column: 1 });
+        assert!(!error.recoverable);
+
+        let mut reader = Reader::new("2s..100ms");
+        let error = delay(&mut reader).err().unwrap();
+        assert_eq!(error.pos, Pos { line: 1, column: 1 });
+        assert!(!error.recoverable);
+    }
+
+    #[test]
+    fn test_option_error() {
+        let mut reader = Reader::new("dealy: 250ms");
+        let error = parse(&mut reader).err().unwrap();
+        assert_eq!(error.pos, Pos { line: 1, column: 1 });
+        assert_eq!(error.inner, ParseError::InvalidOption("dealy".to_string()));
+        assert!(!error.recoverable);
+
+        let mut reader = Reader::new("[Asserts]");
+        let error = parse(&mut reader).err().unwrap();
+        assert!(error.recoverable);
+    }
+}
@@ -25,6 +25,7 @@ use crate::parser::path::path;
 use crate::parser::primitives::*;
 use crate::parser::reader::Reader;
 use crate::parser::sections::*;
+use crate::parser::string::unquoted_template;
 use crate::parser::ParseResult;
 
 pub fn impostor_file(reader: &mut Reader) -> ParseResult<ImpostorFile> {
@@ -37,12 +38,31 @@ pub fn impostor_file(reader: &mut Reader) -> ParseResult<ImpostorFile> {
     })
 }
 
+/// Parse a request on its own, without a response, e.g. to describe requests
+/// that were received.
+pub fn request_only(reader: &mut Reader) -> ParseResult<Request> {
+    let req = request(reader)?;
+    optional_line_terminators(reader)?;
+    eof(reader)?;
+    Ok(req)
+}
+
 fn entry(reader: &mut Reader) -> ParseResult<Entry> {
     let req = request(reader)?;
-    let resp = response(reader)?;
+    if let Some(proxy) = optional(proxy, reader)? {
+        return Ok(Entry {
+            request: req,
+            responses: vec![],
+            proxy: Some(proxy),
+        });
+    }
+    let mut responses = vec![response(reader)?];
+    // Further responses stop at the next request, whose method isn't `HTTP`.
+    responses.extend(zero_or_more(response, reader)?);
     Ok(Entry {
         request: req,
-        response: resp,
+        responses,
+        proxy: None,
     })
 }
 
@@ -111,6 +131,26 @@ fn response(reader: &mut Reader) -> ParseResult<Response> {
     })
 }
 
+fn proxy(reader: &mut Reader) -> ParseResult<Proxy> {
+    let start = reader.state;
+    let line_terminators = optional_line_terminators(reader)?;
+    let space0 = zero_or_more_spaces(reader)?;
+    try_literal("PROXY", reader)?;
+    let space1 = one_or_more_spaces(reader)?;
+    let url = unquoted_template(reader)?;
+    let line_terminator0 = line_terminator(reader)?;
+    let headers = zero_or_more(key_value, reader)?;
+    Ok(Proxy {
+        line_terminators,
+        space0,
+        space1,
+        url,
+        line_terminator0,
+        headers,
+        source_info: SourceInfo::new(start.pos, reader.state.pos),
+    })
+}
+
 fn method(reader: &mut Reader) -> ParseResult<Method> {
     if reader.is_eof() {
         let inner = ParseError::Method {
@@ -119,8 +159,10 @@ fn method(reader: &mut Reader) -> ParseResult<Method> {
         return Err(Error::new(reader.state.pos, true, inner));
     }
     let start = reader.state;
-    let name = reader.read_while(|c| c.is_ascii_alphabetic());
-    if name.is_empty() || name.to_uppercase() != name {
+    // Several methods can be separated by `|`, e.g. `GET|HEAD`.
+    let name = reader.read_while(|c| c.is_ascii_alphabetic() || *c == '|');
+    let is_valid = |method: &str| !method.is_empty() && method.to_uppercase() == method;
+    if !name.split('|').all(is_valid) {
         let inner = ParseError::Method { name };
         Err(Error::new(start.pos, false, inner))
     } else {
@@ -244,7 +286,50 @@ mod tests {
         let mut reader = Reader::new("GET http://google.fr\nHTTP/1.1 200");
         let e = entry(&mut reader).unwrap();
         assert_eq!(e.request.method, Method("GET".to_string()));
-        assert_eq!(e.response.status.value, 200);
+        assert_eq!(e.responses[0].status.value, 200);
+    }
+
+    #[test]
+    fn test_entry_with_proxy() {
+        let mut reader = Reader::new(
+            "GET /users\nPROXY http://localhost:8080/api # real API\nX-Api-Key: test\nGET /health\nHTTP 200",
+        );
+        let e = entry(&mut reader).unwrap();
+        assert!(e.responses.is_empty());
+        let proxy = e.proxy.unwrap();
+        assert_eq!(proxy.url.to_string(), "http://localhost:8080/api");
+        assert_eq!(proxy.headers.len(), 1);
+        assert_eq!(proxy.headers[0].key.to_string(), "X-Api-Key");
+
+        let e = entry(&mut reader).unwrap();
+        assert!(e.proxy.is_none());
+        assert_eq!(e.responses[0].status.value, 200);
+
+        let mut reader = Reader::new("GET /users\nPROXY\n");
+        assert!(entry(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_request_only() {
+        let mut reader = Reader::new("POST /orders\n[Asserts]\nheader \"foo\" == \"bar\"\n\n");
+        let r = request_only(&mut reader).unwrap();
+        assert_eq!(r.method, Method("POST".to_string()));
+        assert_eq!(r.asserts().len(), 1);
+
+        let mut reader = Reader::new("GET /orders\nHTTP 200");
+        assert!(request_only(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_entry_with_several_responses() {
+        let mut reader = Reader::new("GET /foo\nHTTP 503\n\nHTTP 200\n`ok`\n\nGET /bar\nHTTP 200");
+        let e = entry(&mut reader).unwrap();
+        let statuses: Vec<u16> = e.responses.iter().map(|r| r.status.value).collect();
+        assert_eq!(statuses, vec![503, 200]);
+
+        let e = entry(&mut reader).unwrap();
+        assert_eq!(e.request.path.to_string(), "/bar");
+        assert_eq!(e.responses.len(), 1);
     }
 
     #[test]
@@ -530,6 +615,14 @@ mod tests {
         let mut reader = Reader::new("CUSTOM");
         assert_eq!(method(&mut reader).unwrap(), Method("CUSTOM".to_string()));
         assert_eq!(reader.state.cursor, 6);
+
+        let mut reader = Reader::new("GET|HEAD /");
+        assert_eq!(method(&mut reader).unwrap(), Method("GET|HEAD".to_string()));
+        assert_eq!(reader.state.cursor, 8);
+
+        let mut reader = Reader::new("GET| /");
+        let error = method(&mut reader).err().unwrap();
+        assert_eq!(error.pos, Pos { line: 1, column: 1 });
     }
 
     #[test]
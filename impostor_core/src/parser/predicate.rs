@@ -79,7 +79,9 @@ fn predicate_func_value(reader: &mut Reader) -> ParseResult<PredicateFuncValue>
             start_with_predicate,
             end_with_predicate,
             contain_predicate,
+            include_json_predicate,
             include_predicate,
+            match_schema_predicate,
             match_predicate,
             integer_predicate,
             float_predicate,
@@ -284,6 +286,25 @@ fn include_predicate(reader: &mut Reader) -> ParseResult<PredicateFuncValue> {
     Ok(PredicateFuncValue::Include { space0, value })
 }
 
+fn include_json_predicate(reader: &mut Reader) -> ParseResult<PredicateFuncValue> {
+    try_literal("includesJson", reader)?;
+    let space0 = one_or_more_spaces(reader)?;
+    let unordered = match try_literal("unordered", reader) {
+        Ok(()) => Some(one_or_more_spaces(reader)?),
+        Err(_) => None,
+    };
+    let save = reader.state;
+    let value = predicate_value(reader)?;
+    if !matches!(value, PredicateValue::Json(_)) {
+        return Err(Error::new(save.pos, false, ParseError::PredicateValue));
+    }
+    Ok(PredicateFuncValue::IncludeJson {
+        space0,
+        unordered,
+        value,
+    })
+}
+
 fn match_predicate(reader: &mut Reader) -> ParseResult<PredicateFuncValue> {
     try_literal("matches", reader)?;
     let space0 = one_or_more_spaces(reader)?;
@@ -295,6 +316,17 @@ fn match_predicate(reader: &mut Reader) -> ParseResult<PredicateFuncValue> {
     Ok(PredicateFuncValue::Match { space0, value })
 }
 
+fn match_schema_predicate(reader: &mut Reader) -> ParseResult<PredicateFuncValue> {
+    try_literal("matchesSchema", reader)?;
+    let space0 = one_or_more_spaces(reader)?;
+    let save = reader.state;
+    let value = predicate_value(reader)?;
+    if !matches!(value, PredicateValue::File(_) | PredicateValue::Json(_)) {
+        return Err(Error::new(save.pos, false, ParseError::PredicateValue));
+    }
+    Ok(PredicateFuncValue::MatchSchema { space0, value })
+}
+
 fn integer_predicate(reader: &mut Reader) -> ParseResult<PredicateFuncValue> {
     try_literal("isInteger", reader)?;
     Ok(PredicateFuncValue::IsInteger)
@@ -514,6 +546,69 @@ mod tests {
         assert_eq!(error.inner, ParseError::PredicateValue);
     }
 
+    #[test]
+    fn test_include_json_predicate() {
+        let mut reader = Reader::new("includesJson unordered {\"tags\": [\"a\"]}");
+        let predicate = predicate_func_value(&mut reader).unwrap();
+        let PredicateFuncValue::IncludeJson {
+            unordered, value, ..
+        } = predicate
+        else {
+            panic!("expected includesJson, got {:?}", predicate);
+        };
+        assert!(unordered.is_some());
+        assert!(matches!(
+            value,
+            PredicateValue::Json(JsonValue::Object { .. })
+        ));
+
+        let mut reader = Reader::new("includesJson [1]");
+        assert!(matches!(
+            predicate_func_value(&mut reader).unwrap(),
+            PredicateFuncValue::IncludeJson {
+                unordered: None,
+                value: PredicateValue::Json(JsonValue::List { .. }),
+                ..
+            }
+        ));
+
+        let mut reader = Reader::new("includesJson \"a\"");
+        let error = predicate_func_value(&mut reader).err().unwrap();
+        assert_eq!(
+            error.pos,
+            Pos {
+                line: 1,
+                column: 14
+            }
+        );
+        assert_eq!(error.inner, ParseError::PredicateValue);
+    }
+
+    #[test]
+    fn test_match_schema_predicate() {
+        let mut reader = Reader::new("matchesSchema file,schemas/order.json;");
+        let predicate = predicate_func_value(&mut reader).unwrap();
+        let PredicateFuncValue::MatchSchema {
+            value: PredicateValue::File(file),
+            ..
+        } = predicate
+        else {
+            panic!("expected matchesSchema, got {:?}", predicate);
+        };
+        assert_eq!(file.filename.value, "schemas/order.json");
+
+        let mut reader = Reader::new("matchesSchema /a/");
+        let error = predicate_func_value(&mut reader).err().unwrap();
+        assert_eq!(
+            error.pos,
+            Pos {
+                line: 1,
+                column: 15
+            }
+        );
+        assert_eq!(error.inner, ParseError::PredicateValue);
+    }
+
     #[test]
     fn test_date_predicate() {
         let mut reader = Reader::new("isDate");
@@ -17,6 +17,7 @@
  */
 use crate::ast::*;
 use crate::parser::combinators::*;
+use crate::parser::json;
 use crate::parser::multiline::multiline_string;
 use crate::parser::number::number;
 use crate::parser::primitives::*;
@@ -67,6 +68,14 @@ pub fn predicate_value(reader: &mut Reader) -> ParseResult<PredicateValue> {
                 Ok(value) => Ok(PredicateValue::Regex(value)),
                 Err(e) => Err(e),
             },
+            |p1| match json::object_value(p1) {
+                Ok(value) => Ok(PredicateValue::Json(value)),
+                Err(e) => Err(e),
+            },
+            |p1| match json::list_value(p1) {
+                Ok(value) => Ok(PredicateValue::Json(value)),
+                Err(e) => Err(e),
+            },
         ],
         reader,
     )
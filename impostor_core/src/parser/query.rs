@@ -37,8 +37,13 @@ fn query_value(reader: &mut Reader) -> ParseResult<QueryValue> {
     choice(
         &[
             url_query,
+            host_query,
+            scheme_query,
+            port_query,
             header_query,
             query_param_query,
+            form_query,
+            multipart_query,
             cookie_query,
             body_query,
             xpath_query,
@@ -55,7 +60,22 @@ fn query_value(reader: &mut Reader) -> ParseResult<QueryValue> {
 
 fn url_query(reader: &mut Reader) -> ParseResult<QueryValue> {
     try_literal("url", reader)?;
-    Ok(QueryValue::Path)
+    Ok(QueryValue::Url)
+}
+
+fn host_query(reader: &mut Reader) -> ParseResult<QueryValue> {
+    try_literal("host", reader)?;
+    Ok(QueryValue::Host)
+}
+
+fn scheme_query(reader: &mut Reader) -> ParseResult<QueryValue> {
+    try_literal("scheme", reader)?;
+    Ok(QueryValue::Scheme)
+}
+
+fn port_query(reader: &mut Reader) -> ParseResult<QueryValue> {
+    try_literal("port", reader)?;
+    Ok(QueryValue::Port)
 }
 
 fn header_query(reader: &mut Reader) -> ParseResult<QueryValue> {
@@ -72,6 +92,43 @@ fn query_param_query(reader: &mut Reader) -> ParseResult<QueryValue> {
     Ok(QueryValue::QueryParam { space0, name })
 }
 
+fn form_query(reader: &mut Reader) -> ParseResult<QueryValue> {
+    try_literal("form", reader)?;
+    let space0 = one_or_more_spaces(reader)?;
+    let name = quoted_template(reader).map_err(|e| e.non_recoverable())?;
+    Ok(QueryValue::Form { space0, name })
+}
+
+fn multipart_query(reader: &mut Reader) -> ParseResult<QueryValue> {
+    try_literal("multipart", reader)?;
+    let space0 = one_or_more_spaces(reader)?;
+    let name = quoted_template(reader).map_err(|e| e.non_recoverable())?;
+    let attribute = multipart_attribute(reader);
+    Ok(QueryValue::Multipart {
+        space0,
+        name,
+        attribute,
+    })
+}
+
+// can not fail
+fn multipart_attribute(reader: &mut Reader) -> Option<MultipartAttribute> {
+    let save = reader.state;
+    let Ok(space0) = one_or_more_spaces(reader) else {
+        reader.state = save;
+        return None;
+    };
+    let name = if try_literal("filename", reader).is_ok() {
+        MultipartAttributeName::Filename
+    } else if try_literal("contentType", reader).is_ok() {
+        MultipartAttributeName::ContentType
+    } else {
+        reader.state = save;
+        return None;
+    };
+    Some(MultipartAttribute { space0, name })
+}
+
 fn cookie_query(reader: &mut Reader) -> ParseResult<QueryValue> {
     try_literal("cookie", reader)?;
     let space0 = one_or_more_spaces(reader)?;
@@ -200,6 +257,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_url_queries() {
+        for (text, value) in [
+            ("url", QueryValue::Url),
+            ("host", QueryValue::Host),
+            ("scheme", QueryValue::Scheme),
+            ("port", QueryValue::Port),
+        ] {
+            let mut reader = Reader::new(text);
+            assert_eq!(query_value(&mut reader).unwrap(), value);
+            assert_eq!(reader.state.cursor, text.len());
+        }
+    }
+
+    #[test]
+    fn test_multipart_query() {
+        let mut reader = Reader::new("multipart \"avatar\" contentType ==");
+        let QueryValue::Multipart {
+            name, attribute, ..
+        } = query_value(&mut reader).unwrap()
+        else {
+            panic!("expected a multipart query");
+        };
+        assert_eq!(name.to_string(), "avatar");
+        assert_eq!(attribute.unwrap().name, MultipartAttributeName::ContentType);
+        assert_eq!(reader.state.cursor, 30);
+
+        // The spaces before the predicate aren't part of the query.
+        let mut reader = Reader::new("multipart \"avatar\" ==");
+        assert!(matches!(
+            query_value(&mut reader).unwrap(),
+            QueryValue::Multipart {
+                attribute: None,
+                ..
+            }
+        ));
+        assert_eq!(reader.state.cursor, 18);
+
+        let mut reader = Reader::new("form \"name\"");
+        assert!(matches!(
+            query_value(&mut reader).unwrap(),
+            QueryValue::Form { .. }
+        ));
+    }
+
     #[test]
     fn test_cookie_query() {
         let mut reader = Reader::new("cookie \"Foo[Domain]\"");
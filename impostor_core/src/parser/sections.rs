@@ -23,7 +23,7 @@ use crate::parser::predicate::predicate;
 use crate::parser::primitives::*;
 use crate::parser::query::query;
 use crate::parser::reader::Reader;
-use crate::parser::{key_string, ParseResult};
+use crate::parser::{key_string, options, ParseResult};
 
 pub fn request_sections(reader: &mut Reader) -> ParseResult<Vec<RequestSection>> {
     let sections = zero_or_more(request_section, reader)?;
@@ -43,6 +43,9 @@ fn request_section(reader: &mut Reader) -> ParseResult<RequestSection> {
     let value = match name.as_str() {
         "Captures" => section_value_captures(reader)?,
         "Asserts" => section_value_asserts(reader)?,
+        "Options" => section_value_options(reader)?,
+        "QueryStringParams" => RequestSectionValue::QueryParams(zero_or_more(key_value, reader)?),
+        "Cookies" => RequestSectionValue::Cookies(zero_or_more(key_value, reader)?),
         _ => {
             let inner = ParseError::ResponseSectionName { name: name.clone() };
             let pos = Pos::new(start.line, start.column + 1);
@@ -84,6 +87,11 @@ fn section_value_asserts(reader: &mut Reader) -> ParseResult<RequestSectionValue
     Ok(RequestSectionValue::Asserts(asserts))
 }
 
+fn section_value_options(reader: &mut Reader) -> ParseResult<RequestSectionValue> {
+    let options = zero_or_more(options::parse, reader)?;
+    Ok(RequestSectionValue::Options(options))
+}
+
 fn capture(reader: &mut Reader) -> ParseResult<Capture> {
     let line_terminators = optional_line_terminators(reader)?;
     let space0 = zero_or_more_spaces(reader)?;
@@ -258,6 +266,21 @@ mod tests {
         assert!(!error.recoverable);
     }
 
+    #[test]
+    fn test_query_params_and_cookies_sections() {
+        let mut reader =
+            Reader::new("[QueryStringParams]\nname: John\npage: 2\n[Cookies]\nid: 1\n");
+        let sections = request_sections(&mut reader).unwrap();
+        assert_eq!(sections.len(), 2);
+        let RequestSectionValue::QueryParams(params) = &sections[0].value else {
+            panic!("expected query params, got {:?}", sections[0].value);
+        };
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[1].key.to_string(), "page");
+        assert_eq!(params[1].value.to_string(), "2");
+        assert_eq!(sections[1].name(), "Cookies");
+    }
+
     #[test]
     fn test_capture() {
         let mut reader = Reader::new("url: header \"Location\"");
@@ -0,0 +1,641 @@
+/*
+ * Copyright (C) 2023 The Impostor Contributors
+ * Copyright (C) 2023 Orange
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *          http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ */
+
+//! Print an AST back to Impostor syntax.
+//!
+//! Every node keeps the whitespace, comments and escapes it was parsed from, so
+//! printing a parsed file gives back the same text. ASTs built by hand print
+//! as whatever their whitespace says, and parse back to the same AST as long
+//! as their strings are encoded the way the parser expects.
+
+use crate::ast::*;
+
+/// Print a complete Impostor file.
+pub fn print_impostor_file(file: &ImpostorFile) -> String {
+    let mut out = String::new();
+    file.print(&mut out);
+    out
+}
+
+/// Print a request on its own, as [`parse_request`](crate::parser::parse_request)
+/// reads it.
+pub fn print_request(request: &Request) -> String {
+    let mut out = String::new();
+    request.print(&mut out);
+    out
+}
+
+trait Print {
+    fn print(&self, out: &mut String);
+}
+
+impl<T: Print> Print for Vec<T> {
+    fn print(&self, out: &mut String) {
+        for item in self {
+            item.print(out);
+        }
+    }
+}
+
+impl<T: Print> Print for Option<T> {
+    fn print(&self, out: &mut String) {
+        if let Some(item) = self {
+            item.print(out);
+        }
+    }
+}
+
+impl Print for ImpostorFile {
+    fn print(&self, out: &mut String) {
+        self.entries.print(out);
+        self.line_terminators.print(out);
+    }
+}
+
+impl Print for Entry {
+    fn print(&self, out: &mut String) {
+        self.request.print(out);
+        self.proxy.print(out);
+        self.responses.print(out);
+    }
+}
+
+impl Print for Request {
+    fn print(&self, out: &mut String) {
+        self.line_terminators.print(out);
+        self.space0.print(out);
+        out.push_str(&self.method.0);
+        self.space1.print(out);
+        self.path.print(out);
+        self.line_terminator0.print(out);
+        self.headers.print(out);
+        self.sections.print(out);
+        self.body.print(out);
+    }
+}
+
+impl Print for Response {
+    fn print(&self, out: &mut String) {
+        self.line_terminators.print(out);
+        // The parser reads the spaces before the version into `space0` and
+        // the spaces between the version and the status into `space1`.
+        self.space0.print(out);
+        out.push_str(&self.version.to_string());
+        self.space1.print(out);
+        out.push_str(&self.status.to_string());
+        self.line_terminator0.print(out);
+        self.headers.print(out);
+        self.body.print(out);
+    }
+}
+
+impl Print for Proxy {
+    fn print(&self, out: &mut String) {
+        self.line_terminators.print(out);
+        self.space0.print(out);
+        out.push_str("PROXY");
+        self.space1.print(out);
+        self.url.print(out);
+        self.line_terminator0.print(out);
+        self.headers.print(out);
+    }
+}
+
+impl Print for KeyValue {
+    fn print(&self, out: &mut String) {
+        self.line_terminators.print(out);
+        self.space0.print(out);
+        self.key.print(out);
+        self.space1.print(out);
+        out.push(':');
+        self.space2.print(out);
+        self.value.print(out);
+        self.line_terminator0.print(out);
+    }
+}
+
+impl Print for RequestSection {
+    fn print(&self, out: &mut String) {
+        self.line_terminators.print(out);
+        self.space0.print(out);
+        out.push('[');
+        out.push_str(self.name());
+        out.push(']');
+        self.line_terminator0.print(out);
+        match &self.value {
+            RequestSectionValue::Captures(captures) => captures.print(out),
+            RequestSectionValue::Asserts(asserts) => asserts.print(out),
+            RequestSectionValue::Options(options) => options.print(out),
+            RequestSectionValue::QueryParams(values) | RequestSectionValue::Cookies(values) => {
+                values.print(out)
+            }
+        }
+    }
+}
+
+impl Print for Capture {
+    fn print(&self, out: &mut String) {
+        self.line_terminators.print(out);
+        self.space0.print(out);
+        self.name.print(out);
+        self.space1.print(out);
+        out.push(':');
+        self.space2.print(out);
+        self.query.print(out);
+        self.filters.print(out);
+        self.line_terminator0.print(out);
+    }
+}
+
+impl Print for Assert {
+    fn print(&self, out: &mut String) {
+        self.line_terminators.print(out);
+        self.space0.print(out);
+        self.query.print(out);
+        self.filters.print(out);
+        self.space1.print(out);
+        self.predicate.print(out);
+        self.line_terminator0.print(out);
+    }
+}
+
+impl Print for EntryOption {
+    fn print(&self, out: &mut String) {
+        self.line_terminators.print(out);
+        self.space0.print(out);
+        out.push_str(self.kind.name());
+        self.space1.print(out);
+        out.push(':');
+        self.space2.print(out);
+        let value = match &self.kind {
+            OptionKind::Delay(delay) => delay.to_string(),
+            OptionKind::Fault(fault) => fault.to_string(),
+            OptionKind::AfterLast(after_last) => after_last.to_string(),
+            OptionKind::Scenario(name) | OptionKind::State(name) | OptionKind::NextState(name) => {
+                name.clone()
+            }
+            OptionKind::Fallback(fallback) => fallback.to_string(),
+        };
+        out.push_str(&value);
+        self.line_terminator0.print(out);
+    }
+}
+
+impl Print for Query {
+    fn print(&self, out: &mut String) {
+        match &self.value {
+            QueryValue::Url => out.push_str("url"),
+            QueryValue::Host => out.push_str("host"),
+            QueryValue::Scheme => out.push_str("scheme"),
+            QueryValue::Port => out.push_str("port"),
+            QueryValue::Header { space0, name } => keyword("header", space0, name, out),
+            QueryValue::Cookie { space0, expr } => {
+                out.push_str("cookie");
+                space0.print(out);
+                out.push('"');
+                expr.name.print(out);
+                if let Some(attribute) = &expr.attribute {
+                    out.push('[');
+                    attribute.space0.print(out);
+                    out.push_str(&attribute.name.value());
+                    attribute.space1.print(out);
+                    out.push(']');
+                }
+                out.push('"');
+            }
+            QueryValue::Body => out.push_str("body"),
+            QueryValue::Xpath { space0, expr } => keyword("xpath", space0, expr, out),
+            QueryValue::Jsonpath { space0, expr } => keyword("jsonpath", space0, expr, out),
+            QueryValue::QueryParam { space0, name } => keyword("queryparam", space0, name, out),
+            QueryValue::Form { space0, name } => keyword("form", space0, name, out),
+            QueryValue::Multipart {
+                space0,
+                name,
+                attribute,
+            } => {
+                keyword("multipart", space0, name, out);
+                if let Some(attribute) = attribute {
+                    attribute.space0.print(out);
+                    out.push_str(attribute.name.value());
+                }
+            }
+            QueryValue::Regex { space0, value } => keyword("regex", space0, value, out),
+            QueryValue::Variable { space0, name } => keyword("variable", space0, name, out),
+            QueryValue::Bytes => out.push_str("bytes"),
+            QueryValue::Sha256 => out.push_str("sha256"),
+            QueryValue::Md5 => out.push_str("md5"),
+        }
+    }
+}
+
+impl Print for (Whitespace, Filter) {
+    fn print(&self, out: &mut String) {
+        let (space, filter) = self;
+        space.print(out);
+        match &filter.value {
+            FilterValue::Count => out.push_str("count"),
+            FilterValue::DaysAfterNow => out.push_str("daysAfterNow"),
+            FilterValue::DaysBeforeNow => out.push_str("daysBeforeNow"),
+            FilterValue::Decode { space0, encoding } => keyword("decode", space0, encoding, out),
+            FilterValue::Format { space0, fmt } => keyword("format", space0, fmt, out),
+            FilterValue::HtmlEscape => out.push_str("htmlEscape"),
+            FilterValue::HtmlUnescape => out.push_str("htmlUnescape"),
+            FilterValue::IgnoreCase => out.push_str("ignoreCase"),
+            FilterValue::JsonPath { space0, expr } => keyword("jsonpath", space0, expr, out),
+            FilterValue::MediaType => out.push_str("mediaType"),
+            FilterValue::Nth { space0, n } => {
+                out.push_str("nth");
+                space0.print(out);
+                out.push_str(&n.to_string());
+            }
+            FilterValue::Regex { space0, value } => keyword("regex", space0, value, out),
+            FilterValue::Replace {
+                space0,
+                old_value,
+                space1,
+                new_value,
+            } => {
+                keyword("replace", space0, old_value, out);
+                space1.print(out);
+                new_value.print(out);
+            }
+            FilterValue::Split { space0, sep } => keyword("split", space0, sep, out),
+            FilterValue::ToDate { space0, fmt } => keyword("toDate", space0, fmt, out),
+            FilterValue::ToInt => out.push_str("toInt"),
+            FilterValue::UrlDecode => out.push_str("urlDecode"),
+            FilterValue::UrlEncode => out.push_str("urlEncode"),
+            FilterValue::XPath { space0, expr } => keyword("xpath", space0, expr, out),
+        }
+    }
+}
+
+impl Print for Predicate {
+    fn print(&self, out: &mut String) {
+        if self.not {
+            out.push_str("not");
+            self.space0.print(out);
+        }
+        out.push_str(&self.predicate_func.value.name());
+        match &self.predicate_func.value {
+            PredicateFuncValue::Equal { space0, value, .. }
+            | PredicateFuncValue::NotEqual { space0, value, .. }
+            | PredicateFuncValue::GreaterThan { space0, value, .. }
+            | PredicateFuncValue::GreaterThanOrEqual { space0, value, .. }
+            | PredicateFuncValue::LessThan { space0, value, .. }
+            | PredicateFuncValue::LessThanOrEqual { space0, value, .. }
+            | PredicateFuncValue::StartWith { space0, value }
+            | PredicateFuncValue::EndWith { space0, value }
+            | PredicateFuncValue::Contain { space0, value }
+            | PredicateFuncValue::Include { space0, value }
+            | PredicateFuncValue::Match { space0, value }
+            | PredicateFuncValue::MatchSchema { space0, value } => {
+                space0.print(out);
+                value.print(out);
+            }
+            PredicateFuncValue::IncludeJson {
+                space0,
+                unordered,
+                value,
+            } => {
+                space0.print(out);
+                if let Some(space1) = unordered {
+                    out.push_str("unordered");
+                    space1.print(out);
+                }
+                value.print(out);
+            }
+            PredicateFuncValue::IsInteger
+            | PredicateFuncValue::IsFloat
+            | PredicateFuncValue::IsBoolean
+            | PredicateFuncValue::IsString
+            | PredicateFuncValue::IsCollection
+            | PredicateFuncValue::IsDate
+            | PredicateFuncValue::Exist
+            | PredicateFuncValue::IsEmpty => {}
+        }
+    }
+}
+
+impl Print for PredicateValue {
+    fn print(&self, out: &mut String) {
+        match self {
+            PredicateValue::Base64(base64) => base64.print(out),
+            PredicateValue::Bool(value) => out.push_str(&value.to_string()),
+            PredicateValue::Expression(expr) => expr.print(out),
+            PredicateValue::File(file) => file.print(out),
+            PredicateValue::Hex(hex) => out.push_str(&hex.to_string()),
+            PredicateValue::Json(value) => value.print(out),
+            PredicateValue::MultilineString(multiline) => multiline.print(out),
+            PredicateValue::Null => out.push_str("null"),
+            PredicateValue::Number(number) => out.push_str(&number.to_string()),
+            PredicateValue::Regex(regex) => regex.print(out),
+            PredicateValue::String(template) => template.print(out),
+        }
+    }
+}
+
+impl Print for Body {
+    fn print(&self, out: &mut String) {
+        self.line_terminators.print(out);
+        self.space0.print(out);
+        self.value.print(out);
+        self.line_terminator0.print(out);
+    }
+}
+
+impl Print for Bytes {
+    fn print(&self, out: &mut String) {
+        match self {
+            Bytes::Json(value) => value.print(out),
+            Bytes::Xml(value) => out.push_str(value),
+            Bytes::MultilineString(multiline) => multiline.print(out),
+            Bytes::OnelineString(template) => template.print(out),
+            Bytes::Base64(base64) => base64.print(out),
+            Bytes::File(file) => file.print(out),
+            Bytes::Hex(hex) => out.push_str(&hex.to_string()),
+        }
+    }
+}
+
+impl Print for MultilineString {
+    fn print(&self, out: &mut String) {
+        out.push_str("```");
+        match self {
+            MultilineString::OneLineText(template) => template.print(out),
+            MultilineString::Text(text)
+            | MultilineString::Json(text)
+            | MultilineString::Xml(text) => {
+                out.push_str(self.lang());
+                text.space.print(out);
+                text.newline.print(out);
+                text.value.print(out);
+            }
+            MultilineString::GraphQl(graphql) => {
+                out.push_str(self.lang());
+                graphql.space.print(out);
+                graphql.newline.print(out);
+                graphql.value.print(out);
+                if let Some(variables) = &graphql.variables {
+                    out.push_str("variables");
+                    variables.space.print(out);
+                    variables.value.print(out);
+                    variables.whitespace.print(out);
+                }
+            }
+        }
+        out.push_str("```");
+    }
+}
+
+impl Print for JsonValue {
+    fn print(&self, out: &mut String) {
+        match self {
+            JsonValue::Expression(expr) => expr.print(out),
+            JsonValue::Number(number) => out.push_str(number),
+            JsonValue::String(template) => template.print(out),
+            JsonValue::Boolean(value) => out.push_str(&value.to_string()),
+            JsonValue::List { space0, elements } => {
+                out.push('[');
+                out.push_str(space0);
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&element.space0);
+                    element.value.print(out);
+                    out.push_str(&element.space1);
+                }
+                out.push(']');
+            }
+            JsonValue::Object { space0, elements } => {
+                out.push('{');
+                out.push_str(space0);
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&element.space0);
+                    element.name.print(out);
+                    out.push_str(&element.space1);
+                    out.push(':');
+                    out.push_str(&element.space2);
+                    element.value.print(out);
+                    out.push_str(&element.space3);
+                }
+                out.push('}');
+            }
+            JsonValue::Null => out.push_str("null"),
+        }
+    }
+}
+
+impl Print for Base64 {
+    fn print(&self, out: &mut String) {
+        out.push_str("base64,");
+        self.space0.print(out);
+        out.push_str(&self.encoded);
+        self.space1.print(out);
+        out.push(';');
+    }
+}
+
+impl Print for File {
+    fn print(&self, out: &mut String) {
+        out.push_str("file,");
+        self.space0.print(out);
+        for c in self.filename.value.chars() {
+            if !(c.is_alphanumeric() || ['.', '/', '_', '-', ':'].contains(&c)) {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        self.space1.print(out);
+        out.push(';');
+    }
+}
+
+impl Print for RegexValue {
+    fn print(&self, out: &mut String) {
+        match self {
+            RegexValue::Template(template) => template.print(out),
+            RegexValue::Regex(regex) => regex.print(out),
+        }
+    }
+}
+
+impl Print for Regex {
+    fn print(&self, out: &mut String) {
+        out.push('/');
+        out.push_str(&self.inner.to_string().replace('/', "\\/"));
+        out.push('/');
+    }
+}
+
+impl Print for Template {
+    fn print(&self, out: &mut String) {
+        if let Some(delimiter) = self.delimiter {
+            out.push(delimiter);
+        }
+        for element in &self.elements {
+            match element {
+                TemplateElement::String { encoded, .. } => out.push_str(encoded),
+                TemplateElement::Expression(expr) => expr.print(out),
+            }
+        }
+        if let Some(delimiter) = self.delimiter {
+            out.push(delimiter);
+        }
+    }
+}
+
+impl Print for Expr {
+    fn print(&self, out: &mut String) {
+        out.push_str("{{");
+        self.space0.print(out);
+        out.push_str(&self.variable.name);
+        self.space1.print(out);
+        out.push_str("}}");
+    }
+}
+
+impl Print for LineTerminator {
+    fn print(&self, out: &mut String) {
+        self.space0.print(out);
+        if let Some(comment) = &self.comment {
+            out.push('#');
+            out.push_str(&comment.value);
+        }
+        self.newline.print(out);
+    }
+}
+
+impl Print for Whitespace {
+    fn print(&self, out: &mut String) {
+        out.push_str(&self.value);
+    }
+}
+
+/// Print a keyword followed by its argument, e.g. `header "Content-Type"`.
+fn keyword(name: &str, space: &Whitespace, argument: &impl Print, out: &mut String) {
+    out.push_str(name);
+    space.print(out);
+    argument.print(out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_impostor_file, parse_request};
+
+    fn assert_round_trip(s: &str) {
+        let file = parse_impostor_file(s).unwrap();
+        assert_eq!(print_impostor_file(&file), s);
+    }
+
+    #[test]
+    fn test_print_request() {
+        let s = "GET /orders/{{ id }}  # an order\n\
+                 Accept: application/json\n\
+                 [Asserts]\n\
+                 header \"x-foo\" not   contains \"b\\\"ar\"\n\
+                 queryparam \"page\" toInt >= 2\n\
+                 header \"Accept\" mediaType  ignoreCase contains \"json\"\n\
+                 cookie \"session[Max-Age]\" exists\n\
+                 body matches /a\\/b/\n";
+        let request = parse_request(s).unwrap();
+        assert_eq!(print_request(&request), s);
+    }
+
+    #[test]
+    fn test_print_bodies() {
+        assert_round_trip("POST /a\n{ \"a\" : [1, 2.50, true, null, \"{{x}}\"] }\nHTTP 200\n");
+        assert_round_trip("GET /a\nHTTP 200\n```json\n{\"a\": 1}\n```\n");
+        assert_round_trip("GET /a\nHTTP/1.1   200\n`hello \\`world\\``\n\n");
+        assert_round_trip("GET /a\nHTTP 200\nbase64, aGVsbG8= ;\nGET /b\nHTTP 200\nhex,00ff;\n");
+        assert_round_trip("GET /a\nHTTP 200\nfile, data/a\\ b.bin;\n");
+    }
+
+    #[test]
+    fn test_print_proxy() {
+        assert_round_trip(
+            "GET /users/:id\n\
+             PROXY  http://localhost:8080/api # the real one\n\
+             Authorization: Bearer test\n\
+             GET /health\n\
+             HTTP 200\n",
+        );
+    }
+
+    #[test]
+    fn test_print_query_params_and_cookies() {
+        assert_round_trip(
+            "GET /users\n\
+             [QueryStringParams]\n\
+             name: John # the first one\n\
+             [Cookies]\n\
+             session: abc\n\
+             HTTP 200\n",
+        );
+    }
+
+    #[test]
+    fn test_print_json_predicates() {
+        assert_round_trip(
+            "POST /users\n\
+             [Asserts]\n\
+             body == {\"name\": \"Ada\", \"tags\": [1, 2]}\n\
+             jsonpath \"$.tags\" == [ 1,2 ]\n\
+             body includesJson {\"name\": \"Ada\"}\n\
+             body not includesJson unordered  {\"tags\": [2]}\n\
+             body matchesSchema file,schemas/user.json;\n\
+             body matchesSchema {\"type\": \"object\"}\n\
+             form \"name\" == \"Ada\"\n\
+             multipart \"avatar\"  filename endsWith \".png\"\n\
+             multipart \"avatar\" contentType == \"image/png\"\n\
+             multipart \"note\" exists\n\
+             url startsWith \"http://api.\"\n\
+             host == \"api.example.com\"\n\
+             scheme == \"http\"\n\
+             port == 8080\n\
+             HTTP 200\n",
+        );
+    }
+
+    #[test]
+    fn test_print_options() {
+        assert_round_trip(
+            "# comment\n\
+             GET /a\n\
+             [Options]\n\
+             delay: normal(250ms, 50ms)\n\
+             fault: truncated-body\n\
+             after-last: cycle\n\
+             scenario: checkout\n\
+             state: started\n\
+             next-state: paid\n\
+             fallback: true\n\
+             HTTP 200\n\
+             HTTP 404\n\n",
+        );
+    }
+
+    #[test]
+    fn test_print_tour() {
+        assert_round_trip(include_str!("../../samples/tour.impostor"));
+    }
+}
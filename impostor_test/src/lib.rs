@@ -0,0 +1,246 @@
+//! Serve Impostor mocks from Rust tests.
+//!
+//! ```no_run
+//! use std::str::FromStr;
+//!
+//! use impostor_test::MockServer;
+//!
+//! #[tokio::test]
+//! async fn test_checkout() {
+//!     let server = MockServer::from_str("POST /payments\nHTTP 201\n").unwrap();
+//!
+//!     // Point the code under test at `server.base_url()`, then:
+//!     server.assert_received("POST /payments", 1);
+//! }
+//! ```
+
+use std::{
+    net::{SocketAddr, TcpListener},
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+};
+
+use impostor_compiler_axum::{Error, JournalEntry, Mocks, State};
+use tokio::task::JoinHandle;
+
+/// A mock server listening on an ephemeral port on localhost.
+///
+/// The server runs as a task on the current Tokio runtime, so it has to be
+/// created from within one, e.g. in a `#[tokio::test]`. It stops accepting
+/// connections, and closes the ones it has, when it's dropped.
+pub struct MockServer {
+    address: SocketAddr,
+    mocks: Mocks,
+    server: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Compile the Impostor file at `path` and start serving it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a Tokio runtime.
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<MockServer, Error> {
+        MockServer::start(Mocks::from_file(path)?)
+    }
+
+    fn start(mocks: Mocks) -> Result<MockServer, Error> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).map_err(Error::Io)?;
+        listener.set_nonblocking(true).map_err(Error::Io)?;
+        let address = listener.local_addr().map_err(Error::Io)?;
+        let listener = tokio::net::TcpListener::from_std(listener).map_err(Error::Io)?;
+
+        let server = tokio::spawn(impostor_compiler_axum::serve(listener, mocks.router()));
+        Ok(MockServer {
+            address,
+            mocks,
+            server,
+        })
+    }
+
+    /// The address the server is listening on.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The URL of the server, without a trailing slash, e.g.
+    /// `http://127.0.0.1:41203`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// The URL of `path` on the server.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url(), path)
+    }
+
+    /// Get the state of the mocks, e.g. to add entries while the server is
+    /// running.
+    pub fn state(&self) -> Arc<State> {
+        self.mocks.state()
+    }
+
+    /// Get the most recent requests received by the server, oldest first.
+    pub fn requests(&self) -> Vec<JournalEntry> {
+        self.mocks.state().journal()
+    }
+
+    /// Find the requests received by the server that match a request
+    /// definition in Impostor syntax, i.e. a request without a response.
+    pub fn verify(&self, definition: &str) -> Result<Vec<JournalEntry>, Error> {
+        self.mocks.state().verify(definition)
+    }
+
+    /// Check that the server received exactly `count` requests matching a
+    /// request definition in Impostor syntax.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the definition is invalid, or if the number of matching
+    /// requests is different. The message lists every request the server
+    /// received, to help figure out what went wrong.
+    pub fn assert_received(&self, definition: &str, count: usize) {
+        let matched = match self.verify(definition) {
+            Ok(matched) => matched,
+            Err(e) => panic!("invalid request definition: {}", e),
+        };
+        if matched.len() == count {
+            return;
+        }
+
+        let received: Vec<String> = self
+            .requests()
+            .iter()
+            .map(|request| format!("  {} {} -> {}", request.method, request.uri, request.status))
+            .collect();
+        panic!(
+            "expected {} request(s) matching:\n{}\nbut {} matched. Received:\n{}",
+            count,
+            definition.trim_end(),
+            matched.len(),
+            if received.is_empty() {
+                "  nothing".to_string()
+            } else {
+                received.join("\n")
+            }
+        );
+    }
+
+    /// Reset scenarios, hit counts and the journal, e.g. between two parts of
+    /// a test.
+    pub fn reset(&self) {
+        self.mocks.state().reset();
+    }
+}
+
+impl FromStr for MockServer {
+    type Err = Error;
+
+    /// Compile the contents of an Impostor file and start serving them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a Tokio runtime.
+    fn from_str(contents: &str) -> Result<MockServer, Error> {
+        MockServer::start(Mocks::from_str(contents)?)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+
+    /// Send a request without a body and return the response's status line.
+    async fn call(address: SocketAddr, method: &str, path: &str) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(address).await?;
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            method, path, address
+        );
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok(response.lines().next().unwrap_or_default().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_mock_server() {
+        let server = MockServer::from_str("GET /hello\nHTTP 200\n`hello`\n").unwrap();
+        assert!(server.base_url().starts_with("http://127.0.0.1:"));
+        assert_eq!(
+            server.url("/hello"),
+            format!("http://{}/hello", server.address())
+        );
+
+        let status = call(server.address(), "GET", "/hello?name=world").await;
+        assert_eq!(status.unwrap(), "HTTP/1.1 200 OK");
+        let status = call(server.address(), "GET", "/nope").await;
+        assert_eq!(status.unwrap(), "HTTP/1.1 404 Not Found");
+
+        assert_eq!(server.requests().len(), 2);
+        server.assert_received("GET /hello", 1);
+        server.assert_received("GET /hello\n[Asserts]\nqueryparam \"name\" == \"you\"\n", 0);
+        assert!(server.verify("GET").is_err());
+
+        server.reset();
+        server.assert_received("GET /hello", 0);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "GET /nope -> 404")]
+    async fn test_assert_received_lists_requests() {
+        let server = MockServer::from_str("GET /hello\nHTTP 200\n").unwrap();
+        call(server.address(), "GET", "/nope").await.unwrap();
+        server.assert_received("GET /hello", 1);
+    }
+
+    #[tokio::test]
+    async fn test_shut_down_on_drop() {
+        let server = MockServer::from_str("GET /hello\nHTTP 200\n").unwrap();
+        let address = server.address();
+        drop(server);
+        // Let the aborted server task finish, so that the listener is closed.
+        tokio::task::yield_now().await;
+
+        assert!(call(address, "GET", "/hello").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_close_keep_alive_connections_on_drop() {
+        let server = MockServer::from_str("GET /hello\nHTTP 200\n`hello`\n").unwrap();
+        let mut stream = TcpStream::connect(server.address()).await.unwrap();
+        let request = format!("GET /hello HTTP/1.1\r\nHost: {}\r\n\r\n", server.address());
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        // Read the whole response, which ends with its body, and keep the
+        // connection open.
+        let mut response = vec![];
+        while !response.ends_with(b"hello") {
+            let mut buffer = [0; 1024];
+            let read = stream.read(&mut buffer).await.unwrap();
+            assert_ne!(read, 0, "the connection closed too early");
+            response.extend_from_slice(&buffer[..read]);
+        }
+
+        drop(server);
+        let mut buffer = [0; 1024];
+        let read =
+            tokio::time::timeout(std::time::Duration::from_secs(5), stream.read(&mut buffer))
+                .await
+                .expect("the connection is still open");
+        assert!(read.map_or(true, |read| read == 0));
+    }
+}